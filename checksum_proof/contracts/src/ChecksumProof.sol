@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import {ISP1Verifier} from "@sp1-contracts/ISP1Verifier.sol";
+
+/// @notice The public values committed by the checksum_proof program. Must match
+///         `ChecksumPublicValues` in crates/doprf/src/public_values.rs.
+struct ChecksumPublicValues {
+    // The compressed checksum query.
+    bytes32 query;
+    bytes32 randomModifier;
+    bytes32 checksumSum;
+    // The hash of the active security key the checksum was computed against.
+    bytes32 keyserverCommitmentHash;
+}
+
+/// @title ChecksumProof.
+/// @notice Verifies proofs that the active security checksum query was computed from the
+///         committed inputs.
+contract ChecksumProof {
+    /// @notice The address of the SP1 verifier contract.
+    /// @dev This can either be a specific SP1Verifier for a specific version, or the
+    ///      SP1VerifierGateway which can be used to verify proofs for any version of SP1.
+    ///      For the list of supported verifiers on each chain, see:
+    ///      https://github.com/succinctlabs/sp1-contracts/tree/main/contracts/deployments
+    address public verifier;
+
+    /// @notice The verification key for the checksum_proof program.
+    bytes32 public checksumProgramVKey;
+
+    constructor(address _verifier, bytes32 _checksumProgramVKey) {
+        verifier = _verifier;
+        checksumProgramVKey = _checksumProgramVKey;
+    }
+
+    /// @notice The entrypoint for verifying a proof of the checksum_proof program.
+    /// @param _publicValues The encoded public values.
+    /// @param _proofBytes The encoded proof.
+    function verifyChecksumProof(bytes calldata _publicValues, bytes calldata _proofBytes)
+        public
+        view
+        returns (ChecksumPublicValues memory)
+    {
+        ISP1Verifier(verifier).verifyProof(checksumProgramVKey, _publicValues, _proofBytes);
+        return abi.decode(_publicValues, (ChecksumPublicValues));
+    }
+}
@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import {Test} from "forge-std/Test.sol";
+import {stdJson} from "forge-std/StdJson.sol";
+import {ChecksumProof, ChecksumPublicValues} from "../src/ChecksumProof.sol";
+import {SP1VerifierGateway} from "@sp1-contracts/SP1VerifierGateway.sol";
+import {SP1Verifier as SP1VerifierGroth16} from "@sp1-contracts/v3.0.0/SP1VerifierGroth16.sol";
+import {SP1Verifier as SP1VerifierPlonk} from "@sp1-contracts/v3.0.0/SP1VerifierPlonk.sol";
+
+contract ChecksumProofTest is Test {
+    /// @dev The public values encoded by the tests in lib/src/lib.rs.
+    bytes constant PUBLIC_VALUES =
+        hex"5555555555555555555555555555555555555555555555555555555555555555"
+        hex"3333333333333333333333333333333333333333333333333333333333333333"
+        hex"4444444444444444444444444444444444444444444444444444444444444444"
+        hex"6666666666666666666666666666666666666666666666666666666666666666";
+
+    address verifier;
+    ChecksumProof public target;
+
+    function setUp() public {
+        verifier = address(new SP1VerifierGateway(address(1)));
+        target = new ChecksumProof(verifier, bytes32(uint256(1)));
+    }
+
+    /// @dev A bytes32 with every byte set to `b`.
+    function repeated(uint8 b) internal pure returns (bytes32) {
+        return bytes32(uint256(b) * (type(uint256).max / 0xff));
+    }
+
+    function test_DecodesPublicValues() public {
+        vm.mockCall(verifier, abi.encodeWithSelector(SP1VerifierGateway.verifyProof.selector), abi.encode(true));
+
+        ChecksumPublicValues memory values = target.verifyChecksumProof(PUBLIC_VALUES, new bytes(32));
+        assertEq(values.query, repeated(0x55));
+        assertEq(values.randomModifier, repeated(0x33));
+        assertEq(values.checksumSum, repeated(0x44));
+        assertEq(values.keyserverCommitmentHash, repeated(0x66));
+    }
+
+    function testFail_InvalidChecksumProofProof() public view {
+        // Create a fake proof.
+        bytes memory fakeProof = new bytes(32);
+
+        target.verifyChecksumProof(PUBLIC_VALUES, fakeProof);
+    }
+}
+
+/// @dev A fixture written by `--evm` in script/src/bin/main.rs.
+struct SP1ProofFixtureJson {
+    bytes proof;
+    bytes publicValues;
+    bytes32 vkey;
+}
+
+/// @notice Verifies the fixtures written by the script with the real SP1 verifiers. A test is
+///         skipped until its proof system's fixture has been generated.
+contract ChecksumProofFixtureTest is Test {
+    using stdJson for string;
+
+    function loadFixture(string memory system) internal returns (bool found, SP1ProofFixtureJson memory fixture) {
+        string memory path = string.concat(vm.projectRoot(), "/src/fixtures/", system, "-fixture.json");
+        if (!vm.exists(path)) {
+            return (false, fixture);
+        }
+        string memory json = vm.readFile(path);
+        fixture.proof = json.readBytes(".proof");
+        fixture.publicValues = json.readBytes(".publicValues");
+        fixture.vkey = json.readBytes32(".vkey");
+        return (true, fixture);
+    }
+
+    function checkFixture(string memory system, address verifier) internal {
+        (bool found, SP1ProofFixtureJson memory fixture) = loadFixture(system);
+        if (!found) {
+            vm.skip(true);
+            return;
+        }
+        ChecksumProof target = new ChecksumProof(verifier, fixture.vkey);
+
+        ChecksumPublicValues memory values = target.verifyChecksumProof(fixture.publicValues, fixture.proof);
+        assertEq(abi.encode(values), fixture.publicValues);
+
+        // The proof does not vouch for any other public values
+        bytes memory tampered = bytes.concat(fixture.publicValues);
+        tampered[tampered.length - 1] ^= bytes1(0x01);
+        vm.expectRevert();
+        target.verifyChecksumProof(tampered, fixture.proof);
+    }
+
+    function test_VerifiesGroth16Fixture() public {
+        checkFixture("groth16", address(new SP1VerifierGroth16()));
+    }
+
+    function test_VerifiesPlonkFixture() public {
+        checkFixture("plonk", address(new SP1VerifierPlonk()));
+    }
+}
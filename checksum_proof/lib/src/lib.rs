@@ -1,22 +1,45 @@
-use alloy_sol_types::sol;
+//! The public values committed by the checksum_proof program.
 
-sol! {
-    /// The public values encoded as a struct that can be easily deserialized inside Solidity.
-    struct PublicValuesStruct {
-        uint32 n;
-        uint32 a;
-        uint32 b;
+pub use doprf::public_values::ChecksumPublicValues;
+
+#[cfg(test)]
+mod tests {
+    use alloy_sol_types::SolType;
+
+    use super::*;
+
+    /// The encoding of [`sample`], which `contracts/test/ChecksumProof.t.sol` decodes too.
+    const ENCODED: &str = concat!(
+        "5555555555555555555555555555555555555555555555555555555555555555",
+        "3333333333333333333333333333333333333333333333333333333333333333",
+        "4444444444444444444444444444444444444444444444444444444444444444",
+        "6666666666666666666666666666666666666666666666666666666666666666",
+    );
+
+    fn sample() -> ChecksumPublicValues {
+        ChecksumPublicValues {
+            query: [0x55; 32].into(),
+            randomModifier: [0x33; 32].into(),
+            checksumSum: [0x44; 32].into(),
+            keyserverCommitmentHash: [0x66; 32].into(),
+        }
+    }
+
+    #[test]
+    fn encodes_as_the_contract_decodes() {
+        assert_eq!(
+            hex::encode(ChecksumPublicValues::abi_encode(&sample())),
+            ENCODED
+        );
     }
-}
 
-/// Compute the n'th fibonacci number (wrapping around on overflows), using normal Rust code.
-pub fn fibonacci(n: u32) -> (u32, u32) {
-    let mut a = 0u32;
-    let mut b = 1u32;
-    for _ in 0..n {
-        let c = a.wrapping_add(b);
-        a = b;
-        b = c;
+    #[test]
+    fn decodes_what_it_encodes() {
+        let encoded = hex::decode(ENCODED).unwrap();
+        assert_eq!(
+            ChecksumPublicValues::abi_decode(&encoded, true).unwrap(),
+            sample()
+        );
+        assert!(ChecksumPublicValues::abi_decode(&encoded[..encoded.len() - 1], true).is_err());
     }
-    (a, b)
 }
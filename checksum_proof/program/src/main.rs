@@ -6,7 +6,7 @@
 sp1_zkvm::entrypoint!(main);
 
 use alloy_sol_types::SolType;
-use fibonacci_lib::{fibonacci, PublicValuesStruct};
+use checksum_proof_lib::ChecksumPublicValues;
 use curve25519_dalek::ristretto::{RistrettoPoint, CompressedRistretto};
 use curve25519_dalek::scalar::Scalar;
 use doprf::active_security::ActiveSecurityKey;
@@ -33,6 +33,13 @@ pub fn main() {
     let x_0 = checksum * verification_factor_0.invert();
 
     let query = Query::from_rp(x_0 * blinding_factor);
-    // Commit the compressed hash to the zkVM for public verification.
-    sp1_zkvm::io::commit::<Query>(&query);
+    // Commit the compressed query, along with the inputs used, which the aggregation program
+    // checks against the hash proof's.
+    let public_values = ChecksumPublicValues {
+        query: (*query.as_bytes()).into(),
+        randomModifier: hashed_concat_quries_bytes.into(),
+        checksumSum: sum_bytes.into(),
+        keyserverCommitmentHash: active_security_key.commitment_hash().into(),
+    };
+    sp1_zkvm::io::commit_slice(&ChecksumPublicValues::abi_encode(&public_values));
 }
@@ -1,35 +1,78 @@
-//! An end-to-end example of using the SP1 SDK to generate a proof of a program that can be executed
-//! or have a core proof generated.
+//! Runs the checksum_proof program over the checksum of a FASTA file's windows, computed the
+//! same way `QueryStateSet::from_iter` computes it for screening.
 //!
-//! You can run this script using the following command:
+//! You can run this script using the following commands:
 //! ```shell
-//! RUST_LOG=info cargo run --release -- --execute
-//! ```
-//! or
-//! ```shell
-//! RUST_LOG=info cargo run --release -- --prove
+//! RUST_LOG=info cargo run --release -- --fasta order.fasta --active-security-key ask.json --execute
+//! RUST_LOG=info cargo run --release -- --fasta order.fasta --active-security-key ask.json --prove
+//! RUST_LOG=info cargo run --release -- --fasta order.fasta --active-security-key ask.json --evm --system groth16
 //! ```
+//!
+//! `--evm` also writes a fixture to `../contracts/src/fixtures` for the Solidity tests.
+
+use std::path::{Path, PathBuf};
 
 use alloy_sol_types::SolType;
-use clap::Parser;
-use fibonacci_lib::PublicValuesStruct;
-use sp1_sdk::{include_elf, ProverClient, SP1Stdin};
+use checksum_proof_lib::ChecksumPublicValues;
+use clap::{ArgGroup, Parser, ValueEnum};
+use doprf::active_security::ActiveSecurityKey;
+use doprf::prf::QueryStateSet;
+use doprf_client::windows::Windows;
+use quickdna::{BaseSequence, DnaSequence, FastaParseSettings, FastaParser, NucleotideAmbiguous};
+use serde::{Deserialize, Serialize};
+use shared_types::hash::HashSpec;
+use sp1_sdk::{include_elf, HashableKey, ProverClient, SP1ProofWithPublicValues, SP1VerifyingKey};
 
 /// The ELF (executable and linkable format) file for the Succinct RISC-V zkVM.
-pub const FIBONACCI_ELF: &[u8] = include_elf!("fibonacci-program");
+pub const CHECKSUM_ELF: &[u8] = include_elf!("checksum_proof_program");
 
 /// The arguments for the command.
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
+#[clap(group(ArgGroup::new("mode").required(true).args(["execute", "prove", "evm"])))]
 struct Args {
+    /// The FASTA file whose windows are checksummed.
+    #[clap(long)]
+    fasta: PathBuf,
+
+    /// A JSON file holding the `ActiveSecurityKey` of the keyservers the order is screened by.
+    #[clap(long)]
+    active_security_key: PathBuf,
+
+    /// A JSON file holding the `HashSpec` to window with. Defaults to normal-size CECH DNA windows.
+    #[clap(long)]
+    hash_spec: Option<PathBuf>,
+
+    /// Execute the program without proving it.
     #[clap(long)]
     execute: bool,
 
+    /// Generate and verify a compressed proof.
     #[clap(long)]
     prove: bool,
 
-    #[clap(long, default_value = "20")]
-    n: u32,
+    /// Generate an EVM-compatible proof, and write it as a Solidity test fixture.
+    #[clap(long)]
+    evm: bool,
+
+    #[clap(long, value_enum, default_value = "groth16")]
+    system: ProofSystem,
+}
+
+/// Enum representing the available proof systems
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+enum ProofSystem {
+    Plonk,
+    Groth16,
+}
+
+/// A fixture that can be used to test the verification of SP1 zkVM proofs inside Solidity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SP1ChecksumProofFixture {
+    vkey: String,
+    public_values: String,
+    proof: String,
 }
 
 fn main() {
@@ -39,53 +82,116 @@ fn main() {
     // Parse the command line arguments.
     let args = Args::parse();
 
-    if args.execute == args.prove {
-        eprintln!("Error: You must specify either --execute or --prove");
-        std::process::exit(1);
-    }
+    let windows = read_windows(&args.fasta, args.hash_spec.as_deref());
+    let active_security_key: ActiveSecurityKey = read_json(&args.active_security_key);
+    let required_keyholders = active_security_key.supported_quorum() as usize;
+    println!("Windows: {}", windows.len());
+
+    let (_, _, inputs) = QueryStateSet::prepare(windows, required_keyholders, active_security_key);
+    let expected_inputs = inputs.checksum_inputs();
+    let expected_key = inputs.active_security_key.commitment_hash();
+    let stdin = inputs.stdin();
 
     // Setup the prover client.
     let client = ProverClient::new();
 
-    // Setup the inputs.
-    let mut stdin = SP1Stdin::new();
-    stdin.write(&args.n);
-
-    println!("n: {}", args.n);
-
     if args.execute {
         // Execute the program
-        let (output, report) = client.execute(FIBONACCI_ELF, stdin).run().unwrap();
+        let (output, report) = client.execute(CHECKSUM_ELF, stdin).run().unwrap();
         println!("Program executed successfully.");
 
         // Read the output.
-        let decoded = PublicValuesStruct::abi_decode(output.as_slice(), true).unwrap();
-        let PublicValuesStruct { n, a, b } = decoded;
-        println!("n: {}", n);
-        println!("a: {}", a);
-        println!("b: {}", b);
-
-        let (expected_a, expected_b) = fibonacci_lib::fibonacci(n);
-        assert_eq!(a, expected_a);
-        assert_eq!(b, expected_b);
-        println!("Values are correct!");
+        let decoded = ChecksumPublicValues::abi_decode(output.as_slice(), true).unwrap();
+        println!("Public values: {:?}", decoded);
+        assert_eq!(decoded.checksum_inputs(), expected_inputs, "checksum inputs mismatch");
+        assert_eq!(decoded.keyserverCommitmentHash.0, expected_key, "active security key mismatch");
 
         // Record the number of cycles executed.
         println!("Number of cycles: {}", report.total_instruction_count());
-    } else {
-        // Setup the program for proving.
-        let (pk, vk) = client.setup(FIBONACCI_ELF);
+        return;
+    }
 
+    // Setup the program for proving.
+    let (pk, vk) = client.setup(CHECKSUM_ELF);
+
+    if args.prove {
         // Generate the proof
         let proof = client
             .prove(&pk, stdin)
+            .compressed()
             .run()
             .expect("failed to generate proof");
-
         println!("Successfully generated proof!");
 
         // Verify the proof.
         client.verify(&proof, &vk).expect("failed to verify proof");
         println!("Successfully verified proof!");
+    } else {
+        println!("Proof System: {:?}", args.system);
+
+        // Generate the proof based on the selected proof system.
+        let proof = match args.system {
+            ProofSystem::Plonk => client.prove(&pk, stdin).plonk().run(),
+            ProofSystem::Groth16 => client.prove(&pk, stdin).groth16().run(),
+        }
+        .expect("failed to generate proof");
+
+        create_proof_fixture(&proof, &vk, args.system);
+    }
+}
+
+/// Windows every record of the FASTA file at `path`, in order.
+fn read_windows(path: &Path, hash_spec: Option<&Path>) -> Vec<(doprf::tagged::HashTag, String)> {
+    let hash_spec = hash_spec.map_or_else(HashSpec::dna_normal_cech, read_json);
+    let fasta = std::fs::read_to_string(path).expect("failed to read FASTA file");
+    let parser_settings = FastaParseSettings::new()
+        .concatenate_headers(true)
+        .allow_preceding_comment(false);
+    let fasta = FastaParser::<DnaSequence<NucleotideAmbiguous>>::new(parser_settings)
+        .parse_str(&fasta)
+        .expect("failed to parse FASTA file");
+
+    let mut windows = Vec::new();
+    for record in &fasta.records {
+        let dna = record.contents.as_slice().iter().copied();
+        windows.extend(Windows::from_dna(dna, &hash_spec).expect("failed to window FASTA record"));
     }
+    windows
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> T {
+    let file = std::fs::read(path).unwrap_or_else(|e| panic!("failed to read {path:?}: {e}"));
+    serde_json::from_slice(&file).unwrap_or_else(|e| panic!("failed to parse {path:?}: {e}"))
+}
+
+/// Create a fixture for the given proof.
+fn create_proof_fixture(
+    proof: &SP1ProofWithPublicValues,
+    vk: &SP1VerifyingKey,
+    system: ProofSystem,
+) {
+    // Deserialize the public values.
+    let bytes = proof.public_values.as_slice();
+    let public_values = ChecksumPublicValues::abi_decode(bytes, false).unwrap();
+    println!("Decoded Public Values: {:?}", public_values);
+
+    // Create the testing fixture so we can test things end-to-end.
+    let fixture = SP1ChecksumProofFixture {
+        vkey: vk.bytes32().to_string(),
+        public_values: format!("0x{}", hex::encode(bytes)),
+        proof: format!("0x{}", hex::encode(proof.bytes())),
+    };
+
+    println!("Verification Key: {}", fixture.vkey);
+    println!("Public Values: {}", fixture.public_values);
+    println!("Proof Bytes: {}", fixture.proof);
+
+    // Save the fixture to a file.
+    let fixture_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../contracts/src/fixtures");
+    std::fs::create_dir_all(&fixture_path).expect("failed to create fixture path");
+    std::fs::write(
+        fixture_path.join(format!("{:?}-fixture.json", system).to_lowercase()),
+        serde_json::to_string_pretty(&fixture).unwrap(),
+    )
+    .expect("failed to write fixture");
 }
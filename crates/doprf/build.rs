@@ -1,3 +1,132 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+/// The SP1 programs that `proof_backend` embeds, as (project directory, program package).
+#[cfg(feature = "zk")]
+const PROGRAMS: [(&str, &str); 3] = [
+    ("hash_proof", "hash_proof_program"),
+    ("checksum_proof", "checksum_proof_program"),
+    ("verification_proof", "verification_proof_program"),
+];
+
 fn main() {
-    // sp1_build::build_program("../../hash_proof/program");
+    // The programs are rebuilt whenever their sources change, and `sp1_build` exports the path of
+    // each ELF as `SP1_ELF_<package>` for `include_elf!`. With `SP1_SKIP_PROGRAM_BUILD` set, the
+    // ELFs committed to each project's `elf` directory are embedded instead, after checking that
+    // they were built from the current sources.
+    #[cfg(feature = "zk")]
+    for (project, package) in PROGRAMS {
+        let program_dir = format!("../../{project}/program");
+        if skip_program_build() {
+            committed::use_committed_elf(project, package, &program_dir);
+        } else {
+            sp1_build::build_program(&program_dir);
+        }
+    }
+}
+
+#[cfg(feature = "zk")]
+fn skip_program_build() -> bool {
+    println!("cargo:rerun-if-env-changed=SP1_SKIP_PROGRAM_BUILD");
+    std::env::var("SP1_SKIP_PROGRAM_BUILD").is_ok_and(|v| v.eq_ignore_ascii_case("true"))
+}
+
+#[cfg(feature = "zk")]
+mod committed {
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use sha3::{Digest, Sha3_256};
+
+    pub const ELF_NAME: &str = "riscv32im-succinct-zkvm-elf";
+    /// Holds the digest of the sources the committed ELF was built from, next to the ELF.
+    pub const DIGEST_NAME: &str = "source-digest";
+
+    /// Points `SP1_ELF_<package>` at the committed ELF of `project`, failing the build if the
+    /// ELF is missing or was built from other sources than the ones in the tree.
+    pub fn use_committed_elf(project: &str, package: &str, program_dir: &str) {
+        let elf_dir = Path::new("../../").join(project).join("elf");
+        let elf = elf_dir.join(ELF_NAME);
+        let digest_file = elf_dir.join(DIGEST_NAME);
+        println!("cargo:rerun-if-changed={}", elf.display());
+        println!("cargo:rerun-if-changed={}", digest_file.display());
+
+        let expected = source_digest(program_dir);
+        let committed = fs::read_to_string(&digest_file).unwrap_or_default();
+        if committed.trim() != expected {
+            panic!(
+                "SP1_SKIP_PROGRAM_BUILD is set, but {elf} was not built from the current sources \
+                 of {package}. Build without SP1_SKIP_PROGRAM_BUILD, or rebuild the ELF with \
+                 `cargo prove build --output-directory ../elf` in {program_dir} and write \
+                 {expected} to {digest}.",
+                elf = elf.display(),
+                digest = digest_file.display(),
+            );
+        }
+
+        let elf = elf.canonicalize().expect("committed ELF is missing");
+        println!("cargo:rustc-env=SP1_ELF_{package}={}", elf.display());
+    }
+
+    /// Hashes the manifests and sources of every local package the program is built from.
+    fn source_digest(program_dir: &str) -> String {
+        let metadata = cargo_metadata::MetadataCommand::new()
+            .manifest_path(Path::new(program_dir).join("Cargo.toml"))
+            .exec()
+            .expect("failed to read program metadata");
+        let root = Path::new("../../").canonicalize().unwrap();
+
+        // Only the program and its dependencies, not the other members of its workspace.
+        let resolve = metadata
+            .resolve
+            .as_ref()
+            .expect("program metadata has no resolve");
+        let program = metadata.root_package().expect("program has no package");
+        let mut pending = vec![&program.id];
+        let mut dependencies = HashSet::new();
+        while let Some(id) = pending.pop() {
+            if dependencies.insert(id) {
+                let node = resolve.nodes.iter().find(|n| &n.id == id).unwrap();
+                pending.extend(&node.dependencies);
+            }
+        }
+
+        let mut files = vec![];
+        let local = metadata.packages.iter().filter(|p| p.source.is_none());
+        for package in local.filter(|p| dependencies.contains(&p.id)) {
+            let manifest = PathBuf::from(package.manifest_path.as_std_path());
+            let package_dir = manifest.parent().unwrap();
+            println!("cargo:rerun-if-changed={}", package_dir.display());
+            collect_files(&package_dir.join("src"), &mut files);
+            files.push(manifest);
+        }
+        files.sort();
+        files.dedup();
+
+        let mut hasher = Sha3_256::new();
+        for file in files {
+            let relative = file.strip_prefix(&root).unwrap_or(&file);
+            let contents = fs::read(&file).expect("failed to read program source");
+            for part in [relative.to_string_lossy().as_bytes(), &contents] {
+                hasher.update((part.len() as u64).to_le_bytes());
+                hasher.update(part);
+            }
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.map(|e| e.expect("failed to list program sources")) {
+            let path = entry.path();
+            if path.is_dir() {
+                collect_files(&path, files);
+            } else {
+                files.push(path);
+            }
+        }
+    }
 }
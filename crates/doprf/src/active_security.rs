@@ -15,7 +15,7 @@ use curve25519_dalek::{
 };
 use rand::rngs::OsRng;
 use serde::{de, ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
-use sha3::Sha3_512;
+use sha3::{Digest, Sha3_256, Sha3_512};
 
 use crate::lagrange::evaluate_lagrange_polynomial;
 use crate::party::KeyserverId;
@@ -69,6 +69,22 @@ impl ActiveSecurityKey {
         self.0.len() as u32
     }
 
+    /// Identifies this key in the public values of the screening proofs.
+    pub fn commitment_hash(&self) -> [u8; 32] {
+        commitment_hash(&self.0)
+    }
+
+    /// The commitment to the keyshare of `keyserver_id`, which its responses are proven against.
+    pub fn keyserver_commitment(&self, keyserver_id: &KeyserverId) -> RistrettoPoint {
+        evaluate_lagrange_polynomial(&self.0, keyserver_id.into())
+    }
+
+    /// Whether both keys commit to the same secret key, as they do after the keyshares are
+    /// refreshed or reshared.
+    pub fn has_same_secret(&self, other: &Self) -> bool {
+        self.0.first() == other.0.first()
+    }
+
     #[cfg(test)]
     pub fn from_secret_and_keyshares<'a>(
         secret: &KeyShare,
@@ -225,6 +241,18 @@ impl Display for InvalidSecretAndKeyshareInput {
     }
 }
 
+/// The inputs to the active security checksum, as committed by both the hash_proof program,
+/// which derives them from the windows it hashes, and the checksum_proof program, which computes
+/// the checksum query from them. The aggregation program requires the two to be equal, so the
+/// checksum is bound to the real queries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChecksumInputs {
+    /// The random modifier of the [`RandomizedTarget`]: the hash of the concatenated queries.
+    pub random_modifier: [u8; 32],
+    /// The compressed sum of each window's hash point times its verification factor.
+    pub sum: [u8; 32],
+}
+
 /// Randomised target is a modification of the established target
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SerializableRandomizedTarget {
@@ -264,7 +292,20 @@ pub struct RandomizedTarget {
     commitments: Vec<RistrettoPoint>,
 }
 
+fn commitment_hash(commitments: &[RistrettoPoint]) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    for commitment in commitments {
+        hasher.update(commitment.compress().as_bytes());
+    }
+    hasher.finalize().into()
+}
+
 impl RandomizedTarget {
+    /// The [`ActiveSecurityKey::commitment_hash`] of the key this target was derived from.
+    pub fn commitment_hash(&self) -> [u8; 32] {
+        commitment_hash(&self.commitments)
+    }
+
     pub fn to_serializable_randomized_target(&self) -> SerializableRandomizedTarget {
         SerializableRandomizedTarget {
             random_modifier: self.random_modifier.to_bytes(),
@@ -442,4 +483,20 @@ mod tests {
 
         assert_eq!(as_key, decoded_key);
     }
+
+    #[test]
+    fn randomized_target_identifies_its_key() {
+        let key = ActiveSecurityKey::from_commitments([
+            Commitment::hash_from_bytes_for_tests_only(&[1]),
+            Commitment::hash_from_bytes_for_tests_only(&[2]),
+        ]);
+        let other = ActiveSecurityKey::from_commitments([
+            Commitment::hash_from_bytes_for_tests_only(&[1]),
+            Commitment::hash_from_bytes_for_tests_only(&[3]),
+        ]);
+        let target = key.randomized_target(Scalar::from(5u64));
+
+        assert_eq!(target.commitment_hash(), key.commitment_hash());
+        assert_ne!(target.commitment_hash(), other.commitment_hash());
+    }
 }
@@ -0,0 +1,10 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use clap::Parser;
+use doprf::shims::dkg;
+
+fn main() -> std::io::Result<()> {
+    let opts = dkg::Opts::parse();
+    dkg::main(&opts, &mut std::io::stdout(), &mut std::io::stderr())
+}
@@ -0,0 +1,1187 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Distributed generation of the keyshares and the [`ActiveSecurityKey`], so that the DOPRF
+//! secret is never held by anyone.
+//!
+//! This is Pedersen's DKG built from Feldman VSS. Every participant deals a random polynomial
+//! of degree `threshold - 1`: it broadcasts a [`Dealing`] committing to the coefficients, and
+//! privately sends every other participant a [`SecretShare`], the polynomial evaluated at their
+//! id. A participant whose share is missing or does not match the dealing broadcasts a
+//! [`Complaint`], which the dealer answers by revealing that share in a [`Justification`].
+//! Dealers that leave a complaint unanswered are disqualified. A participant's keyshare is the
+//! sum of the shares it got from the qualified dealers, so the secret is the sum of their
+//! constant terms, which nobody learns unless `threshold` keyshares are pooled.
+//!
+//! The protocol does not carry messages itself. Dealings, complaints and justifications must
+//! reach every participant unchanged, and each share must reach only its recipient. As with any
+//! Feldman-based DKG, a dishonest dealer can skew the public key somewhat by choosing whether
+//! to get itself disqualified, but cannot learn anything about the secret.
+//!
+//! The same rounds also refresh existing keyshares (see [`Participant::refresh`]). Every
+//! dealer's polynomial then has a constant term of zero, so adding the shares to the old
+//! keyshares re-randomizes them without changing the secret, and keyshares stolen before the
+//! refresh are useless alongside keyshares stolen after it.
+//!
+//! They also reshare the key to a different set of keyholders or a different threshold (see
+//! [`Participant::reshare`]). There, the old keyholders deal their keyshares as constant terms,
+//! which the old active security key lets everyone check, and each new keyholder combines the
+//! shares it gets with the Lagrange coefficients of the qualified dealers. The joint polynomial
+//! still has the old secret as its constant term, so hashes already in the database stay valid.
+
+use std::error::Error;
+use std::fmt;
+use std::num::NonZeroU32;
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::Identity;
+use rand::{CryptoRng, RngCore};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha3::{Digest, Sha3_512};
+
+use crate::active_security::{ActiveSecurityKey, Commitment};
+use crate::party::{KeyserverId, KeyserverIdSet};
+use crate::prf::KeyShare;
+
+const KNOWLEDGE_DOMAIN: &[u8] = b"SecureDNA DKG dealing v1";
+
+/// A dealer's broadcast: commitments to the coefficients of its polynomial, constant term
+/// first, with a proof that the dealer knows the constant term.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dealing {
+    pub dealer: KeyserverId,
+    pub commitments: Vec<Commitment>,
+    proof: KnowledgeProof,
+}
+
+impl Dealing {
+    /// Whether `share` is the dealer's polynomial evaluated at `recipient`.
+    fn matches(&self, recipient: KeyserverId, share: &KeyShare) -> bool {
+        let points: Vec<RistrettoPoint> = self.commitments.iter().map(|c| c.to_rp()).collect();
+        share.multiply_by_base() == evaluate_commitments(&points, recipient.to_scalar())
+    }
+}
+
+/// A dealer's share for one participant. It must only be seen by that participant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretShare {
+    pub dealer: KeyserverId,
+    pub recipient: KeyserverId,
+    share: KeyShare,
+}
+
+/// Broadcast by `accuser` when the share from `dealer` is missing or does not match its
+/// dealing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Complaint {
+    pub accuser: KeyserverId,
+    pub dealer: KeyserverId,
+}
+
+/// A dealer's answer to a complaint, revealing the share it dealt to `accuser` to everyone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Justification {
+    pub dealer: KeyserverId,
+    pub accuser: KeyserverId,
+    share: KeyShare,
+}
+
+/// One participant's side of the DKG.
+///
+/// It holds secret shares, so it must be stored as carefully as a keyshare between rounds.
+#[derive(Serialize, Deserialize)]
+pub struct Participant {
+    id: KeyserverId,
+    /// The number of keyshares needed to hash once the DKG ends.
+    threshold: NonZeroU32,
+    /// Those who deal a polynomial. Unless resharing, these are the same as the recipients.
+    dealers: Vec<KeyserverId>,
+    /// Those who end up with a keyshare.
+    recipients: Vec<KeyserverId>,
+    /// The shares this participant dealt, kept to answer complaints.
+    dealt: Vec<SecretShare>,
+    /// Every dealing received, this participant's own included.
+    dealings: Vec<Dealing>,
+    /// The shares that matched their dealings, this participant's own included.
+    shares: Vec<SecretShare>,
+    basis: Basis,
+}
+
+/// What the keyshares are dealt from.
+#[derive(Serialize, Deserialize)]
+enum Basis {
+    /// Nothing: the keyshares are generated from scratch.
+    Fresh,
+    /// This participant's keyshare, which the dealt polynomials are added to.
+    Refresh {
+        keyshare: KeyShare,
+        active_security_key: Vec<Commitment>,
+    },
+    /// The old keyholders' keyshares, each dealt as the constant term of a polynomial.
+    Reshare {
+        active_security_key: Vec<Commitment>,
+    },
+}
+
+impl Basis {
+    fn active_security_key(&self) -> Option<ActiveSecurityKey> {
+        match self {
+            Self::Fresh => None,
+            Self::Refresh {
+                active_security_key,
+                ..
+            }
+            | Self::Reshare {
+                active_security_key,
+            } => Some(ActiveSecurityKey::from_commitments(
+                active_security_key.iter().copied(),
+            )),
+        }
+    }
+}
+
+impl Participant {
+    /// Starts the DKG as `id`, dealing a fresh polynomial. Returns the dealing to broadcast and
+    /// the shares to send to each of the other participants.
+    pub fn deal(
+        id: KeyserverId,
+        threshold: NonZeroU32,
+        participants: &KeyserverIdSet,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(Self, Dealing, Vec<SecretShare>), DkgError> {
+        let participants: Vec<KeyserverId> = participants.iter().copied().collect();
+        let constant_term = Scalar::random(rng);
+        Self::start(
+            id,
+            threshold,
+            participants.clone(),
+            participants,
+            Basis::Fresh,
+            Some(constant_term),
+            rng,
+        )
+        .map(|(participant, dealing, shares)| (participant, dealing.expect("dealt"), shares))
+    }
+
+    /// Starts refreshing `keyshare`, which `id` holds under `active_security_key`, dealing a
+    /// fresh polynomial with a constant term of zero. Every keyholder must take part, as
+    /// keyshares that are not refreshed no longer work with the refreshed ones.
+    pub fn refresh(
+        id: KeyserverId,
+        keyshare: KeyShare,
+        active_security_key: &[Commitment],
+        participants: &KeyserverIdSet,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(Self, Dealing, Vec<SecretShare>), DkgError> {
+        let key = ActiveSecurityKey::from_commitments(active_security_key.iter().copied());
+        let threshold = NonZeroU32::new(key.supported_quorum())
+            .filter(|_| keyshare.multiply_by_base() == key.keyserver_commitment(&id))
+            .ok_or(DkgError::KeyshareMismatch(id))?;
+        let participants: Vec<KeyserverId> = participants.iter().copied().collect();
+        let basis = Basis::Refresh {
+            keyshare,
+            active_security_key: active_security_key.to_vec(),
+        };
+        Self::start(
+            id,
+            threshold,
+            participants.clone(),
+            participants,
+            basis,
+            Some(Scalar::ZERO),
+            rng,
+        )
+        .map(|(participant, dealing, shares)| (participant, dealing.expect("dealt"), shares))
+    }
+
+    /// Starts moving the secret key committed to by `active_security_key` from
+    /// `old_keyholders` to `new_keyholders`, any `threshold` of whom will be able to hash.
+    /// The new keyholders get new keyshares and a new active security key, but the secret key
+    /// stays the same, so the existing database hashes remain valid.
+    ///
+    /// Each old keyholder passes its `keyshare` and deals it as the constant term of a fresh
+    /// polynomial. A new keyholder that held no keyshare passes `None`, gets no dealing back,
+    /// and only receives. At least a quorum of the old keyholders must deal.
+    pub fn reshare(
+        id: KeyserverId,
+        keyshare: Option<KeyShare>,
+        active_security_key: &[Commitment],
+        old_keyholders: &KeyserverIdSet,
+        threshold: NonZeroU32,
+        new_keyholders: &KeyserverIdSet,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(Self, Option<Dealing>, Vec<SecretShare>), DkgError> {
+        let key = ActiveSecurityKey::from_commitments(active_security_key.iter().copied());
+        let old_threshold =
+            NonZeroU32::new(key.supported_quorum()).ok_or(DkgError::KeyshareMismatch(id))?;
+        if old_keyholders.len() < old_threshold.get() as usize {
+            return Err(DkgError::UnreachableQuorum {
+                threshold: old_threshold,
+                participants: old_keyholders.len(),
+            });
+        }
+        let dealers: Vec<KeyserverId> = old_keyholders.iter().copied().collect();
+        let constant_term = if dealers.contains(&id) {
+            let keyshare = keyshare
+                .filter(|k| k.multiply_by_base() == key.keyserver_commitment(&id))
+                .ok_or(DkgError::KeyshareMismatch(id))?;
+            Some(keyshare.to_scalar())
+        } else {
+            None
+        };
+        let recipients = new_keyholders.iter().copied().collect();
+        let basis = Basis::Reshare {
+            active_security_key: active_security_key.to_vec(),
+        };
+        Self::start(
+            id,
+            threshold,
+            dealers,
+            recipients,
+            basis,
+            constant_term,
+            rng,
+        )
+    }
+
+    /// Deals a polynomial with `constant_term` to the recipients, unless there is none because
+    /// `id` only receives.
+    fn start(
+        id: KeyserverId,
+        threshold: NonZeroU32,
+        dealers: Vec<KeyserverId>,
+        recipients: Vec<KeyserverId>,
+        basis: Basis,
+        constant_term: Option<Scalar>,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(Self, Option<Dealing>, Vec<SecretShare>), DkgError> {
+        if !dealers.contains(&id) && !recipients.contains(&id) {
+            return Err(DkgError::NotAParticipant(id));
+        }
+        for ids in [&dealers, &recipients] {
+            if let Some(pair) = ids.windows(2).find(|pair| pair[0] == pair[1]) {
+                return Err(DkgError::DuplicateParticipant(pair[0]));
+            }
+        }
+        if recipients.len() < threshold.get() as usize {
+            return Err(DkgError::UnreachableQuorum {
+                threshold,
+                participants: recipients.len(),
+            });
+        }
+
+        let mut participant = Self {
+            id,
+            threshold,
+            dealers,
+            recipients,
+            dealt: vec![],
+            dealings: vec![],
+            shares: vec![],
+            basis,
+        };
+        let Some(constant_term) = constant_term else {
+            return Ok((participant, None, vec![]));
+        };
+
+        let coefficients: Vec<Scalar> = std::iter::once(constant_term)
+            .chain((1..threshold.get()).map(|_| Scalar::random(rng)))
+            .collect();
+        let commitments: Vec<Commitment> = coefficients
+            .iter()
+            .map(|c| Commitment::from_rp(RistrettoPoint::mul_base(c)))
+            .collect();
+        let proof = KnowledgeProof::prove(id, &coefficients[0], &commitments, rng);
+        let dealing = Dealing {
+            dealer: id,
+            commitments,
+            proof,
+        };
+        participant.dealt = participant
+            .recipients
+            .iter()
+            .map(|&recipient| SecretShare {
+                dealer: id,
+                recipient,
+                share: evaluate_polynomial(&coefficients, recipient.to_scalar()).into(),
+            })
+            .collect();
+
+        let outgoing = participant
+            .dealt
+            .iter()
+            .filter(|s| s.recipient != id)
+            .cloned()
+            .collect();
+        participant.shares = participant
+            .dealt
+            .iter()
+            .filter(|s| s.recipient == id)
+            .cloned()
+            .collect();
+        participant.dealings.push(dealing.clone());
+        Ok((participant, Some(dealing), outgoing))
+    }
+
+    pub fn id(&self) -> KeyserverId {
+        self.id
+    }
+
+    /// The participants expected to deal.
+    pub fn dealers(&self) -> &[KeyserverId] {
+        &self.dealers
+    }
+
+    /// Everyone taking part, as a dealer, a recipient or both.
+    pub fn participants(&self) -> Vec<KeyserverId> {
+        let mut participants: Vec<KeyserverId> = self
+            .dealers
+            .iter()
+            .chain(&self.recipients)
+            .copied()
+            .collect();
+        participants.sort_unstable();
+        participants.dedup();
+        participants
+    }
+
+    /// Whether this participant ends up with a keyshare. When resharing, old keyholders that
+    /// are not among the new ones only deal.
+    pub fn receives_keyshare(&self) -> bool {
+        self.recipients.contains(&self.id)
+    }
+
+    /// Takes in another participant's dealing, along with the share it sent this participant
+    /// if one arrived. Returns a complaint to broadcast if the share is missing or wrong.
+    pub fn receive(
+        &mut self,
+        dealing: Dealing,
+        share: Option<SecretShare>,
+    ) -> Result<Option<Complaint>, DkgError> {
+        let dealer = dealing.dealer;
+        if !self.dealers.contains(&dealer) {
+            return Err(DkgError::NotAParticipant(dealer));
+        }
+        if self.dealings.iter().any(|d| d.dealer == dealer) {
+            return Err(DkgError::DuplicateDealing(dealer));
+        }
+        if let Some(share) = &share {
+            if share.dealer != dealer || share.recipient != self.id {
+                return Err(DkgError::MisaddressedShare {
+                    dealer: share.dealer,
+                    recipient: share.recipient,
+                });
+            }
+        }
+
+        let well_formed = self.is_well_formed(&dealing);
+        let share = share.filter(|s| well_formed && dealing.matches(self.id, &s.share));
+        self.dealings.push(dealing);
+        match share {
+            Some(share) => {
+                self.shares.push(share);
+                Ok(None)
+            }
+            None if well_formed && self.receives_keyshare() => Ok(Some(Complaint {
+                accuser: self.id,
+                dealer,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Answers the complaints against this participant by revealing the shares in question.
+    pub fn justify(&self, complaints: &[Complaint]) -> Vec<Justification> {
+        complaints
+            .iter()
+            .filter(|c| c.dealer == self.id)
+            .filter_map(|c| self.dealt.iter().find(|s| s.recipient == c.accuser))
+            .map(|s| Justification {
+                dealer: self.id,
+                accuser: s.recipient,
+                share: s.share,
+            })
+            .collect()
+    }
+
+    /// Ends the DKG, given every complaint and justification that was broadcast.
+    ///
+    /// Every honest participant that saw the same messages agrees on the qualified dealers
+    /// and so on the active security key.
+    pub fn finish(
+        self,
+        complaints: &[Complaint],
+        justifications: &[Justification],
+    ) -> Result<DkgOutput, DkgError> {
+        if !self.receives_keyshare() {
+            return Err(DkgError::NoKeyshare(self.id));
+        }
+        let mut contributions = vec![];
+        for dealing in &self.dealings {
+            if !self.is_qualified(dealing, complaints, justifications) {
+                continue;
+            }
+            let revealed = justifications
+                .iter()
+                .filter(|j| j.dealer == dealing.dealer && j.accuser == self.id)
+                .map(|j| &j.share)
+                .find(|share| dealing.matches(self.id, share));
+            let share = self
+                .shares
+                .iter()
+                .find(|s| s.dealer == dealing.dealer)
+                .map(|s| &s.share)
+                .or(revealed)
+                .ok_or(DkgError::MissingShare(dealing.dealer))?;
+            contributions.push((dealing, share));
+        }
+
+        let threshold = self.threshold.get() as usize;
+        // Resharing interpolates the old keyshares, so it needs a quorum of the old keyholders.
+        let required = match &self.basis {
+            Basis::Reshare {
+                active_security_key,
+            } => active_security_key.len(),
+            _ => threshold,
+        };
+        if contributions.len() < required {
+            return Err(DkgError::TooFewQualified {
+                qualified: contributions.len(),
+                required,
+            });
+        }
+        let qualified: KeyserverIdSet = contributions.iter().map(|(d, _)| d.dealer).collect();
+
+        // When resharing, each qualified dealer's polynomial is weighted by its Lagrange
+        // coefficient, so the joint polynomial's constant term is the old secret.
+        let weight = |dealer: &KeyserverId| match &self.basis {
+            Basis::Reshare { .. } => qualified.langrange_coefficient_for_id(dealer),
+            _ => Scalar::ONE,
+        };
+        let mut keyshare = match &self.basis {
+            Basis::Refresh { keyshare, .. } => keyshare.to_scalar(),
+            _ => Scalar::ZERO,
+        };
+        let mut coefficient_commitments = vec![RistrettoPoint::identity(); threshold];
+        for (dealing, share) in &contributions {
+            let coefficient = weight(&dealing.dealer);
+            keyshare += coefficient * share.to_scalar();
+            for (sum, commitment) in coefficient_commitments.iter_mut().zip(&dealing.commitments) {
+                *sum += coefficient * commitment.to_rp();
+            }
+        }
+
+        // The active security key is the joint polynomial at 0, 1, ..., threshold - 1, the
+        // same points `commitments_from_secret_and_keyshares` commits to. When refreshing, the
+        // joint polynomial is added to the one already committed to.
+        let commitments: Vec<Commitment> = (0..threshold)
+            .map(|x| {
+                let base = match &self.basis {
+                    Basis::Refresh {
+                        active_security_key,
+                        ..
+                    } => active_security_key[x].to_rp(),
+                    _ => RistrettoPoint::identity(),
+                };
+                let added = evaluate_commitments(&coefficient_commitments, Scalar::from(x as u64));
+                Commitment::from_rp(base + added)
+            })
+            .collect();
+
+        // Qualified dealings can't move the secret, but hashes already in the database are
+        // only valid as long as this holds, so check it outright.
+        if let Some(old_key) = self.basis.active_security_key() {
+            let new_key = ActiveSecurityKey::from_commitments(commitments.iter().copied());
+            if !new_key.has_same_secret(&old_key) {
+                return Err(DkgError::SecretChanged);
+            }
+        }
+        Ok(DkgOutput {
+            keyshare: keyshare.into(),
+            commitments,
+            qualified,
+        })
+    }
+
+    /// Whether the dealing has the right degree and a valid proof. When refreshing, its
+    /// constant term must also be zero, and when resharing, the dealer's old keyshare. Anyone
+    /// can check this, so a malformed dealing is disqualified without needing a complaint.
+    fn is_well_formed(&self, dealing: &Dealing) -> bool {
+        let constant_term = match &self.basis {
+            Basis::Fresh => None,
+            Basis::Refresh { .. } => Some(RistrettoPoint::identity()),
+            Basis::Reshare {
+                active_security_key,
+            } => Some(
+                ActiveSecurityKey::from_commitments(active_security_key.iter().copied())
+                    .keyserver_commitment(&dealing.dealer),
+            ),
+        };
+        dealing.commitments.len() == self.threshold.get() as usize
+            && dealing.proof.verify(dealing.dealer, &dealing.commitments)
+            && constant_term.map_or(true, |c| dealing.commitments[0].to_rp() == c)
+    }
+
+    /// A dealer is qualified if its dealing is well formed and every complaint against it was
+    /// answered with a share that matches the dealing.
+    fn is_qualified(
+        &self,
+        dealing: &Dealing,
+        complaints: &[Complaint],
+        justifications: &[Justification],
+    ) -> bool {
+        self.is_well_formed(dealing)
+            && complaints
+                .iter()
+                .filter(|c| c.dealer == dealing.dealer && self.recipients.contains(&c.accuser))
+                .all(|c| {
+                    justifications.iter().any(|j| {
+                        j.dealer == c.dealer
+                            && j.accuser == c.accuser
+                            && dealing.matches(c.accuser, &j.share)
+                    })
+                })
+    }
+}
+
+/// What a participant ends the DKG with.
+#[derive(Debug, Clone)]
+pub struct DkgOutput {
+    pub keyshare: KeyShare,
+    /// The commitments making up the active security key, the same for every participant.
+    pub commitments: Vec<Commitment>,
+    /// The dealers whose polynomials went into the keyshares.
+    pub qualified: KeyserverIdSet,
+}
+
+impl DkgOutput {
+    pub fn active_security_key(&self) -> ActiveSecurityKey {
+        ActiveSecurityKey::from_commitments(self.commitments.iter().copied())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DkgError {
+    NotAParticipant(KeyserverId),
+    DuplicateParticipant(KeyserverId),
+    UnreachableQuorum {
+        threshold: NonZeroU32,
+        participants: usize,
+    },
+    DuplicateDealing(KeyserverId),
+    MisaddressedShare {
+        dealer: KeyserverId,
+        recipient: KeyserverId,
+    },
+    MissingShare(KeyserverId),
+    KeyshareMismatch(KeyserverId),
+    TooFewQualified {
+        qualified: usize,
+        required: usize,
+    },
+    NoKeyshare(KeyserverId),
+    SecretChanged,
+}
+
+impl fmt::Display for DkgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAParticipant(id) => write!(f, "keyserver {id} is not a participant"),
+            Self::DuplicateParticipant(id) => write!(f, "keyserver {id} is listed twice"),
+            Self::UnreachableQuorum {
+                threshold,
+                participants,
+            } => write!(
+                f,
+                "{participants} participants can't reach quorum of {threshold}"
+            ),
+            Self::DuplicateDealing(id) => write!(f, "received a second dealing from {id}"),
+            Self::MisaddressedShare { dealer, recipient } => {
+                write!(f, "share from {dealer} is addressed to {recipient}")
+            }
+            Self::MissingShare(id) => write!(
+                f,
+                "no valid share from qualified dealer {id}; was a complaint missed?"
+            ),
+            Self::KeyshareMismatch(id) => write!(
+                f,
+                "the keyshare of {id} does not match the active security key"
+            ),
+            Self::TooFewQualified {
+                qualified,
+                required,
+            } => write!(
+                f,
+                "only {qualified} dealers qualified, fewer than the {required} required"
+            ),
+            Self::NoKeyshare(id) => write!(f, "keyserver {id} only deals and gets no keyshare"),
+            Self::SecretChanged => write!(f, "the new active security key has a different secret"),
+        }
+    }
+}
+
+impl Error for DkgError {}
+
+/// Schnorr proof of knowledge of a dealing's constant term, so that no dealer can choose its
+/// commitment after seeing the others' to cancel them out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KnowledgeProof {
+    challenge: Scalar,
+    response: Scalar,
+}
+
+impl KnowledgeProof {
+    fn prove(
+        dealer: KeyserverId,
+        constant_term: &Scalar,
+        commitments: &[Commitment],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Self {
+        let nonce = Scalar::random(rng);
+        let challenge = knowledge_challenge(dealer, commitments, &RistrettoPoint::mul_base(&nonce));
+        Self {
+            challenge,
+            response: nonce + challenge * constant_term,
+        }
+    }
+
+    fn verify(&self, dealer: KeyserverId, commitments: &[Commitment]) -> bool {
+        let Some(constant_term) = commitments.first() else {
+            return false;
+        };
+        let nonce_commitment =
+            RistrettoPoint::mul_base(&self.response) - self.challenge * constant_term.to_rp();
+        knowledge_challenge(dealer, commitments, &nonce_commitment) == self.challenge
+    }
+}
+
+impl Serialize for KnowledgeProof {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = [0; 64];
+        bytes[..32].copy_from_slice(self.challenge.as_bytes());
+        bytes[32..].copy_from_slice(self.response.as_bytes());
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+}
+
+impl<'de> Deserialize<'de> for KnowledgeProof {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let bytes: [u8; 64] = hex::decode(s)
+            .map_err(de::Error::custom)?
+            .try_into()
+            .map_err(|_| de::Error::custom("proof must be 64 bytes"))?;
+        let scalar = |half: &[u8]| {
+            Option::<Scalar>::from(Scalar::from_canonical_bytes(half.try_into().unwrap()))
+                .ok_or_else(|| de::Error::custom("proof contains a non-canonical scalar"))
+        };
+        Ok(Self {
+            challenge: scalar(&bytes[..32])?,
+            response: scalar(&bytes[32..])?,
+        })
+    }
+}
+
+fn knowledge_challenge(
+    dealer: KeyserverId,
+    commitments: &[Commitment],
+    nonce_commitment: &RistrettoPoint,
+) -> Scalar {
+    let mut hasher = Sha3_512::new();
+    hasher.update(KNOWLEDGE_DOMAIN);
+    hasher.update(dealer.as_u32().to_le_bytes());
+    hasher.update((commitments.len() as u64).to_le_bytes());
+    for commitment in commitments {
+        hasher.update(commitment.as_bytes());
+    }
+    hasher.update(nonce_commitment.compress().as_bytes());
+    Scalar::from_hash(hasher)
+}
+
+fn evaluate_polynomial(coefficients: &[Scalar], x: Scalar) -> Scalar {
+    coefficients
+        .iter()
+        .rev()
+        .fold(Scalar::ZERO, |acc, coefficient| acc * x + coefficient)
+}
+
+/// The polynomial committed to by `commitments` at `x`, times the base point.
+fn evaluate_commitments(commitments: &[RistrettoPoint], x: Scalar) -> RistrettoPoint {
+    commitments
+        .iter()
+        .rev()
+        .fold(RistrettoPoint::identity(), |acc, commitment| {
+            acc * x + commitment
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+    use std::sync::{mpsc, Barrier};
+    use std::thread;
+
+    use curve25519_dalek::{RistrettoPoint, Scalar};
+    use rand::rngs::OsRng;
+
+    use super::{Complaint, Dealing, DkgError, DkgOutput, Justification, Participant, SecretShare};
+    use crate::active_security::Commitment;
+    use crate::party::{KeyserverId, KeyserverIdSet};
+    use crate::prf::{KeyShare, QueryState};
+
+    enum Message {
+        Dealing(Dealing),
+        Share(SecretShare),
+        Complaints(Vec<Complaint>),
+        Justifications(Vec<Justification>),
+    }
+
+    type Start = (Participant, Option<Dealing>, Vec<SecretShare>);
+    type Results = Vec<(KeyserverId, Result<DkgOutput, DkgError>)>;
+
+    fn run_dkg(
+        threshold: u32,
+        num_participants: u32,
+        tamper: impl Fn(KeyserverId, KeyserverId, &mut Message) + Sync,
+    ) -> Vec<(KeyserverId, DkgOutput)> {
+        let ids: Vec<KeyserverId> = (1..=num_participants).map(id).collect();
+        let participants = KeyserverIdSet::from(ids.clone());
+        let threshold = NonZeroU32::new(threshold).unwrap();
+        let start = |id| {
+            let (participant, dealing, shares) =
+                Participant::deal(id, threshold, &participants, &mut OsRng).unwrap();
+            (participant, Some(dealing), shares)
+        };
+        keyholders(run(&ids, start, tamper), &ids)
+    }
+
+    fn run_refresh(
+        outputs: &[(KeyserverId, DkgOutput)],
+        tamper: impl Fn(KeyserverId, KeyserverId, &mut Message) + Sync,
+    ) -> Vec<(KeyserverId, DkgOutput)> {
+        let ids: Vec<KeyserverId> = outputs.iter().map(|(id, _)| *id).collect();
+        let participants = KeyserverIdSet::from(ids.clone());
+        let start = |id| {
+            let (_, output) = outputs.iter().find(|(o, _)| *o == id).unwrap();
+            let key = &output.commitments;
+            let (participant, dealing, shares) =
+                Participant::refresh(id, output.keyshare, key, &participants, &mut OsRng).unwrap();
+            (participant, Some(dealing), shares)
+        };
+        keyholders(run(&ids, start, tamper), &ids)
+    }
+
+    /// Reshares the key of `outputs` from the `old` keyholders to the `new` ones, any
+    /// `threshold` of whom can hash afterwards.
+    fn run_reshare(
+        outputs: &[(KeyserverId, DkgOutput)],
+        old: &[u32],
+        threshold: u32,
+        new: &[u32],
+    ) -> Vec<(KeyserverId, DkgOutput)> {
+        let old = KeyserverIdSet::from_iter(old.iter().map(|&i| id(i)));
+        let new: Vec<KeyserverId> = new.iter().map(|&i| id(i)).collect();
+        let ids = union(&old, &new);
+        let threshold = NonZeroU32::new(threshold).unwrap();
+        let key = &outputs[0].1.commitments;
+        let start = |id| {
+            let keyshare = outputs
+                .iter()
+                .find(|(o, _)| *o == id && old.iter().any(|d| *d == id))
+                .map(|(_, output)| output.keyshare);
+            let new = KeyserverIdSet::from(new.clone());
+            Participant::reshare(id, keyshare, key, &old, threshold, &new, &mut OsRng).unwrap()
+        };
+        keyholders(run(&ids, start, |_, _, _| {}), &new)
+    }
+
+    /// Runs the rounds with every participant on its own thread, talking to the others only
+    /// through channels. `tamper(from, to, message)` can alter messages in flight.
+    fn run(
+        ids: &[KeyserverId],
+        start: impl Fn(KeyserverId) -> Start + Sync,
+        tamper: impl Fn(KeyserverId, KeyserverId, &mut Message) + Sync,
+    ) -> Results {
+        let (outboxes, inboxes): (Vec<_>, Vec<_>) = ids.iter().map(|_| mpsc::channel()).unzip();
+        let round = Barrier::new(ids.len());
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = ids
+                .iter()
+                .zip(inboxes)
+                .map(|(&id, inbox)| {
+                    let (outboxes, round, start, tamper) =
+                        (outboxes.clone(), &round, &start, &tamper);
+                    scope.spawn(move || {
+                        let send = |to: KeyserverId, mut message: Message| {
+                            tamper(id, to, &mut message);
+                            let outbox = ids.iter().position(|&i| i == to).unwrap();
+                            outboxes[outbox].send(message).unwrap();
+                        };
+                        let broadcast = |message: &dyn Fn() -> Message| {
+                            for &to in ids.iter().filter(|&&to| to != id) {
+                                send(to, message());
+                            }
+                        };
+                        // Everyone sends, then everyone reads, so nothing from a later round
+                        // overtakes an earlier one.
+                        let exchange = || {
+                            round.wait();
+                            let messages: Vec<Message> = inbox.try_iter().collect();
+                            round.wait();
+                            messages
+                        };
+
+                        let (mut participant, dealing, shares) = start(id);
+                        if let Some(dealing) = dealing {
+                            broadcast(&|| Message::Dealing(dealing.clone()));
+                        }
+                        for share in shares {
+                            send(share.recipient, Message::Share(share));
+                        }
+                        let mut dealings = vec![];
+                        let mut shares = vec![];
+                        for message in exchange() {
+                            match message {
+                                Message::Dealing(dealing) => dealings.push(dealing),
+                                Message::Share(share) => shares.push(share),
+                                _ => unreachable!("messages arrive in rounds"),
+                            }
+                        }
+
+                        let mut complaints = vec![];
+                        for dealing in dealings {
+                            let share = shares
+                                .iter()
+                                .position(|s| s.dealer == dealing.dealer)
+                                .map(|i| shares.swap_remove(i));
+                            complaints.extend(participant.receive(dealing, share).unwrap());
+                        }
+                        broadcast(&|| Message::Complaints(complaints.clone()));
+                        for message in exchange() {
+                            match message {
+                                Message::Complaints(theirs) => complaints.extend(theirs),
+                                _ => unreachable!("messages arrive in rounds"),
+                            }
+                        }
+
+                        let mut justifications = participant.justify(&complaints);
+                        broadcast(&|| Message::Justifications(justifications.clone()));
+                        for message in exchange() {
+                            match message {
+                                Message::Justifications(theirs) => justifications.extend(theirs),
+                                _ => unreachable!("messages arrive in rounds"),
+                            }
+                        }
+                        (id, participant.finish(&complaints, &justifications))
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        })
+    }
+
+    /// The outputs of `ids`, each of which must have ended up with a keyshare.
+    fn keyholders(results: Results, ids: &[KeyserverId]) -> Vec<(KeyserverId, DkgOutput)> {
+        results
+            .into_iter()
+            .filter(|(id, _)| ids.contains(id))
+            .map(|(id, result)| (id, result.unwrap()))
+            .collect()
+    }
+
+    fn union(a: &KeyserverIdSet, b: &[KeyserverId]) -> Vec<KeyserverId> {
+        let mut ids: Vec<KeyserverId> = a.iter().chain(b).copied().collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    fn id(i: u32) -> KeyserverId {
+        KeyserverId::try_from(i).unwrap()
+    }
+
+    /// Recovers the secret from the keyshares of `ids`, as only a quorum could.
+    fn reconstruct(outputs: &[(KeyserverId, DkgOutput)], ids: &[u32]) -> Scalar {
+        let set = KeyserverIdSet::from_iter(ids.iter().map(|&i| id(i)));
+        set.iter()
+            .map(|i| {
+                let (_, output) = outputs.iter().find(|(o, _)| o == i).unwrap();
+                set.langrange_coefficient_for_id(i) * output.keyshare.to_scalar()
+            })
+            .sum()
+    }
+
+    /// Hashes `bytes` with the keyshares of `ids`, the way a client and keyservers would.
+    fn hash(outputs: &[(KeyserverId, DkgOutput)], ids: &[u32], bytes: &[u8]) -> [u8; 32] {
+        let set = KeyserverIdSet::from_iter(ids.iter().map(|&i| id(i)));
+        let mut state = QueryState::new(bytes, set.len());
+        for i in set.iter() {
+            let (_, output) = outputs.iter().find(|(o, _)| o == i).unwrap();
+            let coefficient = set.langrange_coefficient_for_id(i);
+            let part = output
+                .keyshare
+                .apply_query_and_lagrange_coefficient(*state.query(), &coefficient);
+            state.incorporate_response(*i, part);
+        }
+        *state.get_hash_value().unwrap().as_bytes()
+    }
+
+    fn assert_consistent(outputs: &[(KeyserverId, DkgOutput)]) {
+        let (_, first) = &outputs[0];
+        for (id, output) in outputs {
+            assert_eq!(output.commitments, first.commitments);
+            assert_eq!(output.qualified.to_string(), first.qualified.to_string());
+            assert_eq!(
+                output.keyshare.multiply_by_base(),
+                first.active_security_key().keyserver_commitment(id)
+            );
+        }
+    }
+
+    #[test]
+    fn honest_run_agrees_on_an_unknown_secret() {
+        let outputs = run_dkg(3, 5, |_, _, _| {});
+        assert_consistent(&outputs);
+        let (_, output) = &outputs[0];
+        assert_eq!(output.qualified.to_string(), "1,2,3,4,5");
+        assert_eq!(output.active_security_key().supported_quorum(), 3);
+
+        let secret = reconstruct(&outputs, &[1, 2, 3]);
+        assert_eq!(reconstruct(&outputs, &[2, 4, 5]), secret);
+        assert_eq!(
+            RistrettoPoint::mul_base(&secret),
+            output.commitments[0].to_rp()
+        );
+        assert_ne!(reconstruct(&outputs, &[1, 2]), secret);
+    }
+
+    #[test]
+    fn answered_complaint_keeps_dealer() {
+        let outputs = run_dkg(2, 4, |from, to, message| {
+            if let (2, 3, Message::Share(share)) = (from.as_u32(), to.as_u32(), message) {
+                share.share = KeyShare::from(Scalar::ONE);
+            }
+        });
+        assert_consistent(&outputs);
+        assert_eq!(outputs[0].1.qualified.to_string(), "1,2,3,4");
+        assert_eq!(
+            reconstruct(&outputs, &[1, 3]),
+            reconstruct(&outputs, &[2, 4])
+        );
+    }
+
+    #[test]
+    fn unanswered_complaint_disqualifies_dealer() {
+        let outputs = run_dkg(2, 4, |from, to, message| match (from.as_u32(), message) {
+            (2, Message::Share(share)) if to.as_u32() == 3 => {
+                share.share = KeyShare::from(Scalar::ONE);
+            }
+            (2, Message::Justifications(justifications)) => justifications.clear(),
+            _ => {}
+        });
+        // Dealer 2 never learns that it was disqualified, but everyone else agrees.
+        let honest: Vec<_> = outputs.into_iter().filter(|(i, _)| *i != id(2)).collect();
+        assert_consistent(&honest);
+        assert_eq!(honest[0].1.qualified.to_string(), "1,3,4");
+        assert_eq!(reconstruct(&honest, &[1, 3]), reconstruct(&honest, &[3, 4]));
+    }
+
+    #[test]
+    fn forged_dealing_is_disqualified() {
+        let outputs = run_dkg(2, 3, |from, _, message| {
+            if let (1, Message::Dealing(dealing)) = (from.as_u32(), message) {
+                dealing.commitments[0] =
+                    Commitment::from_rp(RistrettoPoint::mul_base(&Scalar::ONE));
+            }
+        });
+        let honest: Vec<_> = outputs.into_iter().filter(|(i, _)| *i != id(1)).collect();
+        assert_consistent(&honest);
+        assert_eq!(honest[0].1.qualified.to_string(), "2,3");
+    }
+
+    #[test]
+    fn refresh_keeps_the_secret() {
+        let outputs = run_dkg(3, 5, |_, _, _| {});
+        let refreshed = run_refresh(&outputs, |_, _, _| {});
+        assert_consistent(&refreshed);
+        assert_eq!(refreshed[0].1.commitments[0], outputs[0].1.commitments[0]);
+        assert_ne!(refreshed[0].1.commitments, outputs[0].1.commitments);
+
+        let secret = reconstruct(&outputs, &[1, 2, 3]);
+        assert_eq!(reconstruct(&refreshed, &[1, 2, 3]), secret);
+        assert_eq!(reconstruct(&refreshed, &[3, 4, 5]), secret);
+
+        // Keyshares stolen before the refresh don't combine with those stolen after it.
+        let mixed = [outputs[0].clone(), outputs[1].clone(), refreshed[2].clone()];
+        assert_ne!(reconstruct(&mixed, &[1, 2, 3]), secret);
+    }
+
+    #[test]
+    fn refresh_disqualifies_dealing_that_changes_the_secret() {
+        let outputs = run_dkg(2, 3, |_, _, _| {});
+        let ids: Vec<KeyserverId> = (1..=3).map(id).collect();
+        let participants = KeyserverIdSet::from(ids.clone());
+        let start = |id| {
+            let (_, output) = outputs.iter().find(|(o, _)| *o == id).unwrap();
+            let (participant, dealing, shares) = if id.as_u32() == 1 {
+                // A dealing with a nonzero constant term would move the secret.
+                let threshold = NonZeroU32::new(2).unwrap();
+                Participant::deal(id, threshold, &participants, &mut OsRng).unwrap()
+            } else {
+                let key = &output.commitments;
+                Participant::refresh(id, output.keyshare, key, &participants, &mut OsRng).unwrap()
+            };
+            (participant, Some(dealing), shares)
+        };
+        let refreshed = run(&ids, start, |_, _, _| {});
+        let honest = keyholders(refreshed, &[id(2), id(3)]);
+        assert_consistent(&honest);
+        assert_eq!(honest[0].1.qualified.to_string(), "2,3");
+        assert_eq!(
+            reconstruct(&honest, &[2, 3]),
+            reconstruct(&outputs, &[1, 2])
+        );
+    }
+
+    #[test]
+    fn refresh_rejects_keyshare_not_matching_key() {
+        let outputs = run_dkg(2, 2, |_, _, _| {});
+        let participants = KeyserverIdSet::from_iter([id(1), id(2)]);
+        let result = Participant::refresh(
+            id(1),
+            outputs[1].1.keyshare,
+            &outputs[0].1.commitments,
+            &participants,
+            &mut OsRng,
+        );
+        assert_eq!(result.err(), Some(DkgError::KeyshareMismatch(id(1))));
+    }
+
+    #[test]
+    fn reshare_moves_the_key_to_new_keyholders() {
+        let outputs = run_dkg(3, 5, |_, _, _| {});
+        let reshared = run_reshare(&outputs, &[1, 2, 3, 4, 5], 4, &[4, 5, 6, 7, 8, 9]);
+        assert_consistent(&reshared);
+        let (_, output) = &reshared[0];
+        assert_eq!(output.qualified.to_string(), "1,2,3,4,5");
+        assert_eq!(output.active_security_key().supported_quorum(), 4);
+        assert!(output
+            .active_security_key()
+            .has_same_secret(&outputs[0].1.active_security_key()));
+
+        let secret = reconstruct(&outputs, &[1, 2, 3]);
+        assert_eq!(reconstruct(&reshared, &[4, 5, 6, 7]), secret);
+        assert_eq!(reconstruct(&reshared, &[6, 7, 8, 9]), secret);
+        assert_ne!(reconstruct(&reshared, &[7, 8, 9]), secret);
+
+        // Hashes already in the database are still what the new keyholders compute.
+        let old_hash = hash(&outputs, &[1, 2, 5], b"an existing database entry");
+        assert_eq!(
+            hash(&reshared, &[5, 6, 8, 9], b"an existing database entry"),
+            old_hash
+        );
+    }
+
+    #[test]
+    fn reshare_needs_only_a_quorum_of_old_keyholders() {
+        let outputs = run_dkg(3, 5, |_, _, _| {});
+        let reshared = run_reshare(&outputs, &[2, 3, 5], 2, &[1, 2, 3]);
+        assert_consistent(&reshared);
+        assert_eq!(reshared[0].1.qualified.to_string(), "2,3,5");
+        assert_eq!(
+            reconstruct(&reshared, &[1, 3]),
+            reconstruct(&outputs, &[1, 2, 3])
+        );
+
+        let too_few = KeyserverIdSet::from_iter([id(2), id(3)]);
+        let result = Participant::reshare(
+            id(2),
+            Some(outputs[1].1.keyshare),
+            &outputs[0].1.commitments,
+            &too_few,
+            NonZeroU32::new(2).unwrap(),
+            &KeyserverIdSet::from_iter([id(1), id(2), id(3)]),
+            &mut OsRng,
+        );
+        assert_eq!(
+            result.err(),
+            Some(DkgError::UnreachableQuorum {
+                threshold: NonZeroU32::new(3).unwrap(),
+                participants: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn reshare_disqualifies_dealer_not_dealing_its_keyshare() {
+        let outputs = run_dkg(2, 3, |_, _, _| {});
+        let old = KeyserverIdSet::from_iter([id(1), id(2), id(3)]);
+        let new = KeyserverIdSet::from_iter([id(3), id(4), id(5)]);
+        let everyone = KeyserverIdSet::from_iter((1..=5).map(id));
+        let threshold = NonZeroU32::new(2).unwrap();
+        let key = &outputs[0].1.commitments;
+        let start = |id: KeyserverId| {
+            if id.as_u32() == 1 {
+                // A well-formed dealing of some other constant term would move the secret.
+                let (participant, dealing, shares) =
+                    Participant::deal(id, threshold, &everyone, &mut OsRng).unwrap();
+                return (participant, Some(dealing), shares);
+            }
+            let keyshare = outputs
+                .iter()
+                .find(|(o, _)| *o == id)
+                .map(|(_, output)| output.keyshare);
+            Participant::reshare(id, keyshare, key, &old, threshold, &new, &mut OsRng).unwrap()
+        };
+        let ids: Vec<KeyserverId> = everyone.iter().copied().collect();
+        let reshared = keyholders(run(&ids, start, |_, _, _| {}), &[id(3), id(4), id(5)]);
+        assert_consistent(&reshared);
+        assert_eq!(reshared[0].1.qualified.to_string(), "2,3");
+        assert_eq!(
+            reconstruct(&reshared, &[4, 5]),
+            reconstruct(&outputs, &[1, 2])
+        );
+    }
+
+    #[test]
+    fn reshare_leaves_old_only_keyholders_without_keyshare() {
+        let outputs = run_dkg(2, 2, |_, _, _| {});
+        let old = KeyserverIdSet::from_iter([id(1), id(2)]);
+        let new = KeyserverIdSet::from_iter([id(2), id(3)]);
+        let threshold = NonZeroU32::new(2).unwrap();
+        let key = &outputs[0].1.commitments;
+        let start = |id: KeyserverId| {
+            let keyshare = outputs
+                .iter()
+                .find(|(o, _)| *o == id)
+                .map(|(_, output)| output.keyshare);
+            Participant::reshare(id, keyshare, key, &old, threshold, &new, &mut OsRng).unwrap()
+        };
+        let results = run(&[id(1), id(2), id(3)], start, |_, _, _| {});
+        assert_eq!(
+            results[0].1.as_ref().err(),
+            Some(&DkgError::NoKeyshare(id(1)))
+        );
+        assert!(results[1..].iter().all(|(_, result)| result.is_ok()));
+    }
+
+    #[test]
+    fn participant_round_trips_through_json() {
+        let participants = KeyserverIdSet::from_iter([id(1), id(2)]);
+        let (participant, dealing, shares) = Participant::deal(
+            id(1),
+            NonZeroU32::new(2).unwrap(),
+            &participants,
+            &mut OsRng,
+        )
+        .unwrap();
+        let participant: Participant =
+            serde_json::from_str(&serde_json::to_string(&participant).unwrap()).unwrap();
+        let dealing: Dealing =
+            serde_json::from_str(&serde_json::to_string(&dealing).unwrap()).unwrap();
+        assert!(participant.is_well_formed(&dealing));
+        assert!(dealing.matches(shares[0].recipient, &shares[0].share));
+    }
+}
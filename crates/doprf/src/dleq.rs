@@ -0,0 +1,232 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Batched Chaum-Pedersen (DLEQ) proofs that a keyserver answered a whole request with the
+//! keyshare behind its commitment.
+//!
+//! Each hash part should be the query times `lagrange_coefficient * keyshare`. Rather than
+//! proving every hash part on its own, the queries and hash parts are each folded into a
+//! single point with weights drawn from a hash of the whole exchange, and one proof is given
+//! that the folded hash part is the folded query times the same scalar the commitment is the
+//! base point times. A single wrong hash part makes the folded points disagree, except with
+//! negligible probability.
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::VartimeMultiscalarMul;
+use rand::rngs::OsRng;
+use sha3::{Digest, Sha3_512};
+
+use crate::prf::{HashPart, Query};
+
+const BATCH_DOMAIN: &[u8] = b"SecureDNA keyserver DLEQ batch v1";
+const CHALLENGE_DOMAIN: &[u8] = b"SecureDNA keyserver DLEQ challenge v1";
+
+/// Proves that every hash part in a keyserver response is the matching query multiplied by
+/// the keyserver's Lagrange coefficient and keyshare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DleqProof {
+    challenge: Scalar,
+    response: Scalar,
+}
+
+impl DleqProof {
+    /// Encoded size, which is that of two hash parts, so a proof can trail a response.
+    pub const SIZE: usize = 64;
+
+    pub(crate) fn prove(
+        keyshare: &Scalar,
+        lagrange_coefficient: &Scalar,
+        queries: &[Query],
+        hash_parts: &[HashPart],
+    ) -> Self {
+        let commitment = RistrettoPoint::mul_base(keyshare);
+        let weights = batch_weights(&commitment, lagrange_coefficient, queries, hash_parts);
+        let base = lagrange_coefficient * fold(&weights, queries.iter().map(|q| q.to_rp()));
+        let folded_hash_part = keyshare * base;
+
+        let nonce = Scalar::random(&mut OsRng);
+        let challenge = challenge(
+            &commitment,
+            &base,
+            &folded_hash_part,
+            &RistrettoPoint::mul_base(&nonce),
+            &(nonce * base),
+        );
+        Self {
+            challenge,
+            response: nonce + challenge * keyshare,
+        }
+    }
+
+    /// Checks the proof against the `commitment` of the keyserver that sent `hash_parts` in
+    /// answer to `queries`.
+    pub fn verify(
+        &self,
+        commitment: &RistrettoPoint,
+        lagrange_coefficient: &Scalar,
+        queries: &[Query],
+        hash_parts: &[HashPart],
+    ) -> bool {
+        if queries.len() != hash_parts.len() {
+            return false;
+        }
+        let weights = batch_weights(commitment, lagrange_coefficient, queries, hash_parts);
+        let base = lagrange_coefficient * fold(&weights, queries.iter().map(|q| q.to_rp()));
+        let folded_hash_part = fold(&weights, hash_parts.iter().map(|h| h.to_rp()));
+
+        let base_nonce = RistrettoPoint::mul_base(&self.response) - self.challenge * commitment;
+        let query_nonce = self.response * base - self.challenge * folded_hash_part;
+        challenge(
+            commitment,
+            &base,
+            &folded_hash_part,
+            &base_nonce,
+            &query_nonce,
+        ) == self.challenge
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[..32].copy_from_slice(self.challenge.as_bytes());
+        bytes[32..].copy_from_slice(self.response.as_bytes());
+        bytes
+    }
+
+    /// Returns `None` unless both halves are canonical scalars.
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Option<Self> {
+        let scalar = |half: &[u8]| {
+            Option::<Scalar>::from(Scalar::from_canonical_bytes(half.try_into().unwrap()))
+        };
+        Some(Self {
+            challenge: scalar(&bytes[..32])?,
+            response: scalar(&bytes[32..])?,
+        })
+    }
+}
+
+/// One weight per query, derived from everything the proof covers, so the keyserver cannot
+/// choose its hash parts knowing how they will be weighted.
+fn batch_weights(
+    commitment: &RistrettoPoint,
+    lagrange_coefficient: &Scalar,
+    queries: &[Query],
+    hash_parts: &[HashPart],
+) -> Vec<Scalar> {
+    let mut hasher = Sha3_512::new();
+    hasher.update(BATCH_DOMAIN);
+    hasher.update(commitment.compress().as_bytes());
+    hasher.update(lagrange_coefficient.as_bytes());
+    hasher.update((queries.len() as u64).to_le_bytes());
+    for query in queries {
+        hasher.update(query.as_bytes());
+    }
+    for hash_part in hash_parts {
+        hasher.update(hash_part.as_bytes());
+    }
+    let seed = hasher.finalize();
+
+    (0..queries.len() as u64)
+        .map(|i| {
+            Scalar::from_hash(
+                Sha3_512::new()
+                    .chain_update(seed)
+                    .chain_update(i.to_le_bytes()),
+            )
+        })
+        .collect()
+}
+
+fn fold(weights: &[Scalar], points: impl Iterator<Item = RistrettoPoint>) -> RistrettoPoint {
+    RistrettoPoint::vartime_multiscalar_mul(weights, points)
+}
+
+fn challenge(
+    commitment: &RistrettoPoint,
+    base: &RistrettoPoint,
+    folded_hash_part: &RistrettoPoint,
+    base_nonce: &RistrettoPoint,
+    query_nonce: &RistrettoPoint,
+) -> Scalar {
+    let mut hasher = Sha3_512::new();
+    hasher.update(CHALLENGE_DOMAIN);
+    for point in [commitment, base, folded_hash_part, base_nonce, query_nonce] {
+        hasher.update(point.compress().as_bytes());
+    }
+    Scalar::from_hash(hasher)
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::Scalar;
+    use rand::thread_rng;
+
+    use super::DleqProof;
+    use crate::prf::{HashPart, KeyShare, Query};
+
+    fn respond(keyshare: &KeyShare, coefficient: &Scalar, queries: &[Query]) -> Vec<HashPart> {
+        queries
+            .iter()
+            .map(|q| keyshare.apply_query_and_lagrange_coefficient(*q, coefficient))
+            .collect()
+    }
+
+    fn queries(n: u8) -> Vec<Query> {
+        (0..n)
+            .map(|i| Query::hash_from_bytes_for_tests_only(&[i]))
+            .collect()
+    }
+
+    #[test]
+    fn honest_responses_verify() {
+        let keyshare = KeyShare::from(Scalar::random(&mut thread_rng()));
+        let coefficient = Scalar::from(7u64);
+        let queries = queries(20);
+        let hash_parts = respond(&keyshare, &coefficient, &queries);
+
+        let proof = keyshare.prove_responses(&coefficient, &queries, &hash_parts);
+        let commitment = keyshare.multiply_by_base();
+        assert!(proof.verify(&commitment, &coefficient, &queries, &hash_parts));
+
+        let decoded = DleqProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(decoded, proof);
+    }
+
+    #[test]
+    fn one_wrong_hash_part_fails() {
+        let keyshare = KeyShare::from(Scalar::random(&mut thread_rng()));
+        let coefficient = Scalar::from(7u64);
+        let queries = queries(20);
+        let mut hash_parts = respond(&keyshare, &coefficient, &queries);
+        hash_parts[13] = HashPart::hash_from_bytes_for_tests_only(b"wrong");
+
+        let proof = keyshare.prove_responses(&coefficient, &queries, &hash_parts);
+        let commitment = keyshare.multiply_by_base();
+        assert!(!proof.verify(&commitment, &coefficient, &queries, &hash_parts));
+    }
+
+    #[test]
+    fn other_keyshare_fails() {
+        let keyshare = KeyShare::from(Scalar::random(&mut thread_rng()));
+        let other = KeyShare::from(Scalar::random(&mut thread_rng()));
+        let coefficient = Scalar::from(7u64);
+        let queries = queries(20);
+        let hash_parts = respond(&other, &coefficient, &queries);
+
+        let proof = other.prove_responses(&coefficient, &queries, &hash_parts);
+        let commitment = keyshare.multiply_by_base();
+        assert!(!proof.verify(&commitment, &coefficient, &queries, &hash_parts));
+    }
+
+    #[test]
+    fn truncated_response_fails() {
+        let keyshare = KeyShare::from(Scalar::random(&mut thread_rng()));
+        let coefficient = Scalar::from(7u64);
+        let queries = queries(20);
+        let hash_parts = respond(&keyshare, &coefficient, &queries);
+
+        let proof = keyshare.prove_responses(&coefficient, &queries, &hash_parts);
+        let commitment = keyshare.multiply_by_base();
+        assert!(!proof.verify(&commitment, &coefficient, &queries, &hash_parts[1..]));
+    }
+}
@@ -5,6 +5,20 @@ pub mod lagrange;
 pub mod party;
 #[macro_use]
 pub mod prf;
+#[cfg(feature = "zk")]
+pub mod proof_backend;
+#[cfg(feature = "zk")]
+pub mod proof_bundle;
+#[cfg(feature = "zk_types")]
+pub mod proof_inputs;
+#[cfg(feature = "zk_types")]
+pub mod proof_output;
+#[cfg(feature = "zk_types")]
+pub mod public_values;
 pub mod active_security;
+pub mod dkg;
+pub mod dleq;
 pub mod shims;
 pub mod tagged;
+#[cfg(feature = "zk_types")]
+pub mod window_commitment;
@@ -1,16 +1,10 @@
 // Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-// added packages for file write
-use serde_json;
-use std::fs::{self, File};
-use std::io::{self, Read, BufWriter};
-use std::path::Path;
-
-// added packages for SP1 'Script'
-use std::env;
-use clap::Parser;
-use sp1_sdk::{include_elf, utils, ProverClient, SP1Stdin, SP1ProofWithPublicValues, SP1VerifyingKey};
+#[cfg(feature = "zk")]
+use alloy_sol_types::SolType;
+#[cfg(feature = "zk")]
+use sp1_sdk::{SP1ProofWithPublicValues, SP1VerifyingKey};
 
 use std::collections::BTreeMap;
 use std::error::Error;
@@ -27,12 +21,26 @@ use rand::{rngs::OsRng, Rng};
 use rand::{CryptoRng, RngCore};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use sha3::Sha3_512;
+#[cfg(feature = "zk")]
+use tracing::{info_span, warn};
 
 use crate::active_security::{ActiveSecurityKey, RandomizedTarget, SerializableRandomizedTarget};
+use crate::dleq::DleqProof;
 #[cfg(any(feature = "centralized_keygen", test))]
 use crate::lagrange::evaluate_lagrange_polynomial;
 use crate::party::{KeyserverId, KeyserverIdSet};
+#[cfg(feature = "zk")]
+use crate::proof_backend::{
+    ExecuteBackend, MockBackend, ProofBackend, ProofBackendError, ProofMismatch, Sp1Backend,
+    CHECKSUM_ELF, HASH_ELF,
+};
+#[cfg(feature = "zk_types")]
+use crate::proof_inputs::{ChecksumProofInputs, HashProofInputs, WindowInput};
+#[cfg(feature = "zk")]
+use crate::public_values::{ChecksumPublicValues, HashPublicValues};
 use crate::tagged::{HashTag, TaggedHash};
+#[cfg(feature = "zk_types")]
+use crate::window_commitment::{OrderCommitment, SALT_SIZE};
 
 /// The probability that a malicious party could evade active security is 2^(-SECURITY_PARAMETER).
 /// Values of 4N+2 for N=0,1,... will maximise security vs speed.
@@ -47,11 +55,6 @@ impl Query {
     pub fn hash_from_string(seq: &str) -> Self {
         Self(RistrettoPoint::hash_from_bytes::<Sha3_512>(seq.as_bytes()).compress())
     }
-
-    // Added Query method for sential value to be used in SP1
-    pub fn sentinel() -> Self {
-        Query(CompressedRistretto::default())
-    }
 }
 
 /// Response from a keyholder: (H(x)^r)^{f(i)*c_i}
@@ -81,6 +84,21 @@ impl KeyShare {
     pub fn multiply_by_base(&self) -> RistrettoPoint {
         RistrettoPoint::mul_base(&self.0)
     }
+
+    /// Proves that `hash_parts` are `queries` put through
+    /// [`Self::apply_query_and_lagrange_coefficient`] with `lagrange_coefficient`.
+    pub fn prove_responses(
+        &self,
+        lagrange_coefficient: &Scalar,
+        queries: &[Query],
+        hash_parts: &[HashPart],
+    ) -> DleqProof {
+        DleqProof::prove(&self.0, lagrange_coefficient, queries, hash_parts)
+    }
+
+    pub(crate) fn to_scalar(self) -> Scalar {
+        self.0
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -228,18 +246,47 @@ impl fmt::Display for QueryError {
 /// An input to the aggregation program.
 ///
 /// Consists of a proof and a verification key.
-// #[cfg(feature = "sp1")]
+#[cfg(feature = "zk")]
 #[derive(Serialize, Deserialize, Clone)]
 pub struct VerificationInput {
     pub proof: SP1ProofWithPublicValues,
     pub vk: SP1VerifyingKey,
 }
 
+/// How the SP1 programs attesting to a screening are run.
+#[cfg(feature = "zk")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProofMode {
+    /// Execute the hash and checksum programs and check their outputs, without producing any
+    /// proofs. The verification program is not run, since it needs their proofs to aggregate.
+    #[default]
+    Execute,
+    /// Produce proofs with SP1's mock prover. The public values are real, but the proofs are not.
+    Mock,
+    /// Produce real compressed proofs, suitable for recursive aggregation.
+    Compressed,
+}
+
+#[cfg(feature = "zk")]
+impl ProofMode {
+    /// The backend that runs programs in this mode.
+    pub fn backend(self) -> Box<dyn ProofBackend> {
+        match self {
+            ProofMode::Execute => Box::new(ExecuteBackend::new()),
+            ProofMode::Mock => Box::new(MockBackend::new()),
+            ProofMode::Compressed => Box::new(Sp1Backend::new()),
+        }
+    }
+}
+
 // Added this struct for serialization of QueryStateSet
 #[derive(Serialize, Deserialize)]
 pub struct SerializableQueryStateSet {
     querystates: Vec<([u8; 4], SerializableQueryState)>,
     pub randomized_target: SerializableRandomizedTarget,
+    #[cfg(feature = "zk_types")]
+    pub order_commitment: Option<OrderCommitment>,
 }
 
 impl SerializableQueryStateSet {
@@ -263,32 +310,200 @@ impl SerializableQueryStateSet {
                 })
                 .collect(),
             randomized_target: self.randomized_target.to_randomized_target(),
+            #[cfg(feature = "zk_types")]
+            order_commitment: self.order_commitment,
         }
     }
 }
 
+/// Fails with `mismatch` unless what a program committed to `matches` the local computation.
+#[cfg(feature = "zk")]
+fn ensure_match(matches: bool, mismatch: ProofMismatch) -> Result<(), ProofBackendError> {
+    if matches {
+        Ok(())
+    } else {
+        warn!(%mismatch, "proof does not match local computation");
+        Err(ProofBackendError::Mismatch(mismatch))
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct QueryStateSet {
     querystates: Vec<(Option<HashTag>, QueryState)>,
     pub randomized_target: RandomizedTarget,
+    #[cfg(feature = "zk_types")]
+    order_commitment: Option<OrderCommitment>,
 }
 
 impl QueryStateSet {
-    #[cfg(feature = "sp1")]
+    /// Blinds the given windows into queries, and runs the hash and checksum programs over
+    /// exactly those windows and blinding factors on `backend`. The hash program is run once per
+    /// chunk of `chunk_size` windows, which must be a power of two. It also commits to the
+    /// windows themselves under a fresh salt, kept in [`Self::order_commitment`].
+    ///
+    /// Returns the hash proof of each chunk followed by the checksum proof, to be aggregated, or
+    /// nothing if `backend` is execute-only. Fails if the programs committed to anything other
+    /// than what was computed here.
+    #[cfg(feature = "zk")]
     pub fn from_iter(
         iter: impl IntoIterator<Item = (HashTag, impl AsRef<[u8]>)>,
         required_keyholders: usize,
         active_security_key: ActiveSecurityKey,
-    ) -> (Self, Vec<VerificationInput>) {
-        // The ELF we want to execute inside the zkVM, ensure it's up to date (auto-build disabled)
-        const HASH_ELF: &[u8] = include_bytes!("../../../hash_proof/elf/riscv32im-succinct-zkvm-elf");
-        const CHECKSUM_ELF: &[u8] = include_bytes!("../../../checksum_proof/elf/riscv32im-succinct-zkvm-elf");
+        chunk_size: usize,
+        backend: &dyn ProofBackend,
+    ) -> Result<(Self, Vec<VerificationInput>), ProofBackendError> {
+        if !chunk_size.is_power_of_two() {
+            return Err(ProofBackendError::InvalidChunkSize(chunk_size));
+        }
+        let (set, hash_inputs, checksum_inputs) =
+            Self::prepare(iter, required_keyholders, active_security_key);
+
+        // Run the hash_proof program over each chunk of the windows and blinding factors in use
+        let mut chunk_public_values = Vec::new();
+        let mut inputs = Vec::new();
+        let chunks = hash_inputs.chunks(chunk_size);
+        let chunk_count = chunks.len();
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let _span = info_span!("hash_proof", chunk = i, chunks = chunk_count).entered();
+            let (public_values, input) = backend.run(HASH_ELF, chunk.stdin())?;
+            let public_values = HashPublicValues::abi_decode(public_values.as_slice(), true)
+                .map_err(|e| ProofBackendError::MalformedPublicValues(e.to_string()))?;
+            chunk_public_values.push(public_values);
+            inputs.extend(input);
+        }
+        // ...and combine them into what a single run over the whole order would have committed
+        let hash_public_values = HashPublicValues::combine_chunks(&chunk_public_values)
+            .ok_or_else(|| {
+                ProofBackendError::MalformedPublicValues("hash proof chunks do not combine".into())
+            })?;
+
+        // Extract only queries from querystates states, leaving out the checksum state
+        let local_queries: Vec<[u8; 32]> = set.querystates[..set.len() - 1]
+            .iter()
+            .map(|(_, state)| *state.query.as_bytes())
+            .collect();
+        let proof_queries: Vec<[u8; 32]> =
+            hash_public_values.queries.iter().map(|q| q.0).collect();
+
+        // The hash proofs must attest to exactly what is sent to the keyservers
+        ensure_match(proof_queries == local_queries, ProofMismatch::HashQueries)?;
+        let order_commitment = set.order_commitment.as_ref().unwrap();
+        ensure_match(
+            hash_public_values.windowRoot.0 == order_commitment.root,
+            ProofMismatch::WindowCommitment,
+        )?;
+        // The checksum inputs derived from the windows
+        let local_checksum_inputs = checksum_inputs.checksum_inputs();
+        ensure_match(
+            hash_public_values.checksum_inputs() == local_checksum_inputs,
+            ProofMismatch::HashChecksumInputs,
+        )?;
+
+        // Run the checksum_proof program with the same sum and blinding factor
+        let (checksum_public_values, checksum_input) = {
+            let _span = info_span!("checksum_proof").entered();
+            backend.run(CHECKSUM_ELF, checksum_inputs.stdin())?
+        };
+        let checksum_public_values =
+            ChecksumPublicValues::abi_decode(checksum_public_values.as_slice(), true)
+                .map_err(|e| ProofBackendError::MalformedPublicValues(e.to_string()))?;
+
+        // Confirm the checksum_query generated in the program maches the query generated locally
+        let (_, local_checksum_state) = set.querystates.last().unwrap();
+        ensure_match(
+            *local_checksum_state.query.as_bytes() == checksum_public_values.query.0,
+            ProofMismatch::ChecksumQuery,
+        )?;
+        // ...and it was computed from the same inputs the hash proof committed
+        ensure_match(
+            checksum_public_values.checksum_inputs() == local_checksum_inputs,
+            ProofMismatch::ChecksumInputs,
+        )?;
+        ensure_match(
+            checksum_public_values.keyserverCommitmentHash.0
+                == checksum_inputs.active_security_key.commitment_hash(),
+            ProofMismatch::ActiveSecurityKey,
+        )?;
+
+        // The hash proofs followed by the checksum proof, as the aggregation program expects
+        inputs.extend(checksum_input);
+
+        Ok((set, inputs))
+    }
+
+    /// Blinds the given windows into queries, without running any programs, as the classic
+    /// `/screen` flow does.
+    pub fn new(
+        iter: impl IntoIterator<Item = (HashTag, impl AsRef<[u8]>)>,
+        required_keyholders: usize,
+        active_security_key: ActiveSecurityKey,
+    ) -> Self {
+        let (set, _, _) = Self::blind(
+            iter,
+            required_keyholders,
+            &active_security_key,
+            |_, _, _| {},
+        );
+        set
+    }
+
+    /// Blinds the given windows into queries, without running any programs.
+    ///
+    /// Returns the private inputs the hash and checksum programs must be run with for their
+    /// proofs to match this set; [`Self::from_iter`] is this followed by running both. It also
+    /// commits to the windows themselves under a fresh salt, kept in
+    /// [`Self::order_commitment`].
+    #[cfg(feature = "zk_types")]
+    pub fn prepare(
+        iter: impl IntoIterator<Item = (HashTag, impl AsRef<[u8]>)>,
+        required_keyholders: usize,
+        active_security_key: ActiveSecurityKey,
+    ) -> (Self, HashProofInputs, ChecksumProofInputs) {
+        // The windows are committed to under a salt, so the public root reveals nothing about them
+        let mut salt = [0u8; SALT_SIZE];
+        OsRng.fill(&mut salt);
+
+        let mut windows = Vec::new();
+        let (mut set, random_modifier, sum) = Self::blind(
+            iter,
+            required_keyholders,
+            &active_security_key,
+            |window, state, verification_factor| {
+                // The program recomputes each query from the random blinding factor generated in
+                // from_rp, and the checksum sum from the same verification factors
+                windows.push(WindowInput {
+                    window: window.to_vec(),
+                    blinding_factor: state.blinding_factor,
+                    verification_factor,
+                });
+            },
+        );
+
+        let hash_inputs = HashProofInputs { salt, windows };
+        set.order_commitment = Some(hash_inputs.order_commitment());
 
-        // The input stream that the program will read from using `sp1_zkvm::io::read`. Note that the
-        // types of the elements in the input stream must match the types being read in the program.
-        let mut hash_stdin = SP1Stdin::new();
-        let mut checksum_stdin = SP1Stdin::new();
+        let (_, local_checksum_state) = set.querystates.last().unwrap();
+        let checksum_inputs = ChecksumProofInputs {
+            random_modifier,
+            active_security_key,
+            sum,
+            verification_factor: local_checksum_state.verification_factor,
+            blinding_factor: local_checksum_state.blinding_factor,
+        };
+        (set, hash_inputs, checksum_inputs)
+    }
 
+    /// Blinds the given windows into queries, followed by the checksum query. `on_window` is
+    /// called with each window, its query state and its verification factor.
+    ///
+    /// Returns the set along with the random modifier and the verification sum the checksum
+    /// query was derived from.
+    fn blind(
+        iter: impl IntoIterator<Item = (HashTag, impl AsRef<[u8]>)>,
+        required_keyholders: usize,
+        active_security_key: &ActiveSecurityKey,
+        mut on_window: impl FnMut(&[u8], &QueryState, Scalar),
+    ) -> (Self, Scalar, RistrettoPoint) {
         let iter = iter.into_iter();
         let estimated_size = iter.size_hint().0 + 1;
         let mut querystates = Vec::with_capacity(estimated_size);
@@ -301,10 +516,6 @@ impl QueryStateSet {
         let mut concat_queries = Vec::new();
 
         for (tag, b) in iter {
-            let byte_vec = b.as_ref().to_vec();
-            // Write these bytes to the input stream
-            hash_stdin.write(&byte_vec);
-
             let point = RistrettoPoint::hash_from_bytes::<Sha3_512>(b.as_ref());
             let verification_factor = Scalar::from(rng.gen_range(0u32..=verification_factor_max));
 
@@ -314,12 +525,9 @@ impl QueryStateSet {
                 &point,
                 &Scalar::ZERO,
             );
-            
+
             let state = QueryState::from_rp(point, required_keyholders, verification_factor);
-            
-            // Retrieve the random blinding factor generated in from_rp, convert to 
-            // a serializable type and write it
-            hash_stdin.write(&state.blinding_factor.as_bytes());
+            on_window(b.as_ref(), &state, verification_factor);
 
             // Concatenate all queries
             concat_queries.extend_from_slice(state.query.0.as_bytes());
@@ -327,87 +535,9 @@ impl QueryStateSet {
             querystates.push((Some(tag), state));
         }
 
-        // After writing all byte arrays, write a sentinel value
-        let sentinel: Vec<u8> = Vec::new();
-        hash_stdin.write(&sentinel);
-
-        // Create a `ProverClient` method
-        let client = ProverClient::new();
-
-        // DEBUGGING SECTION START
-        // Execute the hash_proof program using the `ProverClient.execute` method,
-        let (mut hash_public_values, execution_report) = client.execute(HASH_ELF, hash_stdin.clone()).run().unwrap();
-        println!(
-            "Hash program executed with {} cycles",
-            execution_report.total_instruction_count() + execution_report.total_syscall_count()
-        );
-
-        // Read the proof hashes from the output stream until sentinel value is reached
-        let mut proof_quries = Vec::with_capacity(estimated_size);
-        let sentinel = Query::sentinel();
-
-        loop {
-            let read_val = hash_public_values.read::<Query>();
-            if read_val == sentinel {
-                break;
-            }
-            proof_quries.push(read_val)
-        }
-
-        // Extract only queries from querystates states
-        let local_queries: Vec<Query> = querystates.iter().map(|(_, state)| state.query.clone()).collect();
-
-        // Confirm matching outputs, for debugging
-        if proof_quries == local_queries {
-            println!("Hash proof: Hashes match.");
-        } else {
-            println!("Hash proof: Hashes do not match.");
-        }   
-
-        // // Determine if the program is using the precompiles
-        // for (code, count) in &(*execution_report.syscall_counts) {
-        //     println!("Syscall {code:?} was called {count} times");
-        // }
-
-        // Read in previously generated proof to avoid unecessary computation
-        let (hash_pk, hash_vk) = client.setup(HASH_ELF);
-        let deserialized_hash_proof =
-            SP1ProofWithPublicValues::load("/client/output/hash_proof-with-pis.bin").expect("loading proof failed");
-        let input_1 = VerificationInput {
-                proof: deserialized_hash_proof,
-                vk: hash_vk.clone(),
-        };
-        // DEBUGGING SECTION END
-
-        // // PROOF GENERATION SECTION START
-        // //Generate the proof for the given program and input
-        // let (hash_pk, hash_vk) = client.setup(HASH_ELF);
-
-        // // Note that we use the "compressed" proof type, which is necessary for aggregation in SP1
-        // let mut hash_proof = client.prove(&hash_pk, hash_stdin).compressed().run().expect("proving failed");
-        // println!("Generated hash_proof");
-
-        // // Verify proof and public values
-        // client.verify(&hash_proof, &hash_vk).expect("verification failed");
-
-        // // Save the proof to file
-        // hash_proof.save("/client/output/hash_proof-with-pis.bin").expect("saving proof failed");
-
-        // // Create a VerificationInputs struct for the hash_proof
-        // let input_1 = VerificationInput {
-        //     proof: hash_proof,
-        //     vk: hash_vk.clone(),
-        // };
-        // // PROOF GENERATION SECTION END
-
         // Hash the concatenated queries (to be used as random_modifier)
         let hashed_concat_quries = Scalar::hash_from_bytes::<Sha3_512>(&concat_queries);
 
-        // write needed values to the input stream of checksum proof
-        checksum_stdin.write(&hashed_concat_quries.as_bytes());
-        checksum_stdin.write(&active_security_key);
-        checksum_stdin.write(&sum.compress().as_bytes());
-
         // Added a parameter in randomized_target to accept a precomuted random_modifier
         let randomized_target = active_security_key.randomized_target(hashed_concat_quries);
 
@@ -416,74 +546,28 @@ impl QueryStateSet {
         let x_0 = checksum * verification_factor_0.invert();
         let local_checksum_state = QueryState::from_rp(x_0, required_keyholders, verification_factor_0);
 
-        // write the remaining values to the input stream
-        checksum_stdin.write(&verification_factor_0.as_bytes());
-        checksum_stdin.write(&local_checksum_state.blinding_factor.as_bytes());
-
-        // DEBUGGING SECTION START
-        // Execute the checksum_proof program using the `ProverClient.execute` method,
-        let (mut checksum_public_values, execution_report) = client.execute(CHECKSUM_ELF, checksum_stdin.clone()).run().unwrap();
-        println!(
-            "Checksum program executed with {} cycles",
-            execution_report.total_instruction_count() + execution_report.total_syscall_count()
-        );
-
-        // Read the checksum_query that is generated in the program
-        let proof_checksum_query = checksum_public_values.read::<Query>();
-
-        // Confirm this output maches the query generated locally
-        if local_checksum_state.query.clone() == proof_checksum_query {
-            println!("Checksum proof: Checksums match.");
-        } else {
-            println!("Checksum proof: Checksums do not match.");
-        }
-
-        // Read in previously generated proof to avoid unecessary computation
-        let (checksum_pk, checksum_vk) = client.setup(CHECKSUM_ELF);
-        let deserialized_checksum_proof =
-            SP1ProofWithPublicValues::load("/client/output/checksum-proof-with-pis.bin").expect("loading proof failed");
-        let input_2 = VerificationInput {
-            proof: deserialized_checksum_proof,
-            vk: checksum_vk.clone(),
-        };
-        // DEBUGGING SECTION END
-
         // Note: required secureDNA line, after check to not be consumed, DO NOT ALTER
         querystates.push((None, local_checksum_state));
 
-        // // PROOF GENERATION SECTION START
-        // // Generate the proof for the given program and input
-        // let (checksum_pk, checksum_vk) = client.setup(CHECKSUM_ELF);
-        // let mut checksum_proof = client.prove(&checksum_pk, checksum_stdin).compressed().run().expect("proving failed");
-        // println!("Generated checksum_proof");
-
-        // // Verify proof and public values
-        // client.verify(&checksum_proof, &checksum_vk).expect("verification failed");
-
-        // // Save the proof to file
-        // checksum_proof.save("/client/output/checksum-proof-with-pis.bin").expect("saving proof failed");
-
-        // // Create a VerificationInputs struct for the checksum_proof
-        // let input_2 = VerificationInput {
-        //     proof: checksum_proof,
-        //     vk: checksum_vk.clone(),
-        // };
-        // // PROOF GENERATION SECTION END
-
-        // Create the a vector of VerificationInputs
-        let inputs = vec![input_1, input_2];
-
-        (Self {
+        let set = Self {
             querystates,
             randomized_target,
-        }, 
-        inputs)
+            #[cfg(feature = "zk_types")]
+            order_commitment: None,
+        };
+        (set, hashed_concat_quries, sum)
     }
 
     pub fn len(&self) -> usize {
         self.querystates.len()
     }
 
+    /// The salted commitment to the windows of this set, if it was created by `prepare`.
+    #[cfg(feature = "zk_types")]
+    pub fn order_commitment(&self) -> Option<&OrderCommitment> {
+        self.order_commitment.as_ref()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
@@ -576,6 +660,8 @@ impl QueryStateSet {
                 .map(|(tag, qs)| (*tag.unwrap_or_default().as_bytes(), qs.to_serializable()))
                 .collect(),
             randomized_target: self.randomized_target.to_serializable_randomized_target(),
+            #[cfg(feature = "zk_types")]
+            order_commitment: self.order_commitment,
         }
     }
 }
@@ -671,6 +757,12 @@ impl FromStr for KeyShare {
     }
 }
 
+impl Serialize for KeyShare {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
 impl<'de> Deserialize<'de> for KeyShare {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -682,13 +774,13 @@ impl<'de> Deserialize<'de> for KeyShare {
 }
 
 impl Query {
-    fn to_rp(self) -> RistrettoPoint {
+    pub(crate) fn to_rp(self) -> RistrettoPoint {
         self.0.decompress().unwrap() // already verified to be valid via try_from_buf
     }
 }
 
 impl HashPart {
-    fn to_rp(self) -> RistrettoPoint {
+    pub(crate) fn to_rp(self) -> RistrettoPoint {
         self.0.decompress().unwrap() // already verified to be valid via try_from_buf
     }
 }
@@ -807,6 +899,8 @@ impls_for_ristretto_point!(CompletedHashValue);
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "zk")]
+    use crate::proof_inputs::DEFAULT_CHUNK_SIZE;
     use curve25519_dalek::scalar::Scalar;
     use itertools::Itertools;
     use quickcheck::{quickcheck, Arbitrary, Gen};
@@ -939,14 +1033,22 @@ mod tests {
         windows: impl IntoIterator<Item = impl AsRef<[u8]>>,
         target: ActiveSecurityKey,
     ) -> Result<Vec<CompletedHashValue>, QueryError> {
-        let mut querystates = QueryStateSet::from_iter(
-            windows
-                .into_iter()
-                .enumerate()
-                .map(|(i, x)| (HashTag::new(i == 0, 0, i), x)),
+        let windows = windows
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| (HashTag::new(i == 0, 0, i), x));
+        #[cfg(feature = "zk")]
+        let (mut querystates, _) = QueryStateSet::from_iter(
+            windows,
             keyshares.chosen_keyservers.len(),
             target,
-        );
+            DEFAULT_CHUNK_SIZE,
+            &ExecuteBackend::new(),
+        )
+        .expect("executing the hash and checksum programs failed");
+        #[cfg(not(feature = "zk"))]
+        let mut querystates =
+            QueryStateSet::new(windows, keyshares.chosen_keyservers.len(), target);
         let keyserver_ids: KeyserverIdSet = keyshares
             .chosen_keyservers
             .iter()
@@ -1042,6 +1144,199 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "zk")]
+    #[test]
+    fn chunked_hash_proofs_combine_into_the_whole_order() {
+        let keys = KeyShares::random(&mut OsRng);
+        let keyholders_required = NonZeroU32::new(keys.chosen_keyservers.len() as u32).unwrap();
+        let target = ActiveSecurityKey::from_secret_and_keyshares(
+            &keys.secret,
+            &keys.shares,
+            keyholders_required,
+        )
+        .unwrap();
+        let messages = ["foobar", "acgtacgtacgt", "", "xyzzy", "wizards"];
+
+        let (set, hash_inputs, checksum_inputs) = QueryStateSet::prepare(
+            messages
+                .iter()
+                .enumerate()
+                .map(|(i, x)| (HashTag::new(i == 0, 0, i), x)),
+            keys.chosen_keyservers.len(),
+            target,
+        );
+        let backend = ExecuteBackend::new();
+        let chunks: Vec<_> = hash_inputs
+            .chunks(2)
+            .iter()
+            .map(|chunk| {
+                let (public_values, _) = backend.run(HASH_ELF, chunk.stdin()).unwrap();
+                HashPublicValues::abi_decode(public_values.as_slice(), true).unwrap()
+            })
+            .collect();
+        assert_eq!(chunks.len(), 3);
+
+        let combined = HashPublicValues::combine_chunks(&chunks).unwrap();
+        assert_eq!(combined.windowRoot.0, set.order_commitment().unwrap().root);
+        assert_eq!(combined.checksum_inputs(), checksum_inputs.checksum_inputs());
+        let queries: Vec<[u8; 32]> = set.queries().map(|q| *q.as_bytes()).collect();
+        let combined_queries: Vec<[u8; 32]> = combined.queries.iter().map(|q| q.0).collect();
+        assert_eq!(combined_queries, queries[..messages.len()]);
+    }
+
+    #[cfg(feature = "zk")]
+    #[test]
+    fn rejects_chunk_sizes_that_are_not_powers_of_two() {
+        let keys = KeyShares::random(&mut OsRng);
+        let keyholders_required = NonZeroU32::new(keys.chosen_keyservers.len() as u32).unwrap();
+        let target = ActiveSecurityKey::from_secret_and_keyshares(
+            &keys.secret,
+            &keys.shares,
+            keyholders_required,
+        )
+        .unwrap();
+        let result = QueryStateSet::from_iter(
+            [(HashTag::new(true, 0, 0), "foobar")],
+            keys.chosen_keyservers.len(),
+            target,
+            3,
+            &ExecuteBackend::new(),
+        );
+        assert!(matches!(result, Err(ProofBackendError::InvalidChunkSize(3))));
+    }
+
+    /// Executes programs, but rewrites the public values committed by runs of `elf`, as a
+    /// dishonest prover could.
+    #[cfg(feature = "zk")]
+    struct TamperingBackend {
+        elf: &'static [u8],
+        tamper: fn(&[u8]) -> Vec<u8>,
+        inner: ExecuteBackend,
+    }
+
+    #[cfg(feature = "zk")]
+    impl ProofBackend for TamperingBackend {
+        fn run(
+            &self,
+            elf: &[u8],
+            stdin: sp1_sdk::SP1Stdin,
+        ) -> Result<(sp1_sdk::SP1PublicValues, Option<VerificationInput>), ProofBackendError>
+        {
+            let (public_values, input) = self.inner.run(elf, stdin)?;
+            if elf != self.elf {
+                return Ok((public_values, input));
+            }
+            let tampered = (self.tamper)(public_values.as_slice());
+            Ok((sp1_sdk::SP1PublicValues::from(&tampered), input))
+        }
+
+        fn verify(&self, input: &VerificationInput) -> Result<(), ProofBackendError> {
+            self.inner.verify(input)
+        }
+
+        fn verifying_key(&self, elf: &[u8]) -> SP1VerifyingKey {
+            self.inner.verifying_key(elf)
+        }
+    }
+
+    #[cfg(feature = "zk")]
+    fn from_iter_with_tampered(
+        elf: &'static [u8],
+        tamper: fn(&[u8]) -> Vec<u8>,
+    ) -> Result<(QueryStateSet, Vec<VerificationInput>), ProofBackendError> {
+        let keys = KeyShares::random(&mut OsRng);
+        let keyholders_required = NonZeroU32::new(keys.chosen_keyservers.len() as u32).unwrap();
+        let target = ActiveSecurityKey::from_secret_and_keyshares(
+            &keys.secret,
+            &keys.shares,
+            keyholders_required,
+        )
+        .unwrap();
+        let backend = TamperingBackend {
+            elf,
+            tamper,
+            inner: ExecuteBackend::new(),
+        };
+        QueryStateSet::from_iter(
+            ["foobar", "acgtacgtacgt", "xyzzy"]
+                .iter()
+                .enumerate()
+                .map(|(i, x)| (HashTag::new(i == 0, 0, i), x)),
+            keys.chosen_keyservers.len(),
+            target,
+            DEFAULT_CHUNK_SIZE,
+            &backend,
+        )
+    }
+
+    #[cfg(feature = "zk")]
+    #[test]
+    fn tampered_hash_proofs_are_rejected() {
+        fn swap_queries(public_values: &[u8]) -> Vec<u8> {
+            let mut values = HashPublicValues::abi_decode(public_values, true).unwrap();
+            values.queries.swap(0, 1);
+            HashPublicValues::abi_encode(&values)
+        }
+        fn replace_window_root(public_values: &[u8]) -> Vec<u8> {
+            let mut values = HashPublicValues::abi_decode(public_values, true).unwrap();
+            values.windowRoot.0 = [0; 32];
+            HashPublicValues::abi_encode(&values)
+        }
+        fn replace_checksum_sum(public_values: &[u8]) -> Vec<u8> {
+            let mut values = HashPublicValues::abi_decode(public_values, true).unwrap();
+            values.checksumSum.0 = [0; 32];
+            HashPublicValues::abi_encode(&values)
+        }
+
+        let cases: [(fn(&[u8]) -> Vec<u8>, ProofMismatch); 3] = [
+            (swap_queries, ProofMismatch::HashQueries),
+            (replace_window_root, ProofMismatch::WindowCommitment),
+            (replace_checksum_sum, ProofMismatch::HashChecksumInputs),
+        ];
+        for (tamper, expected) in cases {
+            let result = from_iter_with_tampered(HASH_ELF, tamper);
+            assert!(
+                matches!(result, Err(ProofBackendError::Mismatch(m)) if m == expected),
+                "expected {expected} mismatch, got {:?}",
+                result.map(|_| ())
+            );
+        }
+    }
+
+    #[cfg(feature = "zk")]
+    #[test]
+    fn tampered_checksum_proofs_are_rejected() {
+        fn replace_query(public_values: &[u8]) -> Vec<u8> {
+            let mut values = ChecksumPublicValues::abi_decode(public_values, true).unwrap();
+            values.query.0 = [0; 32];
+            ChecksumPublicValues::abi_encode(&values)
+        }
+        fn replace_random_modifier(public_values: &[u8]) -> Vec<u8> {
+            let mut values = ChecksumPublicValues::abi_decode(public_values, true).unwrap();
+            values.randomModifier.0 = [0; 32];
+            ChecksumPublicValues::abi_encode(&values)
+        }
+        fn replace_commitment_hash(public_values: &[u8]) -> Vec<u8> {
+            let mut values = ChecksumPublicValues::abi_decode(public_values, true).unwrap();
+            values.keyserverCommitmentHash.0 = [0; 32];
+            ChecksumPublicValues::abi_encode(&values)
+        }
+
+        let cases: [(fn(&[u8]) -> Vec<u8>, ProofMismatch); 3] = [
+            (replace_query, ProofMismatch::ChecksumQuery),
+            (replace_random_modifier, ProofMismatch::ChecksumInputs),
+            (replace_commitment_hash, ProofMismatch::ActiveSecurityKey),
+        ];
+        for (tamper, expected) in cases {
+            let result = from_iter_with_tampered(CHECKSUM_ELF, tamper);
+            assert!(
+                matches!(result, Err(ProofBackendError::Mismatch(m)) if m == expected),
+                "expected {expected} mismatch, got {:?}",
+                result.map(|_| ())
+            );
+        }
+    }
+
     #[cfg(feature = "centralized_keygen")]
     #[test]
     fn generate_keyshares_requires_enough_keyholders_for_quorum() {
@@ -0,0 +1,320 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Backends that run the screening programs and attest to their runs.
+//!
+//! The programs are built by this crate's build script. With `SP1_SKIP_PROGRAM_BUILD` set, the
+//! ELFs committed next to each program are embedded instead, and the build fails if they were
+//! built from other sources than the ones in the tree.
+
+use std::error::Error;
+use std::fmt;
+use std::time::Instant;
+
+use sp1_sdk::{include_elf, ProverClient, SP1PublicValues, SP1Stdin, SP1VerifyingKey};
+use tracing::debug;
+
+use crate::prf::VerificationInput;
+
+/// The program that blinds each window into a query, and commits to the windows.
+pub const HASH_ELF: &[u8] = include_elf!("hash_proof_program");
+/// The program that computes the active security checksum query.
+pub const CHECKSUM_ELF: &[u8] = include_elf!("checksum_proof_program");
+/// The program that aggregates the hash and checksum proofs, and incorporates the keyserver
+/// responses into the hashes sent to the HDB.
+pub const VERIFICATION_ELF: &[u8] = include_elf!("verification_proof_program");
+
+/// Runs SP1 programs, and verifies the proofs of those runs.
+pub trait ProofBackend: Send + Sync {
+    /// Runs `elf` on `stdin`, returning the committed public values along with a proof of the
+    /// run, if this backend produces proofs.
+    fn run(
+        &self,
+        elf: &[u8],
+        stdin: SP1Stdin,
+    ) -> Result<(SP1PublicValues, Option<VerificationInput>), ProofBackendError>;
+
+    /// Checks a proof produced by a backend of the same kind.
+    fn verify(&self, input: &VerificationInput) -> Result<(), ProofBackendError>;
+
+    /// The verifying key that runs of `elf` are proven under.
+    fn verifying_key(&self, elf: &[u8]) -> SP1VerifyingKey;
+}
+
+#[derive(Debug, Clone)]
+pub enum ProofBackendError {
+    Execution(String),
+    Proving(String),
+    Verification(String),
+    MalformedPublicValues(String),
+    NoProofs,
+    InvalidChunkSize(usize),
+    Mismatch(ProofMismatch),
+    NotCompressed,
+}
+
+/// Something a program committed to that differs from what was computed outside of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofMismatch {
+    HashQueries,
+    WindowCommitment,
+    HashChecksumInputs,
+    ChecksumQuery,
+    ChecksumInputs,
+    ActiveSecurityKey,
+    IncorporatedHashes,
+}
+
+impl fmt::Display for ProofMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ProofMismatch::HashQueries => "hash proof queries",
+            ProofMismatch::WindowCommitment => "hash proof window commitment",
+            ProofMismatch::HashChecksumInputs => "hash proof checksum inputs",
+            ProofMismatch::ChecksumQuery => "checksum proof query",
+            ProofMismatch::ChecksumInputs => "checksum proof checksum inputs",
+            ProofMismatch::ActiveSecurityKey => "checksum proof active security key",
+            ProofMismatch::IncorporatedHashes => "verification proof hashes",
+        };
+        f.write_str(s)
+    }
+}
+
+impl Error for ProofBackendError {}
+
+impl fmt::Display for ProofBackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofBackendError::Execution(e) => write!(f, "Program execution failed: {e}"),
+            ProofBackendError::Proving(e) => write!(f, "Proving failed: {e}"),
+            ProofBackendError::Verification(e) => write!(f, "Proof did not verify: {e}"),
+            ProofBackendError::MalformedPublicValues(e) => {
+                write!(f, "Program committed malformed public values: {e}")
+            }
+            ProofBackendError::NoProofs => {
+                write!(f, "Execute-only backend cannot verify proofs")
+            }
+            ProofBackendError::InvalidChunkSize(size) => {
+                write!(f, "Proof chunk size {size} is not a power of two")
+            }
+            ProofBackendError::Mismatch(m) => {
+                write!(f, "Proof does not match what was computed locally: {m}")
+            }
+            ProofBackendError::NotCompressed => {
+                write!(f, "Only compressed proofs can be aggregated")
+            }
+        }
+    }
+}
+
+/// Executes programs without proving them. Nothing it returns can be verified.
+pub struct ExecuteBackend {
+    client: ProverClient,
+}
+
+impl ExecuteBackend {
+    pub fn new() -> Self {
+        Self {
+            client: ProverClient::mock(),
+        }
+    }
+}
+
+impl Default for ExecuteBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProofBackend for ExecuteBackend {
+    fn run(
+        &self,
+        elf: &[u8],
+        stdin: SP1Stdin,
+    ) -> Result<(SP1PublicValues, Option<VerificationInput>), ProofBackendError> {
+        let started = Instant::now();
+        let (public_values, execution_report) = self
+            .client
+            .execute(elf, stdin)
+            .run()
+            .map_err(|e| ProofBackendError::Execution(e.to_string()))?;
+        debug!(
+            cycles = execution_report.total_instruction_count(),
+            elapsed = ?started.elapsed(),
+            "executed program"
+        );
+        Ok((public_values, None))
+    }
+
+    fn verify(&self, _input: &VerificationInput) -> Result<(), ProofBackendError> {
+        Err(ProofBackendError::NoProofs)
+    }
+
+    fn verifying_key(&self, elf: &[u8]) -> SP1VerifyingKey {
+        self.client.setup(elf).1
+    }
+}
+
+/// Produces proofs with SP1's mock prover, for testing the full screening flow without proving
+/// hardware.
+///
+/// Programs are really executed, so the public values are exactly what a real proof would
+/// commit to, and verifying keys match those of [`Sp1Backend`]. The proofs themselves are
+/// placeholders: verifying one only checks its shape, and says nothing about the execution.
+/// Sub-proofs passed to an aggregating program are likewise not checked during execution.
+pub struct MockBackend {
+    client: ProverClient,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self {
+            client: ProverClient::mock(),
+        }
+    }
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProofBackend for MockBackend {
+    fn run(
+        &self,
+        elf: &[u8],
+        stdin: SP1Stdin,
+    ) -> Result<(SP1PublicValues, Option<VerificationInput>), ProofBackendError> {
+        let started = Instant::now();
+        let (pk, vk) = self.client.setup(elf);
+        let proof = self
+            .client
+            .prove(&pk, stdin)
+            .compressed()
+            .deferred_proof_verification(false)
+            .run()
+            .map_err(|e| ProofBackendError::Proving(e.to_string()))?;
+        debug!(elapsed = ?started.elapsed(), "mock proved program");
+        let public_values = proof.public_values.clone();
+        Ok((public_values, Some(VerificationInput { proof, vk })))
+    }
+
+    fn verify(&self, input: &VerificationInput) -> Result<(), ProofBackendError> {
+        self.client
+            .verify(&input.proof, &input.vk)
+            .map_err(|e| ProofBackendError::Verification(e.to_string()))
+    }
+
+    fn verifying_key(&self, elf: &[u8]) -> SP1VerifyingKey {
+        self.client.setup(elf).1
+    }
+}
+
+/// Produces real compressed proofs, suitable for recursive aggregation.
+///
+/// Honors the `SP1_PROVER` environment variable, so proving can happen locally or on the
+/// prover network.
+pub struct Sp1Backend {
+    client: ProverClient,
+}
+
+impl Sp1Backend {
+    pub fn new() -> Self {
+        Self {
+            client: ProverClient::new(),
+        }
+    }
+}
+
+impl Default for Sp1Backend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProofBackend for Sp1Backend {
+    fn run(
+        &self,
+        elf: &[u8],
+        stdin: SP1Stdin,
+    ) -> Result<(SP1PublicValues, Option<VerificationInput>), ProofBackendError> {
+        let started = Instant::now();
+        let (pk, vk) = self.client.setup(elf);
+        // The "compressed" proof type is necessary for aggregation in SP1
+        let proof = self
+            .client
+            .prove(&pk, stdin)
+            .compressed()
+            .run()
+            .map_err(|e| ProofBackendError::Proving(e.to_string()))?;
+        debug!(elapsed = ?started.elapsed(), "proved program");
+        let input = VerificationInput { proof, vk };
+        self.verify(&input)?;
+        let public_values = input.proof.public_values.clone();
+        Ok((public_values, Some(input)))
+    }
+
+    fn verify(&self, input: &VerificationInput) -> Result<(), ProofBackendError> {
+        self.client
+            .verify(&input.proof, &input.vk)
+            .map_err(|e| ProofBackendError::Verification(e.to_string()))
+    }
+
+    fn verifying_key(&self, elf: &[u8]) -> SP1VerifyingKey {
+        self.client.setup(elf).1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use curve25519_dalek::scalar::Scalar;
+    use sp1_sdk::HashableKey;
+
+    use super::*;
+
+    fn hash_stdin() -> SP1Stdin {
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&[3u8; 32]);
+        stdin.write(&1u32);
+        stdin.write(&b"ACGT".to_vec());
+        stdin.write(&Scalar::from(7u64).to_bytes());
+        stdin.write(&Scalar::from(11u64).to_bytes());
+        stdin
+    }
+
+    #[test]
+    fn mock_proofs_commit_real_public_values() {
+        let (executed, none) = ExecuteBackend::new().run(HASH_ELF, hash_stdin()).unwrap();
+        assert!(none.is_none());
+
+        let backend = MockBackend::new();
+        let (public_values, input) = backend.run(HASH_ELF, hash_stdin()).unwrap();
+        let input = input.unwrap();
+        assert_eq!(public_values.as_slice(), executed.as_slice());
+        assert_eq!(input.proof.public_values.as_slice(), executed.as_slice());
+        assert_eq!(
+            input.vk.bytes32(),
+            backend.verifying_key(HASH_ELF).bytes32()
+        );
+        backend.verify(&input).unwrap();
+    }
+
+    #[test]
+    fn mock_proofs_are_deterministic() {
+        let backend = MockBackend::new();
+        let (first, _) = backend.run(HASH_ELF, hash_stdin()).unwrap();
+        let (second, _) = backend.run(HASH_ELF, hash_stdin()).unwrap();
+        assert_eq!(first.as_slice(), second.as_slice());
+    }
+
+    #[test]
+    fn execute_backend_cannot_verify() {
+        let backend = MockBackend::new();
+        let (_, input) = backend.run(HASH_ELF, hash_stdin()).unwrap();
+        assert!(matches!(
+            ExecuteBackend::new().verify(&input.unwrap()),
+            Err(ProofBackendError::NoProofs)
+        ));
+    }
+}
@@ -0,0 +1,383 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! A self-contained record of a screening proof, for archiving or handing to an auditor.
+//!
+//! A bundle holds everything needed to check the proof without contacting any server: the
+//! proof and its verifying key, the active security key the keyserver responses were validated
+//! against, and what the HDB published about itself. It is written as JSON or CBOR; the two
+//! carry the same fields, and [`ProofBundle::from_bytes`] reads either.
+
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sp1_sdk::{HashableKey, SP1ProofWithPublicValues, SP1VerifyingKey};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::active_security::ActiveSecurityKey;
+use crate::prf::VerificationInput;
+use crate::proof_backend::{ProofBackend, CHECKSUM_ELF, HASH_ELF, VERIFICATION_ELF};
+use crate::proof_output::{ScreeningProofOutput, SubProofVkeys};
+
+/// The bundle version written by this build. Readers reject any other version.
+pub const PROOF_BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofBundle {
+    pub version: u32,
+    pub request_id: String,
+    /// When screening began, in seconds since the Unix epoch.
+    pub started_at: i64,
+    /// When the proof was produced, in seconds since the Unix epoch.
+    pub proved_at: i64,
+    /// The `bytes32` digest of `vk`, as pinned by the hdbserver and the Solidity verifier.
+    pub vk_hash: String,
+    /// The hex encoded public values of `proof`, readable without decoding the proof.
+    pub public_values: String,
+    /// The HDB commitment the hdbserver published when it screened the request, if any. Only
+    /// attested by the proof when the program screened the hashes itself.
+    pub hdb_commitment: Option<String>,
+    /// The commitments of the keyserver quorum the responses were validated against.
+    pub active_security_key: ActiveSecurityKey,
+    pub vk: SP1VerifyingKey,
+    pub proof: SP1ProofWithPublicValues,
+}
+
+/// How a [`ProofBundle`] is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum BundleFormat {
+    #[default]
+    Json,
+    Cbor,
+}
+
+impl BundleFormat {
+    /// `Cbor` for paths ending in `.cbor`, `Json` otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension == "cbor" => Self::Cbor,
+            _ => Self::Json,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Cbor => "cbor",
+        }
+    }
+}
+
+impl ProofBundle {
+    pub fn new(
+        request_id: String,
+        started_at: i64,
+        proved_at: i64,
+        hdb_commitment: Option<String>,
+        active_security_key: ActiveSecurityKey,
+        input: VerificationInput,
+    ) -> Self {
+        Self {
+            version: PROOF_BUNDLE_VERSION,
+            request_id,
+            started_at,
+            proved_at,
+            vk_hash: input.vk.bytes32(),
+            public_values: hex::encode(input.proof.public_values.as_slice()),
+            hdb_commitment,
+            active_security_key,
+            vk: input.vk,
+            proof: input.proof,
+        }
+    }
+
+    pub fn to_bytes(&self, format: BundleFormat) -> Result<Vec<u8>, ProofBundleError> {
+        match format {
+            BundleFormat::Json => serde_json::to_vec_pretty(self)
+                .map_err(|e| ProofBundleError::Encoding(e.to_string())),
+            BundleFormat::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(self, &mut bytes)
+                    .map_err(|e| ProofBundleError::Encoding(e.to_string()))?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Reads a bundle in either format. JSON bundles start with `{`, which no CBOR map does.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofBundleError> {
+        let is_json = bytes
+            .iter()
+            .find(|b| !b.is_ascii_whitespace())
+            .is_some_and(|&b| b == b'{');
+        let bundle: Self = if is_json {
+            serde_json::from_slice(bytes).map_err(|e| ProofBundleError::Encoding(e.to_string()))?
+        } else {
+            ciborium::from_reader(bytes).map_err(|e| ProofBundleError::Encoding(e.to_string()))?
+        };
+        if bundle.version != PROOF_BUNDLE_VERSION {
+            return Err(ProofBundleError::UnsupportedVersion(bundle.version));
+        }
+        Ok(bundle)
+    }
+
+    /// Checks the bundle using nothing but the screening programs built into this binary, and
+    /// the active security key of the keyservers the verifier trusts.
+    ///
+    /// The proof must be of the verification program, aggregating proofs of the hash and
+    /// checksum programs, and verify under `backend`. The keyserver responses must have been
+    /// validated against `trusted_key`, since the key shipped in the bundle could be one the
+    /// prover made up. Everything else the bundle states must agree with what the proof commits
+    /// to.
+    pub fn verify(
+        &self,
+        backend: &dyn ProofBackend,
+        trusted_key: &ActiveSecurityKey,
+    ) -> Result<BundleReport, ProofBundleError> {
+        if self.vk.bytes32() != self.vk_hash {
+            return Err(ProofBundleError::VkHashMismatch);
+        }
+        let expected_vk_hash = backend.verifying_key(VERIFICATION_ELF).bytes32();
+        if self.vk_hash != expected_vk_hash {
+            return Err(ProofBundleError::UnexpectedProgram {
+                expected: expected_vk_hash,
+                actual: self.vk_hash.clone(),
+            });
+        }
+        let public_values = self.proof.public_values.as_slice();
+        if hex::encode(public_values) != self.public_values {
+            return Err(ProofBundleError::PublicValuesMismatch);
+        }
+
+        backend
+            .verify(&VerificationInput {
+                proof: self.proof.clone(),
+                vk: self.vk.clone(),
+            })
+            .map_err(|e| ProofBundleError::InvalidProof(e.to_string()))?;
+
+        let output = ScreeningProofOutput::decode(public_values)
+            .map_err(|e| ProofBundleError::MalformedPublicValues(e.to_string()))?;
+        let expected_sub_proof_vkeys = SubProofVkeys {
+            hash: backend.verifying_key(HASH_ELF).hash_u32(),
+            checksum: backend.verifying_key(CHECKSUM_ELF).hash_u32(),
+        };
+        if !expected_sub_proof_vkeys.matches(&output.sub_proof_vkeys) {
+            return Err(ProofBundleError::UnexpectedSubProofs);
+        }
+        if output.keyserver_commitment_hash != trusted_key.commitment_hash() {
+            return Err(ProofBundleError::UntrustedActiveSecurityKey);
+        }
+        if self.active_security_key != *trusted_key {
+            return Err(ProofBundleError::ActiveSecurityKeyMismatch);
+        }
+
+        let hdb_commitment_proven = output.hdb_commitment != [0; 32];
+        if hdb_commitment_proven {
+            let proven = hex::encode(output.hdb_commitment);
+            if self.hdb_commitment.as_ref().is_some_and(|c| *c != proven) {
+                return Err(ProofBundleError::HdbCommitmentMismatch);
+            }
+        }
+
+        Ok(BundleReport {
+            bundle: self,
+            output,
+            hdb_commitment_proven,
+        })
+    }
+}
+
+/// The outcome of successfully verifying a [`ProofBundle`].
+#[derive(Debug)]
+pub struct BundleReport<'a> {
+    pub bundle: &'a ProofBundle,
+    pub output: ScreeningProofOutput,
+    /// Whether the proof itself attests to the HDB commitment, rather than the bundle only
+    /// recording what the hdbserver published.
+    pub hdb_commitment_proven: bool,
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    OffsetDateTime::from_unix_timestamp(timestamp)
+        .ok()
+        .and_then(|t| t.format(&Rfc3339).ok())
+        .unwrap_or_else(|| format!("{timestamp} (out of range)"))
+}
+
+impl fmt::Display for BundleReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bundle = self.bundle;
+        let output = &self.output;
+        writeln!(f, "Proof bundle v{} verified", bundle.version)?;
+        writeln!(f, "  request id:        {}", bundle.request_id)?;
+        writeln!(f, "  screening started: {}", format_timestamp(bundle.started_at))?;
+        writeln!(f, "  proof produced:    {}", format_timestamp(bundle.proved_at))?;
+        writeln!(f, "  program vk:        {}", bundle.vk_hash)?;
+        writeln!(f, "  outcome:           {}", output.status)?;
+        writeln!(f, "  windows:           {}", output.window_count)?;
+        writeln!(
+            f,
+            "  order commitment:  {}",
+            hex::encode(output.order_commitment)
+        )?;
+        writeln!(
+            f,
+            "  keyserver key:     {} ({} keyservers)",
+            hex::encode(output.keyserver_commitment_hash),
+            bundle.active_security_key.supported_quorum()
+        )?;
+        match (&bundle.hdb_commitment, self.hdb_commitment_proven) {
+            (_, true) => writeln!(
+                f,
+                "  HDB commitment:    {} (attested by the proof)",
+                hex::encode(output.hdb_commitment)
+            ),
+            (Some(commitment), false) => writeln!(
+                f,
+                "  HDB commitment:    {commitment} (as published by the hdbserver, not attested)"
+            ),
+            (None, false) => writeln!(f, "  HDB commitment:    none"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofBundleError {
+    Encoding(String),
+    UnsupportedVersion(u32),
+    VkHashMismatch,
+    UnexpectedProgram { expected: String, actual: String },
+    PublicValuesMismatch,
+    InvalidProof(String),
+    MalformedPublicValues(String),
+    UnexpectedSubProofs,
+    UntrustedActiveSecurityKey,
+    ActiveSecurityKeyMismatch,
+    HdbCommitmentMismatch,
+}
+
+impl Error for ProofBundleError {}
+
+impl fmt::Display for ProofBundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofBundleError::Encoding(e) => write!(f, "Could not encode or decode bundle: {e}"),
+            ProofBundleError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported bundle version {version}, expected {PROOF_BUNDLE_VERSION}"
+            ),
+            ProofBundleError::VkHashMismatch => {
+                write!(f, "Bundle vk hash does not match its verifying key")
+            }
+            ProofBundleError::UnexpectedProgram { expected, actual } => write!(
+                f,
+                "Proof is not of the verification program: expected vk {expected}, got {actual}"
+            ),
+            ProofBundleError::PublicValuesMismatch => {
+                write!(f, "Bundle public values do not match the proof")
+            }
+            ProofBundleError::InvalidProof(e) => write!(f, "Proof did not verify: {e}"),
+            ProofBundleError::MalformedPublicValues(e) => {
+                write!(f, "Proof committed malformed public values: {e}")
+            }
+            ProofBundleError::UnexpectedSubProofs => {
+                write!(f, "Proof does not aggregate the hash and checksum programs")
+            }
+            ProofBundleError::UntrustedActiveSecurityKey => write!(
+                f,
+                "Keyserver responses were not validated against the trusted active security key"
+            ),
+            ProofBundleError::ActiveSecurityKeyMismatch => write!(
+                f,
+                "Bundle active security key is not the one the responses were validated against"
+            ),
+            ProofBundleError::HdbCommitmentMismatch => write!(
+                f,
+                "Bundle HDB commitment differs from the one the proof attests to"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sp1_sdk::SP1Stdin;
+
+    use super::*;
+    use crate::proof_backend::MockBackend;
+
+    /// A bundle whose proof is of the hash program, which suffices to exercise everything up to
+    /// the program check.
+    fn bundle() -> ProofBundle {
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&[3u8; 32]);
+        stdin.write(&0u32);
+        let (_, input) = MockBackend::new().run(HASH_ELF, stdin).unwrap();
+        ProofBundle::new(
+            "request".into(),
+            1_700_000_000,
+            1_700_000_060,
+            Some("ab".repeat(32)),
+            ActiveSecurityKey::from_commitments(Vec::new()),
+            input.unwrap(),
+        )
+    }
+
+    #[test]
+    fn round_trips_through_both_formats() {
+        let bundle = bundle();
+        for format in [BundleFormat::Json, BundleFormat::Cbor] {
+            let decoded = ProofBundle::from_bytes(&bundle.to_bytes(format).unwrap()).unwrap();
+            assert_eq!(decoded.request_id, bundle.request_id);
+            assert_eq!(decoded.vk_hash, bundle.vk_hash);
+            assert_eq!(decoded.public_values, bundle.public_values);
+            assert_eq!(decoded.hdb_commitment, bundle.hdb_commitment);
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut bundle = bundle();
+        bundle.version = PROOF_BUNDLE_VERSION + 1;
+        let bytes = bundle.to_bytes(BundleFormat::Json).unwrap();
+        assert_eq!(
+            ProofBundle::from_bytes(&bytes).unwrap_err(),
+            ProofBundleError::UnsupportedVersion(PROOF_BUNDLE_VERSION + 1)
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_or_foreign_bundles() {
+        let backend = MockBackend::new();
+        let trusted_key = ActiveSecurityKey::from_commitments(Vec::new());
+        let mut bundle = bundle();
+        assert!(matches!(
+            bundle.verify(&backend, &trusted_key),
+            Err(ProofBundleError::UnexpectedProgram { .. })
+        ));
+
+        bundle.vk_hash = backend.verifying_key(VERIFICATION_ELF).bytes32();
+        assert_eq!(
+            bundle.verify(&backend, &trusted_key).unwrap_err(),
+            ProofBundleError::VkHashMismatch
+        );
+    }
+
+    #[test]
+    fn format_follows_extension() {
+        assert_eq!(
+            BundleFormat::from_path(Path::new("a/b.cbor")),
+            BundleFormat::Cbor
+        );
+        assert_eq!(
+            BundleFormat::from_path(Path::new("a/b.json")),
+            BundleFormat::Json
+        );
+        assert_eq!(BundleFormat::from_path(Path::new("bundle")), BundleFormat::Json);
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! The private inputs of the hash_proof and checksum_proof programs.
+//!
+//! These are produced by [`QueryStateSet::prepare`](crate::prf::QueryStateSet::prepare), and
+//! written to the programs' stdin in exactly the order the programs read them.
+
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+#[cfg(feature = "zk")]
+use sp1_sdk::SP1Stdin;
+
+use crate::active_security::{ActiveSecurityKey, ChecksumInputs};
+use crate::window_commitment::{leaf_hash, merkle_root, OrderCommitment, SALT_SIZE};
+
+/// How many windows each run of the hash program proves, unless configured otherwise.
+pub const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// A window, along with the factors its query was blinded and verified with.
+#[derive(Debug, Clone)]
+pub struct WindowInput {
+    pub window: Vec<u8>,
+    pub blinding_factor: Scalar,
+    pub verification_factor: Scalar,
+}
+
+#[derive(Debug, Clone)]
+pub struct HashProofInputs {
+    /// The salt the windows are committed to under.
+    pub salt: [u8; SALT_SIZE],
+    pub windows: Vec<WindowInput>,
+}
+
+impl HashProofInputs {
+    #[cfg(feature = "zk")]
+    pub fn stdin(&self) -> SP1Stdin {
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&self.salt);
+        // The window count comes first, since a window may itself be empty
+        let window_count = u32::try_from(self.windows.len()).expect("too many windows");
+        stdin.write(&window_count);
+        for input in &self.windows {
+            stdin.write(&input.window);
+            stdin.write(&input.blinding_factor.as_bytes());
+            stdin.write(&input.verification_factor.as_bytes());
+        }
+        stdin
+    }
+
+    /// Splits these inputs into chunks of `chunk_size` windows under the same salt, to be
+    /// proven by one run of the hash program each. An order without windows is a single empty
+    /// chunk.
+    ///
+    /// `chunk_size` must be a power of two, so that the chunks' window roots combine into the
+    /// order's with [`combine_chunk_roots`](crate::window_commitment::combine_chunk_roots).
+    pub fn chunks(&self, chunk_size: usize) -> Vec<HashProofInputs> {
+        assert!(chunk_size.is_power_of_two(), "chunk size must be a power of two");
+        if self.windows.is_empty() {
+            return vec![self.clone()];
+        }
+        self.windows
+            .chunks(chunk_size)
+            .map(|windows| HashProofInputs {
+                salt: self.salt,
+                windows: windows.to_vec(),
+            })
+            .collect()
+    }
+
+    /// The commitment the program will make to these windows.
+    pub fn order_commitment(&self) -> OrderCommitment {
+        let leaves = self
+            .windows
+            .iter()
+            .map(|input| leaf_hash(&self.salt, &input.window))
+            .collect();
+        OrderCommitment {
+            salt: self.salt,
+            root: merkle_root(&self.salt, leaves),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChecksumProofInputs {
+    /// The hash of the concatenated window queries.
+    pub random_modifier: Scalar,
+    pub active_security_key: ActiveSecurityKey,
+    /// The sum of each window's hash point times its verification factor.
+    pub sum: RistrettoPoint,
+    pub verification_factor: Scalar,
+    pub blinding_factor: Scalar,
+}
+
+impl ChecksumProofInputs {
+    #[cfg(feature = "zk")]
+    pub fn stdin(&self) -> SP1Stdin {
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&self.random_modifier.as_bytes());
+        stdin.write(&self.active_security_key);
+        stdin.write(&self.sum.compress().as_bytes());
+        stdin.write(&self.verification_factor.as_bytes());
+        stdin.write(&self.blinding_factor.as_bytes());
+        stdin
+    }
+
+    /// The inputs the hash program will have derived from the same windows.
+    pub fn checksum_inputs(&self) -> ChecksumInputs {
+        ChecksumInputs {
+            random_modifier: self.random_modifier.to_bytes(),
+            sum: self.sum.compress().to_bytes(),
+        }
+    }
+}
@@ -0,0 +1,439 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! The public output of the verification program. This is the one layout the program commits,
+//! and the one that clients and the HDB decode. On the wire it is a
+//! [`ScreeningPublicValues`], ABI-encoded.
+
+use std::error::Error;
+use std::fmt;
+
+use alloy_sol_types::SolType;
+use serde::{Deserialize, Serialize};
+
+use crate::party::KeyserverId;
+use crate::prf::QueryError;
+use crate::public_values::{
+    vkey_digest_from_bytes, vkey_digest_to_bytes, ScreeningPublicValues, VERDICT_DENIED,
+    VERDICT_GRANTED, VERDICT_HASHED, VERDICT_MALFORMED_RESPONSE,
+    VERDICT_MISSING_KEYSERVER_RESPONSE, VERDICT_VALIDATION_FAILED, VERDICT_WRONG_SIZE_RESPONSE,
+};
+use crate::tagged::TaggedHash;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreeningProofOutput {
+    /// The vkey digests of the proofs that were aggregated: a hash proof for each chunk of the
+    /// order, then the checksum proof. Empty if those programs were only executed.
+    pub sub_proof_vkeys: Vec<[u32; 8]>,
+    /// The salted Merkle root over the order's windows, or all zeroes if the client did not
+    /// commit to its windows.
+    pub order_commitment: [u8; 32],
+    /// The HDB build the hashes were screened against, if the program screened them itself.
+    /// All zeroes otherwise.
+    pub hdb_commitment: [u8; 32],
+    /// The [`ActiveSecurityKey::commitment_hash`](crate::active_security::ActiveSecurityKey::commitment_hash)
+    /// of the key the responses were validated against.
+    pub keyserver_commitment_hash: [u8; 32],
+    pub window_count: u32,
+    pub status: ScreeningStatus,
+}
+
+/// The vkey digests of the programs whose proofs the verification program aggregates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubProofVkeys {
+    pub hash: [u32; 8],
+    pub checksum: [u32; 8],
+}
+
+impl SubProofVkeys {
+    /// Whether `vkeys` are those of one or more hash proofs, one per chunk of the order,
+    /// followed by a single checksum proof.
+    pub fn matches(&self, vkeys: &[[u32; 8]]) -> bool {
+        match vkeys.split_last() {
+            Some((checksum, hashes)) => {
+                *checksum == self.checksum
+                    && !hashes.is_empty()
+                    && hashes.iter().all(|hash| *hash == self.hash)
+            }
+            None => false,
+        }
+    }
+}
+
+/// The outcome of incorporating the keyserver responses into the hashes sent to the HDB.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScreeningStatus {
+    /// The responses validated. Holds the concatenated encodings of the resulting tagged hashes,
+    /// exactly as they are sent to the HDB.
+    Hashed(Vec<u8>),
+    /// A keyserver's response could not be decoded.
+    MalformedResponse(KeyserverId),
+    /// A keyserver responded with the wrong number of hash parts.
+    WrongSizeResponse(KeyserverId),
+    /// Too few keyservers responded to reconstruct the hashes.
+    MissingKeyserverResponse,
+    /// The responses failed active security validation. Holds the keyservers responsible.
+    ValidationFailed(Vec<KeyserverId>),
+    /// The responses validated, and the resulting hashes were screened against the HDB inside
+    /// the proof, so the hashes themselves are not committed.
+    ///
+    /// Screening in the proof takes a witness from the HDB's accumulator for every hash, which
+    /// clients do not have, so this is only produced by provers with the HDB at hand and checked
+    /// offline by `zkverify`. The hdbserver rejects it, as it leaves no hashes for it to screen.
+    Screened(HdbHits),
+}
+
+/// The hits of an order's hashes in the HDB, as counted inside the proof.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HdbHits {
+    /// The number of hashes found in the HDB.
+    pub hits: u32,
+    /// How many of those hits were on reverse screened entries.
+    pub reverse_screened_hits: u32,
+}
+
+impl HdbHits {
+    /// Whether any hit denies synthesis. The proof cannot see the hazard lookup table, so this
+    /// is any hit on an entry that was not reverse screened, whatever its tags or the request's
+    /// region and exemptions.
+    pub fn is_denied(&self) -> bool {
+        self.hits > self.reverse_screened_hits
+    }
+}
+
+impl ScreeningStatus {
+    pub fn from_hashes(hashes: impl IntoIterator<Item = TaggedHash>) -> Self {
+        Self::Hashed(
+            hashes
+                .into_iter()
+                .flat_map(<[u8; TaggedHash::SIZE]>::from)
+                .collect(),
+        )
+    }
+
+    /// Maps an error from `QueryStateSet::get_hash_values`.
+    pub fn from_query_error(error: QueryError) -> Self {
+        match error {
+            // get_hash_values has no keyserver to blame for this, as every response
+            // was checked on incorporation
+            QueryError::WrongSizeResponse | QueryError::MissingKeyserverResponse => {
+                Self::MissingKeyserverResponse
+            }
+            QueryError::ValidationFailed(keyservers) => Self::ValidationFailed(keyservers),
+        }
+    }
+
+    /// The encoded tagged hashes, if the responses validated.
+    pub fn tagged_hashes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Hashed(hashes) => Some(hashes),
+            _ => None,
+        }
+    }
+
+    fn verdict(&self) -> u8 {
+        match self {
+            Self::Hashed(_) => VERDICT_HASHED,
+            Self::MalformedResponse(_) => VERDICT_MALFORMED_RESPONSE,
+            Self::WrongSizeResponse(_) => VERDICT_WRONG_SIZE_RESPONSE,
+            Self::MissingKeyserverResponse => VERDICT_MISSING_KEYSERVER_RESPONSE,
+            Self::ValidationFailed(_) => VERDICT_VALIDATION_FAILED,
+            Self::Screened(hits) if hits.is_denied() => VERDICT_DENIED,
+            Self::Screened(_) => VERDICT_GRANTED,
+        }
+    }
+
+    fn hdb_hits(&self) -> HdbHits {
+        match self {
+            Self::Screened(hits) => *hits,
+            _ => HdbHits::default(),
+        }
+    }
+
+    fn responsible_keyservers(&self) -> Vec<KeyserverId> {
+        match self {
+            Self::MalformedResponse(id) | Self::WrongSizeResponse(id) => vec![*id],
+            Self::ValidationFailed(keyservers) => keyservers.clone(),
+            Self::Hashed(_) | Self::MissingKeyserverResponse | Self::Screened(_) => vec![],
+        }
+    }
+
+    fn from_public_values(values: &ScreeningPublicValues) -> Result<Self, ProofOutputError> {
+        let keyservers = values
+            .responsibleKeyservers
+            .iter()
+            .map(|&id| {
+                KeyserverId::try_from(id).map_err(|_| ProofOutputError::InvalidKeyserverId(id))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let single_keyserver = || match keyservers.as_slice() {
+            [id] => Ok(*id),
+            _ => Err(ProofOutputError::InconsistentVerdict(values.verdict)),
+        };
+        let status = match values.verdict {
+            VERDICT_HASHED => Self::Hashed(values.taggedHashes.to_vec()),
+            VERDICT_MALFORMED_RESPONSE => Self::MalformedResponse(single_keyserver()?),
+            VERDICT_WRONG_SIZE_RESPONSE => Self::WrongSizeResponse(single_keyserver()?),
+            VERDICT_MISSING_KEYSERVER_RESPONSE => Self::MissingKeyserverResponse,
+            VERDICT_VALIDATION_FAILED => Self::ValidationFailed(keyservers.clone()),
+            VERDICT_GRANTED | VERDICT_DENIED => Self::Screened(HdbHits {
+                hits: values.hitCount,
+                reverse_screened_hits: values.reverseScreenedHitCount,
+            }),
+            verdict => return Err(ProofOutputError::UnknownVerdict(verdict)),
+        };
+        // Anything the status does not carry must be empty, so each status has one encoding
+        if status.verdict() != values.verdict
+            || status.responsible_keyservers() != keyservers
+            || (status.tagged_hashes().is_none() && !values.taggedHashes.is_empty())
+            || status.hdb_hits()
+                != (HdbHits {
+                    hits: values.hitCount,
+                    reverse_screened_hits: values.reverseScreenedHitCount,
+                })
+        {
+            return Err(ProofOutputError::InconsistentVerdict(values.verdict));
+        }
+        Ok(status)
+    }
+}
+
+impl fmt::Display for ScreeningStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hashed(hashes) => {
+                write!(f, "hashed {} windows", hashes.len() / TaggedHash::SIZE)
+            }
+            Self::MalformedResponse(id) => write!(f, "keyserver {id} sent a malformed response"),
+            Self::WrongSizeResponse(id) => {
+                write!(f, "keyserver {id} sent a response of the wrong size")
+            }
+            Self::MissingKeyserverResponse => write!(f, "missing keyserver response"),
+            Self::ValidationFailed(keyservers) => write!(
+                f,
+                "responses did not validate, responsible keyservers: {keyservers:?}"
+            ),
+            Self::Screened(hits) => write!(
+                f,
+                "screened against the HDB: synthesis {}, {} hits ({} reverse screened)",
+                if hits.is_denied() { "denied" } else { "granted" },
+                hits.hits,
+                hits.reverse_screened_hits
+            ),
+        }
+    }
+}
+
+impl ScreeningProofOutput {
+    pub fn to_public_values(&self) -> ScreeningPublicValues {
+        ScreeningPublicValues {
+            subProofVkeys: self
+                .sub_proof_vkeys
+                .iter()
+                .map(|vkey| vkey_digest_to_bytes(vkey).into())
+                .collect(),
+            orderCommitment: self.order_commitment.into(),
+            hdbCommitment: self.hdb_commitment.into(),
+            keyserverCommitmentHash: self.keyserver_commitment_hash.into(),
+            windowCount: self.window_count,
+            verdict: self.status.verdict(),
+            hitCount: self.status.hdb_hits().hits,
+            reverseScreenedHitCount: self.status.hdb_hits().reverse_screened_hits,
+            responsibleKeyservers: self
+                .status
+                .responsible_keyservers()
+                .iter()
+                .map(u32::from)
+                .collect(),
+            taggedHashes: self.status.tagged_hashes().unwrap_or_default().to_vec().into(),
+        }
+    }
+
+    /// The public values the verification program commits.
+    pub fn encode(&self) -> Vec<u8> {
+        ScreeningPublicValues::abi_encode(&self.to_public_values())
+    }
+
+    /// Decodes the public values committed by the verification program.
+    pub fn decode(public_values: &[u8]) -> Result<Self, ProofOutputError> {
+        let values = ScreeningPublicValues::abi_decode(public_values, true)
+            .map_err(|e| ProofOutputError::Abi(e.to_string()))?;
+        let output = Self {
+            sub_proof_vkeys: values
+                .subProofVkeys
+                .iter()
+                .map(|vkey| vkey_digest_from_bytes(&vkey.0))
+                .collect(),
+            order_commitment: values.orderCommitment.0,
+            hdb_commitment: values.hdbCommitment.0,
+            keyserver_commitment_hash: values.keyserverCommitmentHash.0,
+            window_count: values.windowCount,
+            status: ScreeningStatus::from_public_values(&values)?,
+        };
+        // The proof only attests to the exact bytes committed, so reject any other encoding of
+        // the same output, including one with trailing bytes.
+        if output.encode() != public_values {
+            return Err(ProofOutputError::NonCanonical);
+        }
+        Ok(output)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofOutputError {
+    Abi(String),
+    UnknownVerdict(u8),
+    InconsistentVerdict(u8),
+    InvalidKeyserverId(u32),
+    NonCanonical,
+}
+
+impl Error for ProofOutputError {}
+
+impl fmt::Display for ProofOutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofOutputError::Abi(e) => write!(f, "Public values are not ABI-encoded: {e}"),
+            ProofOutputError::UnknownVerdict(verdict) => write!(f, "Unknown verdict {verdict}"),
+            ProofOutputError::InconsistentVerdict(verdict) => {
+                write!(f, "Public values do not fit verdict {verdict}")
+            }
+            ProofOutputError::InvalidKeyserverId(id) => write!(f, "Invalid keyserver id {id}"),
+            ProofOutputError::NonCanonical => write!(f, "Public values are not canonically encoded"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prf::CompletedHashValue;
+    use crate::tagged::HashTag;
+
+    fn output(status: ScreeningStatus) -> ScreeningProofOutput {
+        ScreeningProofOutput {
+            sub_proof_vkeys: vec![[1; 8], [2; 8]],
+            order_commitment: [3; 32],
+            hdb_commitment: [0; 32],
+            keyserver_commitment_hash: [4; 32],
+            window_count: 3,
+            status,
+        }
+    }
+
+    #[test]
+    fn decodes_what_commit_encodes() {
+        let hashes = (0..3).map(|i| TaggedHash {
+            tag: HashTag::new(i == 0, 0, i),
+            hash: CompletedHashValue::hash_from_bytes_for_tests_only(&[i as u8]),
+        });
+        for status in [
+            ScreeningStatus::from_hashes(hashes),
+            ScreeningStatus::MalformedResponse(KeyserverId::try_from(2).unwrap()),
+            ScreeningStatus::WrongSizeResponse(KeyserverId::try_from(3).unwrap()),
+            ScreeningStatus::MissingKeyserverResponse,
+            ScreeningStatus::ValidationFailed(vec![KeyserverId::try_from(1).unwrap()]),
+            ScreeningStatus::Screened(HdbHits::default()),
+            ScreeningStatus::Screened(HdbHits {
+                hits: 3,
+                reverse_screened_hits: 1,
+            }),
+        ] {
+            let output = output(status);
+            assert_eq!(ScreeningProofOutput::decode(&output.encode()).unwrap(), output);
+        }
+    }
+
+    #[test]
+    fn hashed_status_holds_encoded_tagged_hashes() {
+        let hash = TaggedHash {
+            tag: HashTag::new(true, 1, 0),
+            hash: CompletedHashValue::hash_from_bytes_for_tests_only(b"window"),
+        };
+        let expected: [u8; TaggedHash::SIZE] = hash.clone().into();
+        let status = ScreeningStatus::from_hashes([hash]);
+        assert_eq!(status.tagged_hashes(), Some(&expected[..]));
+    }
+
+    #[test]
+    fn rejects_truncated_or_extended_output() {
+        let encoded = output(ScreeningStatus::MissingKeyserverResponse).encode();
+        assert!(ScreeningProofOutput::decode(&encoded[..encoded.len() - 1]).is_err());
+
+        let mut extended = encoded.clone();
+        extended.extend([0; 32]);
+        assert!(ScreeningProofOutput::decode(&extended).is_err());
+    }
+
+    #[test]
+    fn rejects_verdicts_that_do_not_fit_their_values() {
+        let mut values = output(ScreeningStatus::MissingKeyserverResponse).to_public_values();
+        values.verdict = 9;
+        assert_eq!(
+            ScreeningProofOutput::decode(&ScreeningPublicValues::abi_encode(&values)),
+            Err(ProofOutputError::UnknownVerdict(9))
+        );
+
+        values.verdict = VERDICT_MALFORMED_RESPONSE;
+        assert_eq!(
+            ScreeningProofOutput::decode(&ScreeningPublicValues::abi_encode(&values)),
+            Err(ProofOutputError::InconsistentVerdict(VERDICT_MALFORMED_RESPONSE))
+        );
+
+        values.verdict = VERDICT_MISSING_KEYSERVER_RESPONSE;
+        values.taggedHashes = vec![0; TaggedHash::SIZE].into();
+        assert_eq!(
+            ScreeningProofOutput::decode(&ScreeningPublicValues::abi_encode(&values)),
+            Err(ProofOutputError::InconsistentVerdict(VERDICT_MISSING_KEYSERVER_RESPONSE))
+        );
+
+        values.taggedHashes = Default::default();
+        values.hitCount = 1;
+        assert_eq!(
+            ScreeningProofOutput::decode(&ScreeningPublicValues::abi_encode(&values)),
+            Err(ProofOutputError::InconsistentVerdict(VERDICT_MISSING_KEYSERVER_RESPONSE))
+        );
+    }
+
+    #[test]
+    fn screened_verdict_follows_hits() {
+        let denied = HdbHits {
+            hits: 2,
+            reverse_screened_hits: 1,
+        };
+        let granted = HdbHits {
+            hits: 2,
+            reverse_screened_hits: 2,
+        };
+        let mut values = output(ScreeningStatus::Screened(denied)).to_public_values();
+        assert_eq!(values.verdict, VERDICT_DENIED);
+        assert_eq!(
+            output(ScreeningStatus::Screened(granted))
+                .to_public_values()
+                .verdict,
+            VERDICT_GRANTED
+        );
+
+        // A denial cannot be passed off as a grant
+        values.verdict = VERDICT_GRANTED;
+        assert_eq!(
+            ScreeningProofOutput::decode(&ScreeningPublicValues::abi_encode(&values)),
+            Err(ProofOutputError::InconsistentVerdict(VERDICT_GRANTED))
+        );
+    }
+
+    #[test]
+    fn sub_proofs_are_hash_chunks_then_checksum() {
+        let vkeys = SubProofVkeys {
+            hash: [1; 8],
+            checksum: [2; 8],
+        };
+        assert!(vkeys.matches(&[[1; 8], [2; 8]]));
+        assert!(vkeys.matches(&[[1; 8], [1; 8], [1; 8], [2; 8]]));
+
+        assert!(!vkeys.matches(&[]));
+        assert!(!vkeys.matches(&[[2; 8]]));
+        assert!(!vkeys.matches(&[[2; 8], [1; 8]]));
+        assert!(!vkeys.matches(&[[1; 8], [3; 8], [2; 8]]));
+    }
+}
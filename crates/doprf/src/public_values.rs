@@ -0,0 +1,193 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! The public values of the screening programs, as Solidity ABI structs.
+//!
+//! Each program commits exactly one of these, ABI-encoded, as its public values. The same bytes
+//! decode with `alloy_sol_types` here, and with `abi.decode` against the matching structs in
+//! each program's contract: `hash_proof/contracts/src/HashProof.sol`,
+//! `checksum_proof/contracts/src/ChecksumProof.sol` and
+//! `verification_proof/contracts/src/SecureDNAVerifier.sol`.
+
+use alloy_sol_types::sol;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::Identity;
+use sha3::Sha3_512;
+
+use crate::active_security::ChecksumInputs;
+use crate::window_commitment::combine_chunk_roots;
+
+sol! {
+    /// Committed by the hash_proof program.
+    #[sol(all_derives)]
+    struct HashPublicValues {
+        /// The salted Merkle root over the order's windows.
+        bytes32 windowRoot;
+        /// The compressed query for each window, in window order.
+        bytes32[] queries;
+        /// The hash of the concatenated queries, which the checksum is randomized by.
+        bytes32 randomModifier;
+        /// The compressed sum of each window's hash point times its verification factor.
+        bytes32 checksumSum;
+    }
+
+    /// Committed by the checksum_proof program.
+    #[sol(all_derives)]
+    struct ChecksumPublicValues {
+        /// The compressed checksum query.
+        bytes32 query;
+        bytes32 randomModifier;
+        bytes32 checksumSum;
+        /// The hash of the active security key the checksum was computed against.
+        bytes32 keyserverCommitmentHash;
+    }
+
+    /// Committed by the verification_proof program. See [`crate::proof_output`] for the Rust view.
+    #[sol(all_derives)]
+    struct ScreeningPublicValues {
+        /// The vkey digests of the aggregated proofs: a hash proof for each chunk of the order,
+        /// then the checksum proof.
+        bytes32[] subProofVkeys;
+        /// The salted Merkle root over the order's windows.
+        bytes32 orderCommitment;
+        /// The commitment to the HDB build the hashes were screened against.
+        bytes32 hdbCommitment;
+        /// The hash of the active security key the responses were validated against.
+        bytes32 keyserverCommitmentHash;
+        uint32 windowCount;
+        /// One of the `VERDICT_*` constants.
+        uint8 verdict;
+        /// The number of windows found in the HDB, when the verdict is `VERDICT_GRANTED` or
+        /// `VERDICT_DENIED`.
+        uint32 hitCount;
+        /// How many of those hits were on reverse screened entries, which do not deny synthesis.
+        uint32 reverseScreenedHitCount;
+        /// The keyservers responsible for a failed verdict.
+        uint32[] responsibleKeyservers;
+        /// The concatenated encodings of the tagged hashes, when the verdict is `VERDICT_HASHED`.
+        bytes taggedHashes;
+    }
+}
+
+pub const VERDICT_HASHED: u8 = 0;
+pub const VERDICT_MALFORMED_RESPONSE: u8 = 1;
+pub const VERDICT_WRONG_SIZE_RESPONSE: u8 = 2;
+pub const VERDICT_MISSING_KEYSERVER_RESPONSE: u8 = 3;
+pub const VERDICT_VALIDATION_FAILED: u8 = 4;
+/// The hashes were screened against the HDB inside the proof, and nothing denied synthesis.
+pub const VERDICT_GRANTED: u8 = 5;
+/// The hashes were screened against the HDB inside the proof, and a hit denied synthesis.
+pub const VERDICT_DENIED: u8 = 6;
+
+impl HashPublicValues {
+    pub fn checksum_inputs(&self) -> ChecksumInputs {
+        ChecksumInputs {
+            random_modifier: self.randomModifier.0,
+            sum: self.checksumSum.0,
+        }
+    }
+
+    /// Combines the values committed by hash proofs over consecutive chunks of an order into
+    /// those a single proof over the whole order would have committed. The chunks must be split
+    /// as [`combine_chunk_roots`] requires.
+    ///
+    /// Returns `None` if there are no chunks, or a chunk's checksum sum is not a valid point.
+    pub fn combine_chunks(chunks: &[Self]) -> Option<Self> {
+        if let [chunk] = chunks {
+            return Some(chunk.clone());
+        }
+        let window_root = combine_chunk_roots(chunks.iter().map(|c| c.windowRoot.0).collect())?;
+        let queries: Vec<_> = chunks.iter().flat_map(|c| c.queries.iter().copied()).collect();
+        let concat_queries: Vec<u8> = queries.iter().flat_map(|q| q.0).collect();
+        let random_modifier = Scalar::hash_from_bytes::<Sha3_512>(&concat_queries);
+        let mut sum = RistrettoPoint::identity();
+        for chunk in chunks {
+            sum += CompressedRistretto(chunk.checksumSum.0).decompress()?;
+        }
+        Some(Self {
+            windowRoot: window_root.into(),
+            queries,
+            randomModifier: random_modifier.to_bytes().into(),
+            checksumSum: sum.compress().to_bytes().into(),
+        })
+    }
+}
+
+impl ChecksumPublicValues {
+    pub fn checksum_inputs(&self) -> ChecksumInputs {
+        ChecksumInputs {
+            random_modifier: self.randomModifier.0,
+            sum: self.checksumSum.0,
+        }
+    }
+}
+
+/// Packs an SP1 vkey digest into a `bytes32`, most significant word first.
+pub fn vkey_digest_to_bytes(digest: &[u32; 8]) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for (chunk, word) in bytes.chunks_exact_mut(4).zip(digest) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    bytes
+}
+
+/// The inverse of [`vkey_digest_to_bytes`].
+pub fn vkey_digest_from_bytes(bytes: &[u8; 32]) -> [u32; 8] {
+    let mut digest = [0; 8];
+    for (word, chunk) in digest.iter_mut().zip(bytes.chunks_exact(4)) {
+        *word = u32::from_be_bytes(chunk.try_into().unwrap());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(i: u64) -> RistrettoPoint {
+        RistrettoPoint::hash_from_bytes::<Sha3_512>(&i.to_le_bytes())
+    }
+
+    fn chunk(root: u8, first: u64, windows: u64) -> HashPublicValues {
+        let queries: Vec<[u8; 32]> = (first..first + windows)
+            .map(|i| point(i).compress().to_bytes())
+            .collect();
+        let concat_queries = queries.concat();
+        let sum: RistrettoPoint = (first..first + windows).map(|i| point(i + 100)).sum();
+        HashPublicValues {
+            windowRoot: [root; 32].into(),
+            queries: queries.into_iter().map(Into::into).collect(),
+            randomModifier: Scalar::hash_from_bytes::<Sha3_512>(&concat_queries)
+                .to_bytes()
+                .into(),
+            checksumSum: sum.compress().to_bytes().into(),
+        }
+    }
+
+    #[test]
+    fn chunks_combine_like_a_single_proof() {
+        let whole = chunk(0, 0, 5);
+        let combined =
+            HashPublicValues::combine_chunks(&[chunk(1, 0, 2), chunk(2, 2, 2), chunk(3, 4, 1)])
+                .unwrap();
+        assert_eq!(combined.queries, whole.queries);
+        assert_eq!(combined.checksum_inputs(), whole.checksum_inputs());
+        assert_eq!(
+            Some(combined.windowRoot.0),
+            combine_chunk_roots(vec![[1; 32], [2; 32], [3; 32]])
+        );
+
+        // A single chunk is the whole order
+        assert_eq!(HashPublicValues::combine_chunks(&[whole.clone()]), Some(whole));
+        assert_eq!(HashPublicValues::combine_chunks(&[]), None);
+    }
+
+    #[test]
+    fn vkey_digest_bytes_round_trip() {
+        let digest = [1, 2, 3, 0xdeadbeef, 5, 6, 7, u32::MAX];
+        let bytes = vkey_digest_to_bytes(&digest);
+        assert_eq!(bytes[12..16], [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(vkey_digest_from_bytes(&bytes), digest);
+    }
+}
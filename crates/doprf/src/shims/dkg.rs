@@ -0,0 +1,479 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+use clap::{ArgAction, Parser, Subcommand};
+use rand::rngs::OsRng;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::active_security::Commitment;
+use crate::dkg::{Complaint, Dealing, DkgError, Justification, Participant, SecretShare};
+use crate::party::{KeyserverId, KeyserverIdSet};
+use crate::prf::KeyShare;
+
+#[derive(Debug, Parser)]
+#[clap(
+    name = "dkg",
+    about = "Generates, refreshes or reshares keyshares and the active security key together with the other keyholders, without anyone learning the secret key",
+    long_about = "Generates, refreshes or reshares keyshares and the active security key together with the other keyholders, without anyone learning the secret key.\n\n\
+        Every keyholder runs each step in turn, and copies the files it writes to the other keyholders' exchange directories before anyone starts the next step. \
+        share-<dealer>-<recipient>.json files must only be copied to their recipient, over a private channel. All other files go to everyone."
+)]
+pub struct Opts {
+    #[clap(
+        long,
+        help = "File keeping this keyholder's progress between steps. It holds secret shares, so keep it as private as a keyshare"
+    )]
+    pub state: PathBuf,
+
+    #[clap(
+        long,
+        help = "Directory the keyholders' messages are written to and read from"
+    )]
+    pub exchange: PathBuf,
+
+    #[clap(subcommand)]
+    pub step: Step,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Step {
+    /// Writes this keyholder's dealing and a share for each of the others
+    Deal {
+        #[clap(long, help = "This keyholder's keyserver id")]
+        id: KeyserverId,
+
+        #[clap(
+            long,
+            short,
+            help = "The number of keyholders required to hash a value"
+        )]
+        keyholders_required: NonZeroU32,
+
+        #[clap(long, action = ArgAction::Set, value_delimiter = ',',
+            help = "The keyserver ids of all keyholders, this one included")]
+        participants: Vec<KeyserverId>,
+    },
+    /// Starts refreshing this keyholder's keyshare instead of generating a new one. The secret
+    /// key stays the same, but every keyholder must take part and swap in the new keyshare
+    Refresh {
+        #[clap(long, help = "This keyholder's keyserver id")]
+        id: KeyserverId,
+
+        #[clap(
+            long,
+            env = "SECUREDNA_KEYSERVER_KEYSHARE",
+            help = "The keyshare to refresh, as a hexadecimal string"
+        )]
+        keyshare: KeyShare,
+
+        #[clap(long, action = ArgAction::Set, value_delimiter = ',',
+            env = "SECUREDNA_KEYSERVER_ACTIVE_SECURITY_KEY",
+            help = "The commitments comprising the current active security key")]
+        active_security_key: Vec<Commitment>,
+
+        #[clap(long, action = ArgAction::Set, value_delimiter = ',',
+            help = "The keyserver ids of all keyholders, this one included")]
+        participants: Vec<KeyserverId>,
+    },
+    /// Starts moving the secret key to a new set of keyholders or a new number of keyholders
+    /// required, keeping the secret so that the existing database stays valid. Old keyholders
+    /// deal their keyshares; new keyholders that hold none only receive
+    Reshare {
+        #[clap(long, help = "This keyholder's keyserver id")]
+        id: KeyserverId,
+
+        #[clap(
+            long,
+            env = "SECUREDNA_KEYSERVER_KEYSHARE",
+            help = "This keyholder's current keyshare, as a hexadecimal string. Only old keyholders have one"
+        )]
+        keyshare: Option<KeyShare>,
+
+        #[clap(long, action = ArgAction::Set, value_delimiter = ',',
+            env = "SECUREDNA_KEYSERVER_ACTIVE_SECURITY_KEY",
+            help = "The commitments comprising the current active security key")]
+        active_security_key: Vec<Commitment>,
+
+        #[clap(long, action = ArgAction::Set, value_delimiter = ',',
+            help = "The keyserver ids of the old keyholders dealing their keyshares, at least a quorum of them")]
+        old_keyholders: Vec<KeyserverId>,
+
+        #[clap(
+            long,
+            short,
+            help = "The number of new keyholders required to hash a value"
+        )]
+        keyholders_required: NonZeroU32,
+
+        #[clap(long, action = ArgAction::Set, value_delimiter = ',',
+            help = "The keyserver ids of all new keyholders")]
+        new_keyholders: Vec<KeyserverId>,
+    },
+    /// Checks the dealings and the shares sent to this keyholder, and writes its complaints
+    Verify,
+    /// Answers the complaints against this keyholder
+    Justify,
+    /// Prints this keyholder's keyshare, then the active security key, and removes the state
+    Finish,
+}
+
+pub fn main<Out: Write, Err: Write>(
+    opts: &Opts,
+    stdout: &mut Out,
+    stderr: &mut Err,
+) -> std::io::Result<()> {
+    let exchange = &opts.exchange;
+    match &opts.step {
+        Step::Deal {
+            id,
+            keyholders_required,
+            participants,
+        } => {
+            let participants = KeyserverIdSet::from_iter(participants.iter().copied());
+            let start = Participant::deal(*id, *keyholders_required, &participants, &mut OsRng)
+                .map(|(participant, dealing, shares)| (participant, Some(dealing), shares));
+            write_start(opts, start)
+        }
+        Step::Refresh {
+            id,
+            keyshare,
+            active_security_key,
+            participants,
+        } => {
+            let participants = KeyserverIdSet::from_iter(participants.iter().copied());
+            let start = Participant::refresh(
+                *id,
+                *keyshare,
+                active_security_key,
+                &participants,
+                &mut OsRng,
+            )
+            .map(|(participant, dealing, shares)| (participant, Some(dealing), shares));
+            write_start(opts, start)
+        }
+        Step::Reshare {
+            id,
+            keyshare,
+            active_security_key,
+            old_keyholders,
+            keyholders_required,
+            new_keyholders,
+        } => {
+            let old_keyholders = KeyserverIdSet::from_iter(old_keyholders.iter().copied());
+            let new_keyholders = KeyserverIdSet::from_iter(new_keyholders.iter().copied());
+            let start = Participant::reshare(
+                *id,
+                *keyshare,
+                active_security_key,
+                &old_keyholders,
+                *keyholders_required,
+                &new_keyholders,
+                &mut OsRng,
+            );
+            write_start(opts, start)
+        }
+        Step::Verify => {
+            let mut participant: Participant = read_json(&opts.state)?;
+            let id = participant.id();
+            let dealers: Vec<KeyserverId> = participant
+                .dealers()
+                .iter()
+                .copied()
+                .filter(|&dealer| dealer != id)
+                .collect();
+            let mut complaints = vec![];
+            for dealer in dealers {
+                let dealing: Dealing = read_json(&exchange.join(format!("dealing-{dealer}.json")))?;
+                let share_file = exchange.join(format!("share-{dealer}-{id}.json"));
+                let share: Option<SecretShare> = if share_file.exists() {
+                    Some(read_json(&share_file)?)
+                } else {
+                    None
+                };
+                let complaint = participant
+                    .receive(dealing, share)
+                    .map_err(|err| std::io::Error::new(ErrorKind::InvalidData, err))?;
+                if let Some(complaint) = complaint {
+                    writeln!(stderr, "complaining about the share from {dealer}")?;
+                    complaints.push(complaint);
+                }
+            }
+            write_json(&exchange.join(format!("complaints-{id}.json")), &complaints)?;
+            write_secret_json(&opts.state, &participant)
+        }
+        Step::Justify => {
+            let participant: Participant = read_json(&opts.state)?;
+            let complaints: Vec<Complaint> = read_all(&participant, exchange, "complaints")?;
+            let justifications = participant.justify(&complaints);
+            let file = format!("justifications-{}.json", participant.id());
+            write_json(&exchange.join(file), &justifications)
+        }
+        Step::Finish => {
+            let participant: Participant = read_json(&opts.state)?;
+            if !participant.receives_keyshare() {
+                let id = participant.id();
+                writeln!(stderr, "keyserver {id} holds no keyshare after resharing")?;
+                return std::fs::remove_file(&opts.state);
+            }
+            let complaints: Vec<Complaint> = read_all(&participant, exchange, "complaints")?;
+            let justifications: Vec<Justification> =
+                read_all(&participant, exchange, "justifications")?;
+            let output = participant
+                .finish(&complaints, &justifications)
+                .map_err(|err| std::io::Error::new(ErrorKind::InvalidData, err))?;
+
+            writeln!(stderr, "qualified dealers: {}", output.qualified)?;
+            writeln!(stdout, "{}", output.keyshare)?;
+            let commitments: Vec<String> =
+                output.commitments.iter().map(|c| c.to_string()).collect();
+            writeln!(stdout, "{}", commitments.join(","))?;
+            std::fs::remove_file(&opts.state)
+        }
+    }
+}
+
+/// Writes the dealing and shares of a newly started participant, if it deals, and its state.
+fn write_start(
+    opts: &Opts,
+    start: Result<(Participant, Option<Dealing>, Vec<SecretShare>), DkgError>,
+) -> std::io::Result<()> {
+    let (participant, dealing, shares) =
+        start.map_err(|err| std::io::Error::new(ErrorKind::InvalidInput, err))?;
+    if let Some(dealing) = dealing {
+        let file = format!("dealing-{}.json", dealing.dealer);
+        write_json(&opts.exchange.join(file), &dealing)?;
+    }
+    for share in shares {
+        let file = format!("share-{}-{}.json", share.dealer, share.recipient);
+        write_secret_json(&opts.exchange.join(file), &share)?;
+    }
+    write_secret_json(&opts.state, &participant)
+}
+
+/// Reads the `<kind>-<id>.json` lists written by every participant, this one included.
+fn read_all<T: DeserializeOwned>(
+    participant: &Participant,
+    exchange: &Path,
+    kind: &str,
+) -> std::io::Result<Vec<T>> {
+    let mut all = vec![];
+    for id in participant.participants() {
+        let items: Vec<T> = read_json(&exchange.join(format!("{kind}-{id}.json")))?;
+        all.extend(items);
+    }
+    Ok(all)
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> std::io::Result<T> {
+    let contents = std::fs::read_to_string(path).map_err(|err| {
+        std::io::Error::new(err.kind(), format!("reading {}: {err}", path.display()))
+    })?;
+    serde_json::from_str(&contents).map_err(|err| {
+        std::io::Error::new(
+            ErrorKind::InvalidData,
+            format!("parsing {}: {err}", path.display()),
+        )
+    })
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> std::io::Result<()> {
+    std::fs::write(path, to_json(value)?)
+}
+
+/// Writes a file holding secret shares so that only its owner can read it. The file is written
+/// in full under a temporary name first, so a crash never leaves a truncated state behind.
+fn write_secret_json<T: Serialize>(path: &Path, value: &T) -> std::io::Result<()> {
+    let contents = to_json(value)?;
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    // A leftover temporary file may have been created with other permissions
+    match std::fs::remove_file(&temp_path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&temp_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&temp_path, path)
+}
+
+fn to_json<T: Serialize>(value: &T) -> std::io::Result<String> {
+    serde_json::to_string_pretty(value)
+        .map_err(|err| std::io::Error::new(ErrorKind::InvalidData, err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+    use std::path::{Path, PathBuf};
+    use std::str::FromStr;
+
+    use rand::random;
+
+    use super::{Opts, Step};
+    use crate::active_security::{ActiveSecurityKey, Commitment};
+    use crate::party::KeyserverId;
+    use crate::prf::KeyShare;
+
+    fn run(state: &Path, exchange: &Path, step: Step) -> String {
+        let opts = Opts {
+            state: state.to_owned(),
+            exchange: exchange.to_owned(),
+            step,
+        };
+        let mut stdout: Vec<u8> = vec![];
+        super::main(&opts, &mut stdout, &mut vec![]).expect("DKG step failed");
+        String::from_utf8(stdout).expect("Got invalid utf8")
+    }
+
+    /// Runs every step for every keyholder in `dir`, returning the keyshare of each keyholder
+    /// that ends up with one and the active security key they agree on.
+    fn run_all(
+        dir: &Path,
+        ids: &[KeyserverId],
+        start: impl Fn(KeyserverId) -> Step,
+    ) -> (Vec<(KeyserverId, KeyShare)>, Vec<Commitment>) {
+        let exchange = dir.join("exchange");
+        std::fs::create_dir_all(&exchange).unwrap();
+        let state = |id: &KeyserverId| dir.join(format!("state-{id}.json"));
+
+        for id in ids {
+            run(&state(id), &exchange, start(*id));
+        }
+        for id in ids {
+            run(&state(id), &exchange, Step::Verify);
+        }
+        for id in ids {
+            run(&state(id), &exchange, Step::Justify);
+        }
+        let outputs: Vec<(KeyserverId, String)> = ids
+            .iter()
+            .map(|id| (*id, run(&state(id), &exchange, Step::Finish)))
+            .filter(|(_, output)| !output.is_empty())
+            .collect();
+        std::fs::remove_dir_all(dir).unwrap();
+
+        let key_line = |output: &str| output.lines().nth(1).unwrap().to_owned();
+        let (_, first) = &outputs[0];
+        assert!(outputs.iter().all(|(_, o)| key_line(o) == key_line(first)));
+        let commitments = key_line(first)
+            .split(',')
+            .map(|c| Commitment::from_str(c).expect("Got invalid commitment"))
+            .collect();
+        let keyshares = outputs
+            .iter()
+            .map(|(id, o)| {
+                let keyshare = o.lines().next().unwrap();
+                (
+                    *id,
+                    KeyShare::from_str(keyshare).expect("Got invalid keyshare"),
+                )
+            })
+            .collect();
+        (keyshares, commitments)
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("dkg-test-{:x}", random::<u64>()))
+    }
+
+    fn ids(ids: impl IntoIterator<Item = u32>) -> Vec<KeyserverId> {
+        ids.into_iter()
+            .map(|i| KeyserverId::try_from(i).unwrap())
+            .collect()
+    }
+
+    fn assert_match(keyshares: &[(KeyserverId, KeyShare)], commitments: &[Commitment]) {
+        let key = ActiveSecurityKey::from_commitments(commitments.iter().copied());
+        for (id, keyshare) in keyshares {
+            assert_eq!(keyshare.multiply_by_base(), key.keyserver_commitment(id));
+        }
+    }
+
+    #[test]
+    fn keyholders_generate_and_refresh_through_exchanged_files() {
+        let ids = ids(1..=3);
+        let (keyshares, commitments) = run_all(&temp_dir(), &ids, |id| Step::Deal {
+            id,
+            keyholders_required: NonZeroU32::new(2).unwrap(),
+            participants: ids.clone(),
+        });
+        assert_match(&keyshares, &commitments);
+
+        let (refreshed, refreshed_commitments) = run_all(&temp_dir(), &ids, |id| Step::Refresh {
+            id,
+            keyshare: keyshares[id.as_u32() as usize - 1].1,
+            active_security_key: commitments.clone(),
+            participants: ids.clone(),
+        });
+        assert_eq!(refreshed_commitments[0], commitments[0]);
+        assert_match(&refreshed, &refreshed_commitments);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn secret_files_are_only_readable_by_their_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir();
+        let exchange = dir.join("exchange");
+        std::fs::create_dir_all(&exchange).unwrap();
+        let state = dir.join("state.json");
+        let ids = ids(1..=2);
+        run(
+            &state,
+            &exchange,
+            Step::Deal {
+                id: ids[0],
+                keyholders_required: NonZeroU32::new(2).unwrap(),
+                participants: ids.clone(),
+            },
+        );
+
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&state), 0o600);
+        assert_eq!(mode(&exchange.join("share-1-2.json")), 0o600);
+        assert!(!dir.join("state.json.tmp").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keyholders_reshare_through_exchanged_files() {
+        let old = ids(1..=3);
+        let (keyshares, commitments) = run_all(&temp_dir(), &old, |id| Step::Deal {
+            id,
+            keyholders_required: NonZeroU32::new(2).unwrap(),
+            participants: old.clone(),
+        });
+
+        let new = ids(2..=5);
+        let (reshared, reshared_commitments) =
+            run_all(&temp_dir(), &ids(1..=5), |id| Step::Reshare {
+                id,
+                keyshare: keyshares.iter().find(|(k, _)| *k == id).map(|(_, k)| *k),
+                active_security_key: commitments.clone(),
+                old_keyholders: old.clone(),
+                keyholders_required: NonZeroU32::new(3).unwrap(),
+                new_keyholders: new.clone(),
+            });
+        let reshared_ids: Vec<KeyserverId> = reshared.iter().map(|(id, _)| *id).collect();
+        assert_eq!(reshared_ids, new);
+        assert_eq!(reshared_commitments.len(), 3);
+        assert_eq!(reshared_commitments[0], commitments[0]);
+        assert_match(&reshared, &reshared_commitments);
+    }
+}
@@ -20,3 +20,8 @@ pub mod genactivesecuritykey;
 
 #[cfg(not(feature = "centralized_keygen"))]
 pub use unimplemented as genactivesecuritykey;
+
+pub mod dkg;
+
+#[cfg(feature = "zk")]
+pub mod zkverify;
@@ -0,0 +1,60 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+
+use clap::{ArgAction, Parser};
+
+use crate::active_security::{ActiveSecurityKey, Commitment};
+use crate::proof_backend::{MockBackend, ProofBackend, Sp1Backend};
+use crate::proof_bundle::ProofBundle;
+
+#[derive(Debug, Parser)]
+#[clap(
+    name = "zkverify",
+    about = "Verifies a screening proof bundle offline, and reports what it proves"
+)]
+pub struct Opts {
+    #[clap(help = "The proof bundle to verify, as JSON or CBOR")]
+    pub bundle: PathBuf,
+
+    #[clap(
+        long,
+        required = true,
+        action = ArgAction::Set,
+        value_delimiter = ',',
+        help = "List of commitments comprising the keyservers' active security key. Bundles whose responses were validated against any other key are rejected."
+    )]
+    pub active_security_key: Vec<Commitment>,
+
+    #[clap(
+        long,
+        help = "Accept bundles from SP1's mock prover. Their proofs attest to nothing, so this is only for testing."
+    )]
+    pub allow_mock: bool,
+}
+
+pub fn main<Out: Write, Err: Write>(
+    opts: &Opts,
+    stdout: &mut Out,
+    stderr: &mut Err,
+) -> std::io::Result<()> {
+    let bytes = std::fs::read(&opts.bundle)?;
+    let bundle = ProofBundle::from_bytes(&bytes)
+        .map_err(|err| std::io::Error::new(ErrorKind::InvalidData, err))?;
+
+    let backend: Box<dyn ProofBackend> = if opts.allow_mock {
+        Box::new(MockBackend::new())
+    } else {
+        Box::new(Sp1Backend::new())
+    };
+    let trusted_key = ActiveSecurityKey::from_commitments(opts.active_security_key.iter().copied());
+    match bundle.verify(backend.as_ref(), &trusted_key) {
+        Ok(report) => write!(stdout, "{report}"),
+        Err(err) => {
+            writeln!(stderr, "Proof bundle for request {} did NOT verify", bundle.request_id)?;
+            Err(std::io::Error::new(ErrorKind::InvalidData, err))
+        }
+    }
+}
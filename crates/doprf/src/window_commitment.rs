@@ -0,0 +1,314 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Salted Merkle commitments to the windows of an order.
+//!
+//! The hash_proof program commits the root over every window it hashes. The root alone reveals
+//! nothing about the windows, since the per-order salt is kept private. Whoever holds the salt
+//! can later reveal the whole order to an auditor, who recomputes the root, or reveal a single
+//! window along with an [`InclusionProof`].
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest as _, Sha3_256};
+
+pub type Digest = [u8; 32];
+
+pub const SALT_SIZE: usize = 32;
+
+const LEAF_TAG: u8 = 0;
+const NODE_TAG: u8 = 1;
+const EMPTY_TAG: u8 = 2;
+
+/// Hashes a single window into a leaf of the tree.
+pub fn leaf_hash(salt: &[u8; SALT_SIZE], window: &[u8]) -> Digest {
+    Sha3_256::new()
+        .chain_update([LEAF_TAG])
+        .chain_update(salt)
+        .chain_update(window)
+        .finalize()
+        .into()
+}
+
+fn node_hash(left: &Digest, right: &Digest) -> Digest {
+    Sha3_256::new()
+        .chain_update([NODE_TAG])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+fn empty_root(salt: &[u8; SALT_SIZE]) -> Digest {
+    Sha3_256::new()
+        .chain_update([EMPTY_TAG])
+        .chain_update(salt)
+        .finalize()
+        .into()
+}
+
+/// Computes the level above `level`. An unpaired last node is promoted as-is, rather than
+/// paired with itself, so that no two distinct leaf sequences share a root.
+fn next_level(level: &[Digest]) -> Vec<Digest> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Computes the root over leaves produced by [`leaf_hash`], in window order.
+pub fn merkle_root(salt: &[u8; SALT_SIZE], mut leaves: Vec<Digest>) -> Digest {
+    if leaves.is_empty() {
+        return empty_root(salt);
+    }
+    while leaves.len() > 1 {
+        leaves = next_level(&leaves);
+    }
+    leaves[0]
+}
+
+/// Computes the root over an order from the roots over consecutive chunks of its windows, as
+/// committed by one hash_proof run per chunk. Every chunk but the last must hold the same
+/// power-of-two number of windows, so that each chunk's root is a node of the order's tree.
+///
+/// Returns `None` if there are no chunks.
+pub fn combine_chunk_roots(mut roots: Vec<Digest>) -> Option<Digest> {
+    if roots.is_empty() {
+        return None;
+    }
+    while roots.len() > 1 {
+        roots = next_level(&roots);
+    }
+    Some(roots[0])
+}
+
+/// The salt and root of an order's windows. Only the root is made public; the salt is what
+/// allows the order to be revealed later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderCommitment {
+    pub salt: [u8; SALT_SIZE],
+    pub root: Digest,
+}
+
+/// A Merkle tree over an order's windows.
+#[derive(Debug, Clone)]
+pub struct WindowMerkleTree {
+    salt: [u8; SALT_SIZE],
+    levels: Vec<Vec<Digest>>,
+}
+
+impl WindowMerkleTree {
+    pub fn new(
+        salt: [u8; SALT_SIZE],
+        windows: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    ) -> Self {
+        let mut levels = vec![windows
+            .into_iter()
+            .map(|w| leaf_hash(&salt, w.as_ref()))
+            .collect::<Vec<_>>()];
+        while levels.last().unwrap().len() > 1 {
+            let next = next_level(levels.last().unwrap());
+            levels.push(next);
+        }
+        Self { salt, levels }
+    }
+
+    pub fn root(&self) -> Digest {
+        match self.levels.last().unwrap().as_slice() {
+            [] => empty_root(&self.salt),
+            [root] => *root,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn commitment(&self) -> OrderCommitment {
+        OrderCommitment {
+            salt: self.salt,
+            root: self.root(),
+        }
+    }
+
+    /// Proves that the window at `index` is part of this tree.
+    /// Returns `None` if there is no such window.
+    pub fn inclusion_proof(&self, index: usize) -> Option<InclusionProof> {
+        if index >= self.levels[0].len() {
+            return None;
+        }
+        let mut position = index;
+        let mut siblings = Vec::with_capacity(self.levels.len() - 1);
+        for level in &self.levels[..self.levels.len() - 1] {
+            siblings.push(level.get(position ^ 1).copied());
+            position /= 2;
+        }
+        Some(InclusionProof { index, siblings })
+    }
+}
+
+/// The path from a window's leaf to the root of a [`WindowMerkleTree`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub index: usize,
+    /// The sibling at each level, from the leaves up. `None` where the node had no sibling and
+    /// was promoted unchanged.
+    pub siblings: Vec<Option<Digest>>,
+}
+
+impl InclusionProof {
+    /// Whether `window` is the window at `self.index` of an order of `window_count` windows
+    /// committed to. Fails if the path does not have the shape such an order's tree would give
+    /// it, so that a leaf cannot be proven at more than one position.
+    pub fn verify(
+        &self,
+        commitment: &OrderCommitment,
+        window_count: usize,
+        window: &[u8],
+    ) -> bool {
+        if self.index >= window_count {
+            return false;
+        }
+        let mut position = self.index;
+        let mut width = window_count;
+        let mut node = leaf_hash(&commitment.salt, window);
+        let mut siblings = self.siblings.iter();
+        while width > 1 {
+            match (siblings.next(), (position ^ 1) < width) {
+                (Some(Some(sibling)), true) if position % 2 == 0 => {
+                    node = node_hash(&node, sibling)
+                }
+                (Some(Some(sibling)), true) => node = node_hash(sibling, &node),
+                (Some(None), false) => {}
+                _ => return false,
+            }
+            position /= 2;
+            width = width.div_ceil(2);
+        }
+        siblings.next().is_none() && node == commitment.root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SALT: [u8; SALT_SIZE] = [7; SALT_SIZE];
+
+    fn windows(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("window{i}")).collect()
+    }
+
+    #[test]
+    fn tree_root_matches_streamed_root() {
+        for n in 0..20 {
+            let windows = windows(n);
+            let tree = WindowMerkleTree::new(SALT, &windows);
+            let leaves = windows.iter().map(|w| leaf_hash(&SALT, w.as_bytes())).collect();
+            assert_eq!(tree.root(), merkle_root(&SALT, leaves), "{n} windows");
+        }
+    }
+
+    #[test]
+    fn chunk_roots_combine_into_the_order_root() {
+        for chunk_size in [1, 2, 4, 8] {
+            for n in 0..20 {
+                let windows = windows(n);
+                let leaves: Vec<_> =
+                    windows.iter().map(|w| leaf_hash(&SALT, w.as_bytes())).collect();
+                let chunk_roots: Vec<_> = if leaves.is_empty() {
+                    vec![merkle_root(&SALT, vec![])]
+                } else {
+                    leaves
+                        .chunks(chunk_size)
+                        .map(|chunk| merkle_root(&SALT, chunk.to_vec()))
+                        .collect()
+                };
+                assert_eq!(
+                    combine_chunk_roots(chunk_roots),
+                    Some(merkle_root(&SALT, leaves)),
+                    "{n} windows in chunks of {chunk_size}"
+                );
+            }
+        }
+        assert_eq!(combine_chunk_roots(vec![]), None);
+    }
+
+    #[test]
+    fn every_window_has_a_valid_inclusion_proof() {
+        for n in 1..20 {
+            let windows = windows(n);
+            let tree = WindowMerkleTree::new(SALT, &windows);
+            let commitment = tree.commitment();
+            for (i, window) in windows.iter().enumerate() {
+                let proof = tree.inclusion_proof(i).unwrap();
+                assert!(proof.verify(&commitment, n, window.as_bytes()), "{i} of {n}");
+            }
+            assert!(tree.inclusion_proof(n).is_none());
+        }
+    }
+
+    #[test]
+    fn inclusion_proof_rejects_wrong_window_index_or_salt() {
+        let windows = windows(5);
+        let tree = WindowMerkleTree::new(SALT, &windows);
+        let commitment = tree.commitment();
+        let proof = tree.inclusion_proof(2).unwrap();
+
+        assert!(!proof.verify(&commitment, 5, b"not a window"));
+        assert!(!proof.verify(&commitment, 5, windows[3].as_bytes()));
+
+        let moved = InclusionProof {
+            index: 3,
+            ..proof.clone()
+        };
+        assert!(!moved.verify(&commitment, 5, windows[2].as_bytes()));
+
+        let resalted = OrderCommitment {
+            salt: [8; SALT_SIZE],
+            ..commitment
+        };
+        assert!(!proof.verify(&resalted, 5, windows[2].as_bytes()));
+    }
+
+    #[test]
+    fn inclusion_proof_rejects_paths_not_shaped_like_the_order() {
+        let windows = windows(5);
+        let tree = WindowMerkleTree::new(SALT, &windows);
+        let commitment = tree.commitment();
+        let proof = tree.inclusion_proof(4).unwrap();
+        assert!(matches!(proof.siblings[..], [None, None, Some(_)]));
+        assert!(proof.verify(&commitment, 5, windows[4].as_bytes()));
+
+        // The last window is promoted twice, so padding its path with further promotions
+        // would otherwise prove it at later positions too.
+        for index in [8, 16] {
+            let mut padded = proof.clone();
+            padded.index = index;
+            padded.siblings.insert(0, None);
+            assert!(!padded.verify(&commitment, 32, windows[4].as_bytes()), "{index}");
+        }
+
+        let mut extended = proof.clone();
+        extended.siblings.push(None);
+        assert!(!extended.verify(&commitment, 5, windows[4].as_bytes()));
+
+        let mut truncated = proof.clone();
+        truncated.siblings.pop();
+        assert!(!truncated.verify(&commitment, 5, windows[4].as_bytes()));
+
+        assert!(!proof.verify(&commitment, 4, windows[4].as_bytes()));
+        assert!(!proof.verify(&commitment, 6, windows[4].as_bytes()));
+    }
+
+    #[test]
+    fn root_depends_on_salt_and_order() {
+        let windows = windows(4);
+        let root = WindowMerkleTree::new(SALT, &windows).root();
+        assert_ne!(root, WindowMerkleTree::new([8; SALT_SIZE], &windows).root());
+
+        let mut swapped = windows.clone();
+        swapped.swap(0, 1);
+        assert_ne!(root, WindowMerkleTree::new(SALT, &swapped).root());
+    }
+}
@@ -5,7 +5,11 @@ use std::sync::Arc;
 
 use crate::error::DoprfError;
 use crate::instant::get_now;
-use crate::operations::{incorporate_responses_and_hash, make_keyserver_querysets};
+#[cfg(feature = "zk")]
+use crate::operations::{make_keyserver_querysets, prepare_keyserver_querysets, spawn_blocking};
+use crate::operations::{incorporate_responses_and_hash, make_unproven_keyserver_querysets};
+#[cfg(feature = "zk")]
+use crate::proof_job::{verification_stdin, ProofJob};
 use crate::scep_client::{ClientConfig, HdbClient, KeyserverSetClient};
 use crate::server_selection::{ChosenSelectionSubset, SelectedKeyserver, ServerSelector};
 use crate::server_version_handler::LastServerVersionHandler;
@@ -13,7 +17,17 @@ use crate::windows::Windows;
 use certificates::{ExemptionTokenGroup, TokenBundle};
 use doprf::active_security::ActiveSecurityKey;
 use doprf::party::{KeyserverIdSet, KeyserverId};
-use doprf::prf::{Query, QueryStateSet, SerializableQueryStateSet, HashPart, VerificationInput};
+use doprf::prf::{HashPart, Query, QueryError, QueryStateSet};
+#[cfg(feature = "zk")]
+use doprf::prf::{ProofMode, VerificationInput};
+#[cfg(feature = "zk")]
+use doprf::proof_backend::{ProofBackend, ProofBackendError, ProofMismatch, VERIFICATION_ELF};
+#[cfg(feature = "zk")]
+use doprf::proof_bundle::ProofBundle;
+#[cfg(feature = "zk")]
+use doprf::proof_inputs::DEFAULT_CHUNK_SIZE;
+#[cfg(feature = "zk")]
+use doprf::proof_output::ScreeningProofOutput;
 use doprf::tagged::{HashTag, TaggedHash};
 use http_client::BaseApiClient;
 use packed_ristretto::{PackableRistretto, PackedRistrettos};
@@ -24,13 +38,10 @@ use shared_types::hash::HashSpec;
 use shared_types::hdb::HdbScreeningResult;
 use shared_types::requests::RequestContext;
 use shared_types::requests::RequestId;
-use shared_types::requests::SerializableRequestContext;
 use shared_types::synthesis_permission::Region;
-use tracing::{debug, info};
-use sp1_sdk::{
-    include_elf, HashableKey, ProverClient, SP1Proof, SP1ProofWithPublicValues, SP1Stdin,
-    SP1VerifyingKey,
-};
+use tracing::{debug, info, warn};
+#[cfg(feature = "zk")]
+use tracing::info_span;
 
 pub struct DoprfConfig<'a, S> {
     pub api_client: &'a BaseApiClient,
@@ -48,6 +59,34 @@ pub struct DoprfConfig<'a, S> {
     /// Exemption tokens.
     pub ets: Vec<WithOtps<TokenBundle<ExemptionTokenGroup>>>,
     pub server_version_handler: &'a LastServerVersionHandler,
+    /// How the screening is proven to the HDB.
+    #[cfg(feature = "zk")]
+    pub proving: ProvingConfig,
+}
+
+/// How a screening is proven to the HDB.
+#[cfg(feature = "zk")]
+#[derive(Debug, Clone, Copy)]
+pub struct ProvingConfig {
+    /// In execute-only mode, the hash and checksum programs are still run and checked, but no
+    /// proof accompanies the screening request.
+    pub mode: ProofMode,
+    /// Answer the screening without running any programs, leaving a [`ProofJob`] in the output
+    /// to be proven afterwards. `mode` is then ignored.
+    pub defer: bool,
+    /// How many windows each hash proof covers. Must be a power of two.
+    pub chunk_size: usize,
+}
+
+#[cfg(feature = "zk")]
+impl Default for ProvingConfig {
+    fn default() -> Self {
+        Self {
+            mode: ProofMode::default(),
+            defer: false,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
 }
 
 impl<'a, S> DoprfConfig<'a, S> {
@@ -80,6 +119,24 @@ pub struct DoprfOutput {
     pub too_short: bool,
     /// The consolidation returned from the HDB
     pub response: HdbScreeningResult,
+    /// The proof of the screening, or what is needed to prove it. `None` if nothing was
+    /// screened, if the programs were only executed, or if exemption tokens were used.
+    #[cfg(feature = "zk")]
+    pub proof: Option<ScreeningProof>,
+    /// Domains of the keyservers that were caught misbehaving and replaced by another quorum.
+    pub replaced_keyservers: Vec<String>,
+    /// How many times the screening moved on to a new quorum.
+    pub failovers: usize,
+}
+
+/// What a screening leaves behind to attest to it.
+#[cfg(feature = "zk")]
+#[derive(Debug)]
+pub enum ScreeningProof {
+    /// A record of the proof sent to the HDB.
+    Bundle(ProofBundle),
+    /// The screening left to prove, since proving was deferred.
+    Job(ProofJob),
 }
 
 impl DoprfOutput {
@@ -88,6 +145,10 @@ impl DoprfOutput {
             n_hashes: 0,
             too_short: true,
             response: HdbScreeningResult::default(),
+            #[cfg(feature = "zk")]
+            proof: None,
+            replaced_keyservers: vec![],
+            failovers: 0,
         }
     }
 }
@@ -144,10 +205,20 @@ impl DoprfWindows {
             non_empty_records,
         })
     }
+
+    /// How many hashes the keyservers are asked for: one per window, plus the active security
+    /// checksum.
+    fn hash_total_count(&self) -> Result<u64, DoprfError> {
+        if self.combined_windows.is_empty() {
+            return Err(DoprfError::SequencesTooBig);
+        }
+
+        self.count.checked_add(1).ok_or(DoprfError::SequencesTooBig)
+    }
 }
 
 struct DoprfClient<'a, S> {
-    config: DoprfConfig<'a, S>,
+    config: &'a DoprfConfig<'a, S>,
     nucleotide_total_count: u64,
     keyserver_id_set: KeyserverIdSet,
     keyservers: Vec<(SelectedKeyserver, Option<u64>)>,
@@ -160,7 +231,7 @@ impl<'a, S> DoprfClient<'a, S> {
     /// Given a DOPRF config, select keyservers and a hdbserver, and open
     /// a connection to the HDB.
     async fn open(
-        config: DoprfConfig<'a, S>,
+        config: &'a DoprfConfig<'a, S>,
         nucleotide_total_count: u64,
     ) -> Result<Self, DoprfError> {
         // if either of these return an error, then a refresh is required by whoever holds the server selector
@@ -279,131 +350,219 @@ impl<'a, S> DoprfClient<'a, S> {
         )
     }
 
-    async fn hash<R>(&self, windows: &DoprfWindows) -> Result<(PackedRistrettos<R>, VerificationInput), DoprfError>
+    /// Hash the windows through the keyservers without running any programs, as the classic
+    /// `/screen` flow does.
+    async fn hash_unproven<R>(
+        &self,
+        windows: &DoprfWindows,
+    ) -> Result<PackedRistrettos<R>, DoprfError>
     where
         R: From<TaggedHash> + PackableRistretto + 'static,
         <R as PackableRistretto>::Array: Send + 'static,
     {
-        if windows.combined_windows.is_empty() {
-            return Err(DoprfError::SequencesTooBig);
-        }
+        let hash_total_count = windows.hash_total_count()?;
 
-        // add one for active security checksum
-        let hash_total_count = windows
-            .count
-            .checked_add(1)
-            .ok_or(DoprfError::SequencesTooBig)?;
-
-        // added 'inputs' return value for recursive proof
-        let (querystate, inputs) = make_keyserver_querysets(
+        let querystate = make_unproven_keyserver_querysets(
             self.config.request_ctx,
             &windows.combined_windows,
             self.keyserver_threshold as usize,
             &self.active_security_key,
         );
+        let keyserver_responses = self.query_keyservers(hash_total_count, &querystate).await?;
+        self.incorporate(querystate, keyserver_responses).await
+    }
 
-        let ks = self.connect_to_keyservers().await?;
-
-        // query keyservers with initial hash to get keyserver response querysets of hashes
-        let now = get_now();
-        let querystate_ristrettos = PackedRistrettos::<Query>::from(&querystate);
-        let keyserver_responses = ks.query(hash_total_count, &querystate_ristrettos).await?;
-        let querying_duration = now.elapsed();
-        debug!("Querying key servers done. Took: {:.2?}", querying_duration);
-
-        const VERIFICATION_ELF: &[u8] = include_bytes!("../../../verification_proof/elf/riscv32im-succinct-zkvm-elf");
+    /// Hash the windows through the keyservers, proving it unless proving is deferred.
+    #[cfg(feature = "zk")]
+    async fn hash<R>(
+        &self,
+        windows: &DoprfWindows,
+    ) -> Result<(PackedRistrettos<R>, Attestation), DoprfError>
+    where
+        R: From<TaggedHash> + PackableRistretto + 'static,
+        <R as PackableRistretto>::Array: Send + 'static,
+    {
+        let hash_total_count = windows.hash_total_count()?;
 
-        // Initialize the proving client.
-        let client = ProverClient::new();
-        // Setup the proving and verifying keys.
-        let (verification_pk, verification_vk) = client.setup(VERIFICATION_ELF);
+        // Checked up front, so a deferred job cannot fail on it long after screening
+        if !self.config.proving.chunk_size.is_power_of_two() {
+            return Err(ProofBackendError::InvalidChunkSize(self.config.proving.chunk_size).into());
+        }
 
-        let mut stdin = SP1Stdin::new();
+        if self.config.proving.defer {
+            let (querystate, hash_inputs, checksum_inputs) = prepare_keyserver_querysets(
+                self.config.request_ctx,
+                &windows.combined_windows,
+                self.keyserver_threshold as usize,
+                &self.active_security_key,
+            );
+            let serializable_querystate = querystate.to_serializable_set();
+            let keyserver_responses = self.query_keyservers(hash_total_count, &querystate).await?;
+            // `started_at` and `hdb_commitment` are filled in by `process`
+            let job = ProofJob {
+                request_id: self.id().to_string(),
+                started_at: 0,
+                active_security_key: self.active_security_key.clone(),
+                hash_inputs,
+                chunk_size: self.config.proving.chunk_size,
+                checksum_inputs,
+                querystate: serializable_querystate,
+                keyserver_responses: keyserver_responses.clone(),
+                request_ctx: self.config.request_ctx.to_serializable_request_context(),
+                hdb_commitment: None,
+            };
+            let hashes = self.incorporate(querystate, keyserver_responses).await?;
+            return Ok((hashes, Attestation::Deferred(job)));
+        }
 
-        // Write the verification keys: recursive proof
-        let vkeys = inputs.iter().map(|input| input.vk.hash_u32()).collect::<Vec<_>>();
-        stdin.write::<Vec<[u32; 8]>>(&vkeys);
+        let backend: Arc<dyn ProofBackend> = self.config.proving.mode.backend().into();
+
+        // Running the hash and checksum programs takes anywhere from seconds to hours, so it
+        // must not hold up the executor. The returned inputs are aggregated by the verification
+        // program below.
+        let (querystate, inputs) = {
+            let request_ctx = self.config.request_ctx.clone();
+            let windows = windows.combined_windows.clone();
+            let threshold = self.keyserver_threshold as usize;
+            let target = self.active_security_key.clone();
+            let chunk_size = self.config.proving.chunk_size;
+            let backend = backend.clone();
+            spawn_blocking(move || {
+                make_keyserver_querysets(
+                    &request_ctx,
+                    &windows,
+                    threshold,
+                    &target,
+                    chunk_size,
+                    backend.as_ref(),
+                )
+            })
+            .await??
+        };
 
-        // Write the public values: recursive proof
-        let public_values_write =
-            inputs.iter().map(|input| input.proof.public_values.to_vec()).collect::<Vec<_>>();
-        stdin.write::<Vec<Vec<u8>>>(&public_values_write);
+        let keyserver_responses = self.query_keyservers(hash_total_count, &querystate).await?;
 
-        // Write the proofs: recursive proof
-        //
-        // Note: this data will not directly read by the aggregation program, instead it will be
-        // witnessed by the prover during the recursive aggregation process inside SP1 itself.
-        for input in inputs {
-            let SP1Proof::Compressed(proof) = input.proof.proof else { panic!() };
-            stdin.write_proof(*proof, input.vk.vk);
+        // The verification program requires the sub-proofs it aggregates, so an execute-only
+        // backend, which produces none, stops after the hash and checksum programs
+        if inputs.is_empty() {
+            let hashes = self.incorporate(querystate, keyserver_responses).await?;
+            return Ok((hashes, Attestation::Proven(None)));
         }
 
-        // Write values needed to incorporate responses and hash
-        stdin.write::<SerializableQueryStateSet>(&querystate.to_serializable_set());
-        stdin.write::<Vec<(KeyserverId, PackedRistrettos<HashPart>)>>(&keyserver_responses);
-        stdin.write::<SerializableRequestContext>(&self.config.request_ctx.to_serializable_request_context());
-
-        // DEBUGGING SECTION START
-        // Execute the verification_proof program using the `ProverClient.execute` method,
-        let (mut public_values, execution_report) = client.execute(VERIFICATION_ELF, stdin.clone()).run().unwrap();
-        println!(
-            "Verification program executed with {} cycles",
-            execution_report.total_instruction_count() + execution_report.total_syscall_count()
-        );
+        let stdin = verification_stdin(
+            inputs,
+            &querystate.to_serializable_set(),
+            &keyserver_responses,
+            &self.config.request_ctx.to_serializable_request_context(),
+            // The client has no HDB witnesses, so the hashes are committed for the HDB to screen
+            None,
+        )?;
+
+        // Run the verification_proof program over this request's keyserver responses
+        let (public_values, hdb_verification_input) = spawn_blocking(move || {
+            let _span = info_span!("verification_proof").entered();
+            backend.run(VERIFICATION_ELF, stdin)
+        })
+        .await??;
 
         // Read the public values
-        let verified_status = public_values.read::<bool>();
-        println!("Verificationation Proof: Recursive proof return value --> {:?}", verified_status);
-        let proof_tagged_hash = public_values.read::<PackedRistrettos<TaggedHash>>();
-
-        let local_tagged_hash: PackedRistrettos<TaggedHash> = incorporate_responses_and_hash(self.config.request_ctx, querystate, keyserver_responses)
-            .await?;
-
-        if proof_tagged_hash.encoded_items() == local_tagged_hash.encoded_items() {
-            println!("Verificationation Proof: Incorporated responses match.");
-        } else {
-            println!("Verificationation Proof: Incorporated responses do not match.EDIT");
+        let proof_output = ScreeningProofOutput::decode(public_values.as_slice())
+            .map_err(|e| DoprfError::MalformedProofOutput(e.to_string()))?;
+        debug!(status = %proof_output.status, "verification proof committed");
+
+        let local_tagged_hash: PackedRistrettos<TaggedHash> =
+            self.incorporate(querystate, keyserver_responses).await?;
+
+        let local_encoded: Vec<u8> = local_tagged_hash.iter_encoded().flatten().copied().collect();
+        // The HDB screens the hashes the proof attests to, so they must be the ones computed here
+        if proof_output.status.tagged_hashes() != Some(&local_encoded[..]) {
+            warn!(
+                status = %proof_output.status,
+                "verification proof does not match local hashes"
+            );
+            return Err(DoprfError::ProofMismatch(ProofMismatch::IncorporatedHashes));
         }
-        
-        let verification_proof = 
-            SP1ProofWithPublicValues::load("/client/output/verification_proof-with-pis.bin").expect("loading proof failed");
-        let hdb_verification_input = VerificationInput {
-            proof: verification_proof,
-            vk: verification_vk.clone(),
-        };
-        // DEBUGGING SECTION END
-
-        // // PROOF GENERATION
-        // // Generate the proof for the given program and input
-        // let (verification_pk, verification_vk) = client.setup(VERIFICATION_ELF);
-        // let mut verification_proof = client.prove(&verification_pk, stdin).run().unwrap();
-        // println!("generated proof");
-
-        // // Verify proof and public values
-        // client.verify(&verification_proof, &verification_vk).expect("verification failed");
-
-        // // Save the proof to file
-        // verification_proof.save("/client/output/verification_proof-with-pis.bin").expect("saving proof failed");
-
-        // let local_tagged_hash: PackedRistrettos<TaggedHash> = incorporate_responses_and_hash(self.config.request_ctx, querystate, keyserver_responses)
-        //     .await?;
 
-        // let hdb_verification_input = VerificationInput {
-        //     proof: verification_proof,
-        //     vk: verification_vk.clone(),
-        // };
-        // // PROOF GENERATION SECTION END
         let packed_ristrettos: PackedRistrettos<R> = local_tagged_hash
             .iter_decoded()
             .map(|item| R::from(item.unwrap()))
             .collect();
 
-        Ok((packed_ristrettos, hdb_verification_input))
+        Ok((packed_ristrettos, Attestation::Proven(hdb_verification_input)))
+    }
+
+    /// Incorporate the keyserver responses and hash, marking the keyservers whose responses
+    /// fail active security validation bad.
+    async fn incorporate<R>(
+        &self,
+        querystate: QueryStateSet,
+        keyserver_responses: Vec<(KeyserverId, PackedRistrettos<HashPart>)>,
+    ) -> Result<PackedRistrettos<R>, DoprfError>
+    where
+        R: From<TaggedHash> + PackableRistretto + 'static,
+        <R as PackableRistretto>::Array: Send + 'static,
+    {
+        incorporate_responses_and_hash(self.config.request_ctx, querystate, keyserver_responses)
+            .await
+            .map_err(|e| match e {
+                DoprfError::CryptoError(QueryError::ValidationFailed(ids)) => {
+                    let domains = self
+                        .keyservers
+                        .iter()
+                        .filter(|(keyserver, _)| ids.contains(&keyserver.id))
+                        .map(|(keyserver, _)| {
+                            keyserver.bad_flag.mark_bad();
+                            keyserver.domain.clone()
+                        })
+                        .collect();
+                    DoprfError::KeyserversFailedValidation { domains }
+                }
+                e => e,
+            })
+    }
+
+    /// Query keyservers with the blinded hashes to get their responses.
+    async fn query_keyservers(
+        &self,
+        hash_total_count: u64,
+        querystate: &QueryStateSet,
+    ) -> Result<Vec<(KeyserverId, PackedRistrettos<HashPart>)>, DoprfError> {
+        let ks = self.connect_to_keyservers().await?;
+
+        let now = get_now();
+        let querystate_ristrettos = PackedRistrettos::<Query>::from(querystate);
+        let keyserver_responses = ks
+            .query(
+                hash_total_count,
+                &querystate_ristrettos,
+                &self.active_security_key,
+            )
+            .await?;
+        let querying_duration = now.elapsed();
+        debug!("Querying key servers done. Took: {:.2?}", querying_duration);
+        Ok(keyserver_responses)
     }
 }
 
+/// How the hashes sent to the HDB are attested to.
+#[cfg(feature = "zk")]
+enum Attestation {
+    /// Proven while hashing. Holds the proof for the HDB to check, unless the programs were
+    /// only executed.
+    Proven(Option<VerificationInput>),
+    /// Left for a prover to prove after screening.
+    Deferred(ProofJob),
+}
+
+/// How many times a screening moves on to a new quorum after catching keyservers misbehaving,
+/// before it gives up.
+const MAX_QUORUM_FAILOVERS: usize = 2;
+
 /// Takes a slice of sequences, hashes them, sends them to the keyservers,
 /// then sends the results to the hdb, per the DOPRF protocol.
+///
+/// Keyservers caught misbehaving are marked bad and the screening is started over with a
+/// quorum that excludes them, up to [`MAX_QUORUM_FAILOVERS`] times.
 pub async fn process<'a, NLike, SliceN>(
     config: DoprfConfig<'a, SliceN>,
 ) -> Result<DoprfOutput, DoprfError>
@@ -418,6 +577,48 @@ where
         return Ok(DoprfOutput::too_short());
     }
 
+    let mut replaced_keyservers = vec![];
+    let mut failovers = 0;
+    loop {
+        let result = screen_with_quorum(&config, nucleotide_total_count).await;
+        let failed_keyservers = match &result {
+            Err(e) if failovers < MAX_QUORUM_FAILOVERS => e.failed_keyservers(),
+            _ => None,
+        };
+        let Some(failed_keyservers) = failed_keyservers else {
+            return result.map(|output| DoprfOutput {
+                replaced_keyservers,
+                failovers,
+                ..output
+            });
+        };
+        warn!(
+            "{}: replacing keyservers {} after: {}",
+            config.request_ctx.id,
+            failed_keyservers.join(", "),
+            result.as_ref().unwrap_err(),
+        );
+        // A keyserver can be caught again after a refreshed selection forgets it was bad
+        for domain in failed_keyservers {
+            if !replaced_keyservers.contains(domain) {
+                replaced_keyservers.push(domain.clone());
+            }
+        }
+        failovers += 1;
+    }
+}
+
+/// Screens the sequences with a freshly chosen quorum of keyservers.
+async fn screen_with_quorum<'a, NLike, SliceN>(
+    config: &'a DoprfConfig<'a, SliceN>,
+    nucleotide_total_count: u64,
+) -> Result<DoprfOutput, DoprfError>
+where
+    NLike: ToNucleotideLike + Copy + 'a,
+    SliceN: AsRef<[NLike]>,
+{
+    #[cfg(feature = "zk")]
+    let started_at = certificates::now_utc().unix_timestamp();
     let client = DoprfClient::open(config, nucleotide_total_count).await?;
 
     if client.sequences_too_short_for_hash_spec() {
@@ -432,16 +633,35 @@ where
             n_hashes: 0,
             too_short: false,
             response: HdbScreeningResult::default(),
+            #[cfg(feature = "zk")]
+            proof: None,
+            replaced_keyservers: vec![],
+            failovers: 0,
         });
     }
 
     info!("{}: generated {} windows", client.id(), windows.count);
-    let (hashes, hdb_verification_input) = client.hash(&windows).await?;
+    // The HDB can't check a proof sent along with exemption tokens, so none is made for them
+    #[cfg(feature = "zk")]
+    let (hashes, hdb_verification_input, proof_job) = if !client.config.ets.is_empty() {
+        (client.hash_unproven(&windows).await?, None, None)
+    } else {
+        match client.hash(&windows).await? {
+            (hashes, Attestation::Proven(input)) => (hashes, input, None),
+            (hashes, Attestation::Deferred(job)) => (hashes, None, Some(job)),
+        }
+    };
+    #[cfg(not(feature = "zk"))]
+    let hashes = client.hash_unproven(&windows).await?;
+    #[cfg(feature = "zk")]
+    let proved_at = certificates::now_utc().unix_timestamp();
+    #[cfg(feature = "zk")]
+    let bundle_input = hdb_verification_input.clone();
 
     let mut response = match &client.config.ets {
         ets if !ets.is_empty() => {
             let et_windows = client.window(ets.iter().flat_map(|w| w.et.token.dna_sequences()))?;
-            let (et_hashes, _) = client.hash(&et_windows).await?;
+            let et_hashes = client.hash_unproven(&et_windows).await?;
             let now = get_now();
             let response = client
                 .hdb_client
@@ -453,7 +673,13 @@ where
         }
         _ => {
             let now = get_now();
-            let response = client.hdb_client.query(&hashes, hdb_verification_input).await?;
+            #[cfg(feature = "zk")]
+            let response = client
+                .hdb_client
+                .query_with_verification(&hashes, hdb_verification_input)
+                .await?;
+            #[cfg(not(feature = "zk"))]
+            let response = client.hdb_client.query(&hashes).await?;
             let hdb_duration = now.elapsed();
             debug!("Querying HDB done. Took: {:.2?}", hdb_duration);
             response
@@ -470,10 +696,32 @@ where
             .ok_or(DoprfError::InvalidRecord)?;
     }
 
+    #[cfg(feature = "zk")]
+    let proof = match (bundle_input, proof_job) {
+        (Some(input), _) => Some(ScreeningProof::Bundle(ProofBundle::new(
+            client.id().to_string(),
+            started_at,
+            proved_at,
+            response.hdb_commitment.clone(),
+            client.active_security_key.clone(),
+            input,
+        ))),
+        (None, Some(mut job)) => {
+            job.started_at = started_at;
+            job.hdb_commitment.clone_from(&response.hdb_commitment);
+            Some(ScreeningProof::Job(job))
+        }
+        (None, None) => None,
+    };
+
     Ok(DoprfOutput {
         n_hashes: windows.count,
         too_short: false,
         response,
+        #[cfg(feature = "zk")]
+        proof,
+        replaced_keyservers: vec![],
+        failovers: 0,
     })
 }
 
@@ -557,6 +805,11 @@ mod tests {
             version_hint: "test".to_owned(),
             ets: vec![],
             server_version_handler: &Default::default(),
+            #[cfg(feature = "zk")]
+            proving: ProvingConfig {
+                mode: ProofMode::Execute,
+                ..Default::default()
+            },
         })
         .await
         .unwrap_err();
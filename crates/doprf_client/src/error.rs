@@ -7,6 +7,8 @@ use thiserror::Error;
 
 use crate::{server_selection::ServerSelectionError, windows::WindowsError};
 use doprf::prf::{DecodeError, QueryError};
+#[cfg(feature = "zk")]
+use doprf::proof_backend::{ProofBackendError, ProofMismatch};
 
 #[derive(Debug, Error)]
 pub enum DoprfError {
@@ -36,6 +38,24 @@ pub enum DoprfError {
     CryptoError(#[from] QueryError),
     #[error("Hazard database responded with invalid record number. This is a bug.")]
     InvalidRecord,
+    #[error("A background task failed to complete")]
+    TaskFailed,
+    #[error("Keyserver {domain} responded with hash parts its proof does not cover")]
+    InvalidKeyserverResponse { domain: String },
+    #[error("Responses of keyservers {} failed active security validation", domains.join(", "))]
+    KeyserversFailedValidation { domains: Vec<String> },
+    #[cfg(feature = "zk")]
+    #[error("Error proving the screening: {0}")]
+    ProofError(ProofBackendError),
+    #[cfg(feature = "zk")]
+    #[error("Screening proof does not match what was computed locally: {0}")]
+    ProofMismatch(ProofMismatch),
+    #[cfg(feature = "zk")]
+    #[error("Screening proof output could not be decoded: {0}")]
+    MalformedProofOutput(String),
+    #[cfg(feature = "zk")]
+    #[error("The proof backend only executes programs, so there is nothing to prove with")]
+    NothingProven,
 }
 
 impl DoprfError {
@@ -51,6 +71,37 @@ impl DoprfError {
             Self::DecodeError { .. } => false,
             Self::CryptoError { .. } => false,
             Self::InvalidRecord => false,
+            Self::TaskFailed => false,
+            // The keyservers are marked bad, so a retry picks a quorum without them
+            Self::InvalidKeyserverResponse { .. } => true,
+            Self::KeyserversFailedValidation { .. } => true,
+            #[cfg(feature = "zk")]
+            Self::ProofError(_) => false,
+            #[cfg(feature = "zk")]
+            Self::ProofMismatch(_) => false,
+            #[cfg(feature = "zk")]
+            Self::MalformedProofOutput(_) => false,
+            #[cfg(feature = "zk")]
+            Self::NothingProven => false,
+        }
+    }
+
+    /// The domains of the keyservers this error was blamed on, if any, which are marked bad.
+    pub fn failed_keyservers(&self) -> Option<&[String]> {
+        match self {
+            Self::InvalidKeyserverResponse { domain } => Some(std::slice::from_ref(domain)),
+            Self::KeyserversFailedValidation { domains } if !domains.is_empty() => Some(domains),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "zk")]
+impl From<ProofBackendError> for DoprfError {
+    fn from(value: ProofBackendError) -> Self {
+        match value {
+            ProofBackendError::Mismatch(mismatch) => Self::ProofMismatch(mismatch),
+            e => Self::ProofError(e),
         }
     }
 }
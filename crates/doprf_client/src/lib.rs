@@ -6,6 +6,8 @@ pub mod error;
 pub mod instant;
 pub mod operations;
 pub mod progress;
+#[cfg(feature = "zk")]
+pub mod proof_job;
 pub mod retry_if; // TODO: how to share this with synthclient?
 pub mod scep_client;
 pub mod server_selection;
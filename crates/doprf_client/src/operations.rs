@@ -6,7 +6,13 @@ use crate::instant::get_now;
 use crate::progress::report_progress;
 use doprf::active_security::ActiveSecurityKey;
 use doprf::party::KeyserverId;
-use doprf::prf::{HashPart, QueryStateSet, VerificationInput};
+use doprf::prf::{HashPart, QueryStateSet};
+#[cfg(feature = "zk")]
+use doprf::prf::VerificationInput;
+#[cfg(feature = "zk")]
+use doprf::proof_backend::ProofBackend;
+#[cfg(feature = "zk")]
+use doprf::proof_inputs::{ChecksumProofInputs, HashProofInputs};
 use doprf::tagged::{HashTag, TaggedHash};
 use packed_ristretto::{PackableRistretto, PackedRistrettos};
 
@@ -14,7 +20,7 @@ use shared_types::requests::RequestContext;
 use tracing::debug;
 
 #[cfg(target_arch = "wasm32")]
-async fn spawn_blocking<F, R>(f: F) -> Result<R, ()>
+pub(crate) async fn spawn_blocking<F, R>(f: F) -> Result<R, DoprfError>
 where
     F: FnOnce() -> R + Send + 'static,
     R: Send + 'static,
@@ -22,13 +28,17 @@ where
     Ok(f())
 }
 
+/// Runs `f` on the blocking pool. Fails if `f` panics, rather than panicking in turn.
 #[cfg(not(target_arch = "wasm32"))]
-fn spawn_blocking<F, R>(f: F) -> tokio::task::JoinHandle<R>
+pub(crate) async fn spawn_blocking<F, R>(f: F) -> Result<R, DoprfError>
 where
     F: FnOnce() -> R + Send + 'static,
     R: Send + 'static,
 {
-    tokio::task::spawn_blocking(f)
+    tokio::task::spawn_blocking(f).await.map_err(|e| {
+        tracing::error!("blocking task failed: {e}");
+        DoprfError::TaskFailed
+    })
 }
 
 /// What size of chunks the requests will be made in.
@@ -48,13 +58,20 @@ pub const CHUNK_SIZE_DEFAULT: usize = 10_000;
 /// correspond to 10,000 sequences each, and the last one to the final chunk of
 /// 2,000 sequences.
 ///
+/// The hash and checksum programs are run over these exact sequences on `backend`, the hash
+/// program once per `chunk_size` of them, and their proofs (if any) are returned alongside the
+/// QueryStateSet.
+///
 /// `sequences` cannot be empty, the method will panic if it is.
+#[cfg(feature = "zk")]
 pub fn make_keyserver_querysets(
     request_ctx: &RequestContext,
     sequences: &[(HashTag, impl AsRef<str> + Sync)],
     num_required_keyshares: usize,
     target: &ActiveSecurityKey,
-) -> (QueryStateSet, Vec<VerificationInput>) {
+    chunk_size: usize,
+    backend: &dyn ProofBackend,
+) -> Result<(QueryStateSet, Vec<VerificationInput>), DoprfError> {
 
     let now = get_now();
 
@@ -67,13 +84,74 @@ pub fn make_keyserver_querysets(
         sequences.iter().map(|(t, w)| (*t, w.as_ref().as_bytes())),
         num_required_keyshares,
         target.clone(),
+        chunk_size,
+        backend,
+    )?;
+
+    report_progress(request_ctx);
+
+    let setup_duration = now.elapsed();
+    debug!("Setting up done. Took: {:.2?}", setup_duration);
+    Ok((querystates, verification_inputs))
+}
+
+/// Like `make_keyserver_querysets`, but runs no programs, as the classic `/screen` flow does.
+///
+/// `sequences` cannot be empty, the method will panic if it is.
+pub fn make_unproven_keyserver_querysets(
+    request_ctx: &RequestContext,
+    sequences: &[(HashTag, impl AsRef<str> + Sync)],
+    num_required_keyshares: usize,
+    target: &ActiveSecurityKey,
+) -> QueryStateSet {
+    let now = get_now();
+
+    assert!(!sequences.is_empty());
+
+    report_progress(request_ctx);
+
+    let querystates = QueryStateSet::new(
+        sequences.iter().map(|(t, w)| (*t, w.as_ref().as_bytes())),
+        num_required_keyshares,
+        target.clone(),
+    );
+
+    report_progress(request_ctx);
+
+    let setup_duration = now.elapsed();
+    debug!("Setting up done. Took: {:.2?}", setup_duration);
+    querystates
+}
+
+/// Like `make_keyserver_querysets`, but runs no programs. Instead, the private inputs the
+/// hash and checksum programs must later be run with are returned alongside the
+/// QueryStateSet, for a deferred proof job.
+///
+/// `sequences` cannot be empty, the method will panic if it is.
+#[cfg(feature = "zk")]
+pub fn prepare_keyserver_querysets(
+    request_ctx: &RequestContext,
+    sequences: &[(HashTag, impl AsRef<str> + Sync)],
+    num_required_keyshares: usize,
+    target: &ActiveSecurityKey,
+) -> (QueryStateSet, HashProofInputs, ChecksumProofInputs) {
+    let now = get_now();
+
+    assert!(!sequences.is_empty());
+
+    report_progress(request_ctx);
+
+    let prepared = QueryStateSet::prepare(
+        sequences.iter().map(|(t, w)| (*t, w.as_ref().as_bytes())),
+        num_required_keyshares,
+        target.clone(),
     );
 
     report_progress(request_ctx);
 
     let setup_duration = now.elapsed();
     debug!("Setting up done. Took: {:.2?}", setup_duration);
-    (querystates, verification_inputs)
+    prepared
 }
 
 /// Given a QueryStateSet, and a Vec of keyserver responses,
@@ -99,8 +177,7 @@ where
             querystate.incorporate_response(id, &parts)?;
             Ok(querystate) // hand back querystate for borrow-checking purposes
         })
-        .await
-        .expect("failed to join task")?;
+        .await??;
     }
 
     let incorporating_duration = now.elapsed();
@@ -114,13 +191,9 @@ where
     let hash_values: PackedRistrettos<R> = spawn_blocking(move || {
         querystate
             .get_hash_values()
-            .expect("error processing keyserver responses")
-            .into_iter()
-            .map(R::from)
-            .collect()
+            .map(|hashes| hashes.into_iter().map(R::from).collect())
     })
-    .await
-    .expect("could not join thread");
+    .await??;
 
     let hash_duration = now.elapsed();
     debug!(
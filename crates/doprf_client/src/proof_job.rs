@@ -0,0 +1,152 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Proving a screening after it has been answered.
+//!
+//! With [`ProvingConfig::defer`](crate::ProvingConfig::defer) set, no zkVM program
+//! runs while screening. Everything the programs need is kept in a [`ProofJob`] instead, to be
+//! proven whenever a prover is free.
+
+use std::fmt;
+
+use doprf::active_security::ActiveSecurityKey;
+use doprf::party::KeyserverId;
+use doprf::prf::{HashPart, SerializableQueryStateSet, VerificationInput};
+use doprf::proof_backend::{
+    ProofBackend, ProofBackendError, CHECKSUM_ELF, HASH_ELF, VERIFICATION_ELF,
+};
+use doprf::proof_bundle::ProofBundle;
+use doprf::proof_inputs::{ChecksumProofInputs, HashProofInputs};
+use hdb_acc::ScreeningWitnesses;
+use packed_ristretto::PackedRistrettos;
+use shared_types::requests::SerializableRequestContext;
+use sp1_sdk::{HashableKey, SP1Proof, SP1Stdin};
+use tracing::info_span;
+
+use crate::error::DoprfError;
+
+/// The private inputs of the verification program: the hash proof of each chunk and the
+/// checksum proof to aggregate, followed by what it needs to incorporate the keyserver
+/// responses and hash. Fails unless every proof is compressed.
+///
+/// With `witnesses` for the resulting hashes, the program screens them against the HDB itself
+/// and commits a [`ScreeningStatus::Screened`](doprf::proof_output::ScreeningStatus::Screened)
+/// verdict instead of the hashes. Witnesses come from the accumulator next to the HDB, so only
+/// its operator can produce such proofs, and they are checked offline with `zkverify`; the
+/// hdbserver only accepts proofs of hashes it screens itself.
+pub fn verification_stdin(
+    inputs: Vec<VerificationInput>,
+    querystate: &SerializableQueryStateSet,
+    keyserver_responses: &Vec<(KeyserverId, PackedRistrettos<HashPart>)>,
+    request_ctx: &SerializableRequestContext,
+    witnesses: Option<&ScreeningWitnesses>,
+) -> Result<SP1Stdin, ProofBackendError> {
+    let mut stdin = SP1Stdin::new();
+
+    // Write the verification keys: recursive proof
+    let vkeys = inputs.iter().map(|input| input.vk.hash_u32()).collect::<Vec<_>>();
+    stdin.write::<Vec<[u32; 8]>>(&vkeys);
+
+    // Write the public values: recursive proof
+    let public_values_write =
+        inputs.iter().map(|input| input.proof.public_values.to_vec()).collect::<Vec<_>>();
+    stdin.write::<Vec<Vec<u8>>>(&public_values_write);
+
+    // Write the proofs: recursive proof
+    //
+    // Note: this data will not directly read by the aggregation program, instead it will be
+    // witnessed by the prover during the recursive aggregation process inside SP1 itself.
+    for input in inputs {
+        let SP1Proof::Compressed(proof) = input.proof.proof else {
+            return Err(ProofBackendError::NotCompressed);
+        };
+        stdin.write_proof(*proof, input.vk.vk);
+    }
+
+    // Write values needed to incorporate responses and hash
+    stdin.write::<SerializableQueryStateSet>(querystate);
+    stdin.write::<Vec<(KeyserverId, PackedRistrettos<HashPart>)>>(keyserver_responses);
+    stdin.write::<SerializableRequestContext>(request_ctx);
+    stdin.write::<Option<&ScreeningWitnesses>>(&witnesses);
+
+    Ok(stdin)
+}
+
+/// A screening that was answered without a proof, and everything needed to prove it.
+///
+/// This holds the order's windows and the factors they were blinded with, so it must never
+/// leave the client that screened the order.
+pub struct ProofJob {
+    pub(crate) request_id: String,
+    pub(crate) started_at: i64,
+    pub(crate) active_security_key: ActiveSecurityKey,
+    pub(crate) hash_inputs: HashProofInputs,
+    pub(crate) chunk_size: usize,
+    pub(crate) checksum_inputs: ChecksumProofInputs,
+    pub(crate) querystate: SerializableQueryStateSet,
+    pub(crate) keyserver_responses: Vec<(KeyserverId, PackedRistrettos<HashPart>)>,
+    pub(crate) request_ctx: SerializableRequestContext,
+    pub(crate) hdb_commitment: Option<String>,
+}
+
+impl ProofJob {
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// Runs the hash, checksum and verification programs on `backend`, which must produce
+    /// proofs. This takes as long as proving inline would have, so it should be run off any
+    /// async executor.
+    pub fn prove(self, backend: &dyn ProofBackend) -> Result<ProofBundle, DoprfError> {
+        let _span = info_span!("proof_job", request_id = %self.request_id).entered();
+
+        let mut inputs = Vec::new();
+        let chunks = self.hash_inputs.chunks(self.chunk_size);
+        let chunk_count = chunks.len();
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let _span = info_span!("hash_proof", chunk = i, chunks = chunk_count).entered();
+            let (_, hash_input) = backend.run(HASH_ELF, chunk.stdin())?;
+            inputs.push(hash_input.ok_or(DoprfError::NothingProven)?);
+        }
+        let (_, checksum_input) = {
+            let _span = info_span!("checksum_proof").entered();
+            backend.run(CHECKSUM_ELF, self.checksum_inputs.stdin())?
+        };
+        inputs.push(checksum_input.ok_or(DoprfError::NothingProven)?);
+
+        let stdin = verification_stdin(
+            inputs,
+            &self.querystate,
+            &self.keyserver_responses,
+            &self.request_ctx,
+            // The client has no HDB witnesses, so the hashes are committed for the HDB to screen
+            None,
+        )?;
+        let (_, input) = {
+            let _span = info_span!("verification_proof").entered();
+            backend.run(VERIFICATION_ELF, stdin)?
+        };
+        let input = input.ok_or(DoprfError::NothingProven)?;
+
+        Ok(ProofBundle::new(
+            self.request_id,
+            self.started_at,
+            certificates::now_utc().unix_timestamp(),
+            self.hdb_commitment,
+            self.active_security_key,
+            input,
+        ))
+    }
+}
+
+// Deliberately leaves out the windows and blinding factors
+impl fmt::Debug for ProofJob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProofJob")
+            .field("request_id", &self.request_id)
+            .field("started_at", &self.started_at)
+            .field("windows", &self.hash_inputs.windows.len())
+            .field("hdb_commitment", &self.hdb_commitment)
+            .finish_non_exhaustive()
+    }
+}
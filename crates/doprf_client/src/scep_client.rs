@@ -9,18 +9,24 @@ use futures::TryStreamExt;
 use shared_types::et::WithOtps;
 
 use crate::error::DoprfError;
+use crate::operations::spawn_blocking;
 use crate::retry_if;
 use crate::server_selection::{bad_flag::ServerBadFlag, SelectedHdb, SelectedKeyserver};
 use certificates::{DatabaseTokenGroup, ExemptionTokenGroup, KeyserverTokenGroup, TokenBundle};
+use doprf::active_security::ActiveSecurityKey;
+use doprf::dleq::DleqProof;
 use doprf::party::{KeyserverId, KeyserverIdSet};
-use doprf::prf::{CompletedHashValue, HashPart, Query, VerificationInput};
+use doprf::prf::{CompletedHashValue, HashPart, Query};
+#[cfg(feature = "zk")]
+use doprf::prf::VerificationInput;
 use doprf::tagged::TaggedHash;
 use http_client::BaseApiClient;
-use packed_ristretto::PackedRistrettos;
+use packed_ristretto::{PackableRistretto, PackedRistrettos};
 use scep::states::OpenedClientState;
 use scep_client_helpers::{ClientCerts, ScepClient};
 use shared_types::hdb::HdbScreeningResult;
 use shared_types::synthesis_permission::Region;
+use tracing::warn;
 
 #[derive(Clone)]
 pub struct ClientConfig {
@@ -82,35 +88,40 @@ impl HdbClient {
     pub async fn query(
         self,
         hashes: &PackedRistrettos<TaggedHash>,
-        hdb_verification_input: VerificationInput,
     ) -> Result<HdbScreeningResult, DoprfError> {
-        let hash_total_count = hashes
-            .len()
-            .try_into()
-            .map_err(|_| DoprfError::SequencesTooBig)?;
+        self.authenticate(hashes).await?;
+
+        retry_with_timeout_and_mark_bad(
+            || async { Ok(self.client.screen(hashes).await?) },
+            &self.server.bad_flag,
+        )
+        .await
+    }
+
+    /// Like [`Self::query`], but if a verification proof is given, the HDB checks it against
+    /// the hashes before screening.
+    #[cfg(feature = "zk")]
+    pub async fn query_with_verification(
+        self,
+        hashes: &PackedRistrettos<TaggedHash>,
+        hdb_verification_input: Option<VerificationInput>,
+    ) -> Result<HdbScreeningResult, DoprfError> {
+        let Some(hdb_verification_input) = hdb_verification_input else {
+            return self.query(hashes).await;
+        };
+
+        self.authenticate(hashes).await?;
 
-        // Step 1: Authentication
         retry_with_timeout_and_mark_bad(
             || async {
                 Ok(self
                     .client
-                    .authenticate(self.state.clone(), hash_total_count)
+                    .screen_and_verify(hashes, hdb_verification_input.clone())
                     .await?)
             },
             &self.server.bad_flag,
-        ).await?;
-
-        // Step 2: Actual screening query (with verification)
-        retry_with_timeout_and_mark_bad(
-            || async { Ok(self.client.screen_and_verify(hashes, hdb_verification_input.clone()).await?) },
-            &self.server.bad_flag,
-        ).await
-
-        // // Step 2: Actual screening query (without verification)
-        // retry_with_timeout_and_mark_bad(
-        //     || async { Ok(self.client.screen(hashes).await?) },
-        //     &self.server.bad_flag,
-        // ).await
+        )
+        .await
     }
 
     /// Post packed `CompletedHashValue`s to the HDB, and return the HDB response set
@@ -120,6 +131,17 @@ impl HdbClient {
         ets: &[WithOtps<TokenBundle<ExemptionTokenGroup>>],
         et_hashes: PackedRistrettos<CompletedHashValue>,
     ) -> Result<HdbScreeningResult, DoprfError> {
+        self.authenticate(hashes).await?;
+
+        retry_with_timeout_and_mark_bad(
+            || async { Ok(self.client.screen_with_ets(hashes, ets, &et_hashes).await?) },
+            &self.server.bad_flag,
+        )
+        .await
+    }
+
+    /// Authenticate for screening `hashes`, the first step of every query.
+    async fn authenticate(&self, hashes: &PackedRistrettos<TaggedHash>) -> Result<(), DoprfError> {
         let hash_total_count = hashes
             .len()
             .try_into()
@@ -134,12 +156,6 @@ impl HdbClient {
             },
             &self.server.bad_flag,
         )
-        .await?;
-
-        retry_with_timeout_and_mark_bad(
-            || async { Ok(self.client.screen_with_ets(hashes, ets, &et_hashes).await?) },
-            &self.server.bad_flag,
-        )
         .await
     }
 
@@ -156,6 +172,7 @@ pub struct KeyserverClient {
     client: ScepClient<KeyserverTokenGroup>,
     server: SelectedKeyserver,
     state: OpenedClientState,
+    keyserver_id_set: KeyserverIdSet,
 }
 
 impl KeyserverClient {
@@ -193,14 +210,17 @@ impl KeyserverClient {
             client,
             server,
             state,
+            keyserver_id_set,
         })
     }
 
     /// Post packed `Query`s to the given keyserver, and return the response of packed `HashPart`s
+    /// once they are checked against the keyserver's commitment in `active_security_key`.
     pub async fn query(
         self,
         hash_total_count: u64,
         queries: &PackedRistrettos<Query>,
+        active_security_key: &ActiveSecurityKey,
     ) -> Result<PackedRistrettos<HashPart>, DoprfError> {
         retry_with_timeout_and_mark_bad(
             || async {
@@ -213,11 +233,69 @@ impl KeyserverClient {
         )
         .await?;
 
-        retry_with_timeout_and_mark_bad(
+        let response = retry_with_timeout_and_mark_bad(
             || async { Ok(self.client.keyserve(queries).await?) },
             &self.server.bad_flag,
         )
-        .await
+        .await?;
+
+        self.verify_response(queries, response, active_security_key)
+            .await
+    }
+
+    /// Splits the `DleqProof` trailing `response` off and checks the hash parts against it,
+    /// marking the keyserver bad if they don't match.
+    ///
+    /// Keyservers that didn't agree to prove their response send no proof, so their hash parts
+    /// are returned unverified, leaving active security to catch a faulty response.
+    async fn verify_response(
+        &self,
+        queries: &PackedRistrettos<Query>,
+        response: PackedRistrettos<HashPart>,
+        active_security_key: &ActiveSecurityKey,
+    ) -> Result<PackedRistrettos<HashPart>, DoprfError> {
+        if !self.state.dleq_proof {
+            warn!(
+                "Keyserver {} does not prove its responses, leaving them unverified",
+                self.server.domain
+            );
+            return Ok(response);
+        }
+
+        let commitment = active_security_key.keyserver_commitment(&self.server.id);
+        let lagrange_coeff = self
+            .keyserver_id_set
+            .langrange_coefficient_for_id(&self.server.id);
+        let queries = queries.clone();
+
+        let hash_parts = spawn_blocking(move || {
+            let mut items = response.encoded_items().to_vec();
+            let proof_start = items.len().checked_sub(DleqProof::SIZE / HashPart::SIZE)?;
+            let proof: Vec<u8> = items.split_off(proof_start).concat();
+            let proof = DleqProof::from_bytes(proof.as_slice().try_into().ok()?)?;
+
+            let hash_parts = PackedRistrettos::<HashPart>::new(items);
+            let decoded_queries: Vec<Query> =
+                queries.iter_decoded().collect::<Result<_, _>>().ok()?;
+            let decoded_parts: Vec<HashPart> =
+                hash_parts.iter_decoded().collect::<Result<_, _>>().ok()?;
+            proof
+                .verify(
+                    &commitment,
+                    &lagrange_coeff,
+                    &decoded_queries,
+                    &decoded_parts,
+                )
+                .then_some(hash_parts)
+        })
+        .await?;
+
+        hash_parts.ok_or_else(|| {
+            self.server.bad_flag.mark_bad();
+            DoprfError::InvalidKeyserverResponse {
+                domain: self.server.domain.clone(),
+            }
+        })
     }
 
     pub fn domain(&self) -> &str {
@@ -263,6 +341,7 @@ impl KeyserverSetClient {
         self,
         hash_total_count: u64,
         queries: &PackedRistrettos<Query>,
+        active_security_key: &ActiveSecurityKey,
     ) -> Result<Vec<(KeyserverId, PackedRistrettos<HashPart>)>, DoprfError> {
         self.clients
             .into_iter()
@@ -270,7 +349,7 @@ impl KeyserverSetClient {
                 let client_id = client.server.id;
                 async move {
                     client
-                        .query(hash_total_count, queries)
+                        .query(hash_total_count, queries, active_security_key)
                         .await
                         .map(|hash_parts| (client_id, hash_parts))
                 }
@@ -893,6 +893,7 @@ mod tests {
                 "1.db.prod.securedna.org".into(),
                 HdbQualificationResponse {
                     supported_generations: vec![0, 1],
+                    hdb_commitment: None,
                 },
             )],
         )
@@ -1000,6 +1001,7 @@ mod tests {
                 "1.db.prod.securedna.org".into(),
                 HdbQualificationResponse {
                     supported_generations: vec![0, 1],
+                    hdb_commitment: None,
                 },
             )],
         )
@@ -15,11 +15,15 @@ use futures::{future, pin_mut};
 
 use doprf::party::KeyserverId;
 use doprf::prf::KeyShare;
+#[cfg(feature = "zk")]
+use doprf::prf::ProofMode;
 use doprf::shims::{genkey, genkeyshares};
 use doprf::{active_security::Commitment, shims::genactivesecuritykey};
 use doprf_client::server_selection::{
     ServerEnumerationSource, ServerSelectionConfig, ServerSelector,
 };
+#[cfg(feature = "zk")]
+use doprf_client::ProvingConfig;
 use doprf_client::{server_version_handler::LastServerVersionHandler, DoprfConfig};
 use hdb::shims::genhdb;
 use http_client::{BaseApiClient, HttpsToHttpRewriter};
@@ -196,6 +200,58 @@ async fn test_hdb() {
         })
         .collect::<Vec<_>>();
 
+    // A keyserver that claims id 3 but holds a keyshare of a different key. It is consistent
+    // with its own active security key, so it starts up fine, but its responses don't match
+    // the commitment the other keyservers agree on.
+    let (faulty_keyshare, faulty_active_security_key) = {
+        let mut stdout: Vec<u8> = vec![];
+        genkey::main(&genkey::Opts {}, &mut stdout, &mut vec![]).expect("Generating key failed");
+        let other_key: KeyShare = std::str::from_utf8(&stdout)
+            .expect("Got invalid utf8 key")
+            .trim_end()
+            .parse()
+            .expect("Got invalid keyshare");
+
+        let mut stdout: Vec<u8> = vec![];
+        genkeyshares::main(
+            &genkeyshares::Opts {
+                secret_key: other_key,
+                keyholders_required: KEYHOLDERS_REQUIRED,
+                num_keyholders: NUM_KEYHOLDERS,
+            },
+            &mut stdout,
+            &mut vec![],
+        )
+        .expect("Generating keyshares failed");
+        let other_shares = std::str::from_utf8(&stdout)
+            .expect("Invalid utf8 keyshares")
+            .lines()
+            .map(|l| KeyShare::from_str(l).expect("Got invalid keyshare"))
+            .collect::<Vec<_>>();
+
+        let mut stdout: Vec<u8> = vec![];
+        genactivesecuritykey::main(
+            &genactivesecuritykey::Opts {
+                secret_key: other_key,
+                keyholders_required: KEYHOLDERS_REQUIRED,
+                keyshares: other_shares.clone(),
+            },
+            &mut stdout,
+            &mut vec![],
+        )
+        .expect("Generating active security key failed");
+        let other_active_security_key = std::str::from_utf8(&stdout)
+            .expect("Invalid utf8 commitments")
+            .lines()
+            .map(|l| {
+                Commitment::from_str(l)
+                    .expect("Got invalid commitment when generating active security key")
+            })
+            .collect::<Vec<_>>();
+
+        (other_shares[2], other_active_security_key)
+    };
+
     // 6. Start the servers
 
     fn find_unused_port() -> TcpListener {
@@ -231,6 +287,19 @@ async fn test_hdb() {
         keypair_passphrase_file: format!("{certs_dir}/database-token.passphrase").into(),
         allow_insecure_cookie: true,
         event_store_path: ":memory:".into(),
+        #[cfg(feature = "zk")]
+        verification_vkey_hash: None,
+        #[cfg(feature = "zk")]
+        hash_vkey_digest: None,
+        #[cfg(feature = "zk")]
+        checksum_vkey_digest: None,
+        #[cfg(feature = "zk")]
+        active_security_key: active_security_key.clone(),
+        #[cfg(feature = "zk")]
+        previous_active_security_key: vec![],
+        supported_generations: vec![0],
+        #[cfg(feature = "zk")]
+        accept_mock_proofs: true,
     };
     let server_config = Arc::new(ServerConfig {
         main: PlaneConfig {
@@ -252,19 +321,23 @@ async fn test_hdb() {
         .build_with_external_world(external_world);
     servers.push(server);
 
-    for k in 0..NUM_KEYHOLDERS.get() {
+    // the faulty keyserver goes last, so its port is `ks_ports[NUM_KEYHOLDERS]`
+    let keyservers = (0..NUM_KEYHOLDERS.get())
+        .map(|k| (k + 1, shares[k as usize], active_security_key.clone()))
+        .chain([(3, faulty_keyshare, faulty_active_security_key)]);
+    for (id, keyshare, keyserver_active_security_key) in keyservers {
         let listener = find_unused_port();
         let address = listener.local_addr().unwrap();
-        let keyserver_file_base =
-            PathBuf::from(format!("{certs_dir}/keyserver-token-{:02}", k + 1));
+        let keyserver_file_base = PathBuf::from(format!("{certs_dir}/keyserver-token-{id:02}"));
         let app_cfg = keyserver::Config {
-            id: KeyserverId::try_from(k + 1).unwrap(),
+            id: KeyserverId::try_from(id).unwrap(),
             keyholders_required: KEYHOLDERS_REQUIRED.get(),
-            keyshare: shares[k as usize],
+            keyshare,
             max_heavy_clients: 1,
             crypto_parallelism_per_server: None,
             crypto_parallelism_per_request: None,
-            active_security_key: active_security_key.clone(),
+            active_security_key: keyserver_active_security_key,
+            generation: 0,
             scep_json_size_limit: 100_000,
             manufacturer_roots: format!("{certs_dir}/manufacturer-roots").into(),
             revocation_list: None,
@@ -319,36 +392,46 @@ async fn test_hdb() {
         let request_ctx = RequestContext::single(request_id);
         let api_client = HttpsToHttpRewriter::inject(BaseApiClient::new(request_ctx.id.clone()));
 
-        // Run server selection (without enumeration since we don't want to run a local DNS server lol)
-        let server_selector = Arc::new(
-            ServerSelector::new(
-                ServerSelectionConfig {
-                    enumeration_source: ServerEnumerationSource::Fixed {
-                        keyserver_domains: (0..KEYHOLDERS_REQUIRED.get())
-                            .map(|i| {
-                                let num = i + 1;
-                                let port = ks_ports[i as usize];
-                                format!("ks{num}.{BASE_DOMAIN}:{port}")
-                            })
-                            .collect(),
-                        hdb_domains: vec![format!("db1.{BASE_DOMAIN}:{hdb_port}")],
-                    },
-                    soft_timeout: None,
-                    blocking_timeout: None,
-                    soft_extra_keyserver_threshold: None,
-                    soft_extra_hdb_threshold: None,
-                },
-                api_client.clone(),
-            )
-            .await
-            .unwrap(),
+        let keyserver_domain = |num: usize| format!("ks{num}.{BASE_DOMAIN}:{}", ks_ports[num - 1]);
+        let faulty_keyserver_domain = format!(
+            "ks3-faulty.{BASE_DOMAIN}:{}",
+            ks_ports[NUM_KEYHOLDERS.get() as usize]
         );
 
+        // Run server selection (without enumeration since we don't want to run a local DNS server lol)
+        let select_servers = |keyserver_domains: Vec<String>| {
+            let api_client = api_client.clone();
+            async move {
+                Arc::new(
+                    ServerSelector::new(
+                        ServerSelectionConfig {
+                            enumeration_source: ServerEnumerationSource::Fixed {
+                                keyserver_domains,
+                                hdb_domains: vec![format!("db1.{BASE_DOMAIN}:{hdb_port}")],
+                            },
+                            soft_timeout: None,
+                            blocking_timeout: None,
+                            soft_extra_keyserver_threshold: None,
+                            soft_extra_hdb_threshold: None,
+                        },
+                        api_client,
+                    )
+                    .await
+                    .unwrap(),
+                )
+            }
+        };
+        let server_selector = select_servers(
+            (1..=KEYHOLDERS_REQUIRED.get() as usize)
+                .map(keyserver_domain)
+                .collect(),
+        )
+        .await;
+
         let client_certs = Arc::new(ClientCerts::load_test_certs());
         let server_versions = Arc::new(tokio::sync::Mutex::new(HashMap::<String, u64>::new()));
 
-        let run_query = |records: Vec<String>, region: Region| {
-            let server_selector = &server_selector;
+        let screen = |selector: Arc<ServerSelector>, records: Vec<String>, region: Region| {
             let request_ctx = &request_ctx;
             let api_client = api_client.clone();
             let sequences = records
@@ -359,9 +442,9 @@ async fn test_hdb() {
 
             let certs = client_certs.clone();
             async move {
-                let output = doprf_client::process(DoprfConfig {
+                doprf_client::process(DoprfConfig {
                     api_client: &api_client,
-                    server_selector: server_selector.clone(),
+                    server_selector: selector,
                     request_ctx,
                     certs,
                     region,
@@ -391,9 +474,21 @@ async fn test_hdb() {
                             })
                         },
                     ),
+                    // Exercise the full screen-and-verify path without proving hardware.
+                    #[cfg(feature = "zk")]
+                    proving: ProvingConfig {
+                        mode: ProofMode::Mock,
+                        ..Default::default()
+                    },
                 })
                 .await
-                .unwrap();
+            }
+        };
+
+        let run_query = |records: Vec<String>, region: Region| {
+            let output = screen(server_selector.clone(), records, region);
+            async move {
+                let output = output.await.unwrap();
 
                 println!("{:#?}", output.response);
 
@@ -627,6 +722,72 @@ async fn test_hdb() {
                 ),
             ]
         );
+
+        // 8. Fail over from a misbehaving keyserver
+
+        let hazard_result = ConsolidatedHazardResult {
+            record: 0,
+            hit_regions: vec![HitRegion {
+                seq_range_start: 0,
+                seq_range_end: 30,
+            }],
+            synthesis_permission: SynthesisPermission::Denied,
+            most_likely_organism: t_integrationitis.clone(),
+            organisms: vec![t_integrationitis.clone()],
+            is_dna: true,
+            is_wild_type: None,
+            exempt: false,
+        };
+
+        // With the faulty keyserver as a replica of keyserver 3, a screening that picks it
+        // should catch it and finish with the honest replica instead.
+        let server_selector = select_servers(vec![
+            keyserver_domain(1),
+            keyserver_domain(2),
+            keyserver_domain(3),
+            faulty_keyserver_domain.clone(),
+        ])
+        .await;
+        let mut failed_over = false;
+        // replicas are picked at random, so give the faulty one plenty of chances
+        for _ in 0..32 {
+            let output = screen(
+                server_selector.clone(),
+                vec![HAZ_RUNT.to_owned()],
+                Region::All,
+            )
+            .await
+            .expect("screening should fail over to the honest replica");
+            assert_eq!(output.response.results, vec![hazard_result.clone()]);
+            if output.failovers > 0 {
+                assert_eq!(output.failovers, 1);
+                assert_eq!(
+                    output.replaced_keyservers,
+                    vec![faulty_keyserver_domain.clone()]
+                );
+                failed_over = true;
+                break;
+            }
+        }
+        assert!(failed_over, "the faulty keyserver was never picked");
+
+        // With the faulty keyserver as the only keyserver 3 (keyserver 1 is listed twice, so
+        // the honest active security key still has a quorum), every quorum includes it, and
+        // the screening gives up once it runs out of failovers.
+        let server_selector = select_servers(vec![
+            keyserver_domain(1),
+            format!("ks1-replica.{BASE_DOMAIN}:{}", ks_ports[0]),
+            keyserver_domain(2),
+            faulty_keyserver_domain.clone(),
+        ])
+        .await;
+        let err = screen(server_selector, vec![HAZ_RUNT.to_owned()], Region::All)
+            .await
+            .expect_err("screening should fail without an honest keyserver 3");
+        assert_eq!(
+            err.failed_keyservers(),
+            Some(&[faulty_keyserver_domain.clone()][..])
+        );
     };
     pin_mut!(tests);
 
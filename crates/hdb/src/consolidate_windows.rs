@@ -322,6 +322,7 @@ impl Consolidation {
     pub fn to_hdb_screening_result(
         self,
         provider_reference: Option<String>,
+        hdb_commitment: Option<String>,
     ) -> hdb_api::HdbScreeningResult {
         fn into_organism(hdb_organism: HdbOrganism) -> hdb_api::Organism {
             let HdbOrganism {
@@ -408,6 +409,7 @@ impl Consolidation {
                     .collect()
             }),
             provider_reference,
+            hdb_commitment,
         }
     }
 }
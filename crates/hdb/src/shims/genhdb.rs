@@ -22,6 +22,8 @@ use doprf::prf::{KeyShare, Query};
 use pipeline_bridge::{self as pb, BuildTrace, DNA_NORMAL_LEN, DNA_RUNT_LEN};
 
 const BUILD_INFO_FILENAME: &str = "BUILD_INFO.json";
+/// Written by `genacc` from the hdb_acc crate.
+const ACCUMULATOR_FILENAME: &str = "hdb.acc";
 
 #[derive(Debug, Parser)]
 #[clap(
@@ -243,7 +245,8 @@ pub fn get_entry_file_paths(
                     de.path().extension().map(|ext| ext.to_string_lossy()) == Some("i".into());
                 let is_hlt = de.file_name().to_str() == Some("hlt.json");
                 let is_buildinfo = de.file_name().to_str() == Some(BUILD_INFO_FILENAME);
-                is_file && !is_hlt && !is_index_file && !is_buildinfo
+                let is_accumulator = de.file_name().to_str() == Some(ACCUMULATOR_FILENAME);
+                is_file && !is_hlt && !is_index_file && !is_buildinfo && !is_accumulator
             } else {
                 false
             }
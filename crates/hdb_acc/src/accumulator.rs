@@ -0,0 +1,626 @@
+//! A bucketed, sorted Merkle tree over the entries of an HDB.
+//!
+//! Entries are split into [`BUCKET_COUNT`] buckets by the first two bytes of their hash. Each
+//! bucket is a Merkle tree over its entries sorted by hash, so a hash that is not in the HDB
+//! falls between two adjacent leaves of its bucket, or before the first or after the last.
+//! Proving it absent means proving those neighbours present and adjacent. Each bucket's digest
+//! binds its number of entries along with its root, which fixes the shape of the bucket tree
+//! and so the index of every leaf. The bucket digests are the leaves of a complete tree of depth
+//! [`BUCKET_DEPTH`], whose root is the published [`HdbCommitment`].
+//!
+//! Splitting into buckets keeps updates cheap: a changed entry only changes its bucket's tree
+//! and the path above it, so an HDB update only rehashes the buckets it touches.
+//!
+//! A witness reveals the entries it is built from, including the HDB entries on either side of
+//! an absent hash. Witnesses are meant to be checked inside a proof, or by an auditor already
+//! trusted with HDB entries; they should not be published.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha3::{Digest as _, Sha3_256};
+
+use crate::{HdbAccError, HASH_BYTE_LENGTH, META_BYTE_LENGTH};
+
+pub type Digest = [u8; 32];
+pub type HashBytes = [u8; HASH_BYTE_LENGTH];
+pub type MetaBytes = [u8; META_BYTE_LENGTH];
+
+pub const BUCKET_DEPTH: usize = 16;
+pub const BUCKET_COUNT: usize = 1 << BUCKET_DEPTH;
+
+const LEAF_TAG: u8 = 0;
+const NODE_TAG: u8 = 1;
+const BUCKET_TAG: u8 = 2;
+
+/// The bucket a hash belongs to: its first two bytes, big-endian, so that buckets are in hash
+/// order and each HDB shard file holds 256 consecutive buckets.
+pub fn bucket_index(hash: &HashBytes) -> usize {
+    u16::from_be_bytes([hash[0], hash[1]]) as usize
+}
+
+/// Hashes a single HDB entry into a leaf of its bucket's tree.
+pub fn leaf_hash(hash: &HashBytes, metadata: &MetaBytes) -> Digest {
+    Sha3_256::new()
+        .chain_update([LEAF_TAG])
+        .chain_update(hash)
+        .chain_update(metadata)
+        .finalize()
+        .into()
+}
+
+fn node_hash(left: &Digest, right: &Digest) -> Digest {
+    Sha3_256::new()
+        .chain_update([NODE_TAG])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+/// The digest of a bucket of `len` entries with the given root, which is `None` exactly when
+/// the bucket is empty.
+pub fn bucket_digest(len: u64, root: Option<&Digest>) -> Digest {
+    let mut hasher = Sha3_256::new()
+        .chain_update([BUCKET_TAG])
+        .chain_update(len.to_le_bytes());
+    if let Some(root) = root {
+        hasher.update(root);
+    }
+    hasher.finalize().into()
+}
+
+/// Computes the level above `level`. An unpaired last node is promoted as-is.
+fn next_level(level: &[Digest]) -> Vec<Digest> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// The public commitment to an HDB build: the root over every bucket digest.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HdbCommitment(pub Digest);
+
+impl fmt::Display for HdbCommitment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl fmt::Debug for HdbCommitment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HdbCommitment({self})")
+    }
+}
+
+impl FromStr for HdbCommitment {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut digest = [0; 32];
+        hex::decode_to_slice(s, &mut digest)?;
+        Ok(Self(digest))
+    }
+}
+
+impl Serialize for HdbCommitment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for HdbCommitment {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A sorted Merkle tree over the entries of a single bucket.
+#[derive(Debug, Clone)]
+pub struct BucketTree {
+    entries: Vec<(HashBytes, MetaBytes)>,
+    levels: Vec<Vec<Digest>>,
+}
+
+impl BucketTree {
+    /// Builds the tree over the given entries, in any order. Fails if two entries share a hash.
+    pub fn new(
+        entries: impl IntoIterator<Item = (HashBytes, MetaBytes)>,
+    ) -> Result<Self, HdbAccError> {
+        let mut entries: Vec<_> = entries.into_iter().collect();
+        entries.sort_unstable();
+        if let Some(pair) = entries.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(HdbAccError::DuplicateHash(hex::encode(pair[0].0)));
+        }
+
+        let mut levels = vec![entries
+            .iter()
+            .map(|(hash, metadata)| leaf_hash(hash, metadata))
+            .collect::<Vec<_>>()];
+        while levels.last().unwrap().len() > 1 {
+            let next = next_level(levels.last().unwrap());
+            levels.push(next);
+        }
+        Ok(Self { entries, levels })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.entries.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn digest(&self) -> Digest {
+        bucket_digest(self.len(), self.levels.last().unwrap().first())
+    }
+
+    fn leaf_proof(&self, index: usize) -> LeafProof {
+        let mut position = index;
+        let mut siblings = Vec::with_capacity(self.levels.len() - 1);
+        for level in &self.levels[..self.levels.len() - 1] {
+            siblings.push(level.get(position ^ 1).copied());
+            position /= 2;
+        }
+        let (hash, metadata) = self.entries[index];
+        LeafProof {
+            index: index as u64,
+            hash,
+            metadata,
+            siblings,
+        }
+    }
+
+    /// Proves whether `hash` is in this bucket.
+    pub fn witness(&self, hash: &HashBytes) -> BucketWitness {
+        let len = self.len();
+        match self.entries.binary_search_by(|(entry, _)| entry.cmp(hash)) {
+            Ok(index) => BucketWitness::Member {
+                len,
+                leaf: self.leaf_proof(index),
+            },
+            Err(index) => BucketWitness::NonMember {
+                len,
+                lower: index.checked_sub(1).map(|i| self.leaf_proof(i)),
+                upper: (index < self.entries.len()).then(|| self.leaf_proof(index)),
+            },
+        }
+    }
+}
+
+/// The bucket digests of an HDB, and the tree over them.
+///
+/// This holds no entries; proving anything about a hash also needs the [`BucketTree`] of its
+/// bucket, which is rebuilt from the HDB on demand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HdbAccumulator {
+    bucket_lens: Vec<u64>,
+    /// `levels[0]` holds the bucket digests, and the last level the root.
+    levels: Vec<Vec<Digest>>,
+}
+
+impl Default for HdbAccumulator {
+    fn default() -> Self {
+        let empty = bucket_digest(0, None);
+        Self::from_buckets(vec![0; BUCKET_COUNT], vec![empty; BUCKET_COUNT])
+    }
+}
+
+impl HdbAccumulator {
+    /// Builds the accumulator over the given entries, in any order.
+    pub fn new(
+        entries: impl IntoIterator<Item = (HashBytes, MetaBytes)>,
+    ) -> Result<Self, HdbAccError> {
+        let mut buckets = vec![Vec::new(); BUCKET_COUNT];
+        for entry in entries {
+            buckets[bucket_index(&entry.0)].push(entry);
+        }
+        let mut bucket_lens = Vec::with_capacity(BUCKET_COUNT);
+        let mut bucket_digests = Vec::with_capacity(BUCKET_COUNT);
+        for entries in buckets {
+            let tree = BucketTree::new(entries)?;
+            bucket_lens.push(tree.len());
+            bucket_digests.push(tree.digest());
+        }
+        Ok(Self::from_buckets(bucket_lens, bucket_digests))
+    }
+
+    /// Rebuilds the tree over previously computed bucket lengths and digests.
+    pub fn from_buckets(bucket_lens: Vec<u64>, bucket_digests: Vec<Digest>) -> Self {
+        assert_eq!(bucket_lens.len(), BUCKET_COUNT);
+        assert_eq!(bucket_digests.len(), BUCKET_COUNT);
+        let mut levels = vec![bucket_digests];
+        while levels.last().unwrap().len() > 1 {
+            let next = next_level(levels.last().unwrap());
+            levels.push(next);
+        }
+        Self {
+            bucket_lens,
+            levels,
+        }
+    }
+
+    /// Replaces bucket `bucket` with `tree`, rehashing only the path above it.
+    pub fn set_bucket(&mut self, bucket: usize, tree: &BucketTree) {
+        self.bucket_lens[bucket] = tree.len();
+        self.levels[0][bucket] = tree.digest();
+        let mut position = bucket;
+        for depth in 0..BUCKET_DEPTH {
+            let level = &self.levels[depth];
+            let pair = position & !1;
+            let parent = node_hash(&level[pair], &level[pair + 1]);
+            position /= 2;
+            self.levels[depth + 1][position] = parent;
+        }
+    }
+
+    /// The total number of entries.
+    pub fn len(&self) -> u64 {
+        self.bucket_lens.iter().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn bucket_len(&self, bucket: usize) -> u64 {
+        self.bucket_lens[bucket]
+    }
+
+    pub fn bucket_digest(&self, bucket: usize) -> &Digest {
+        &self.levels[0][bucket]
+    }
+
+    pub fn commitment(&self) -> HdbCommitment {
+        HdbCommitment(self.levels[BUCKET_DEPTH][0])
+    }
+
+    /// Proves whether `hash` is in the HDB, given the tree of its bucket. Fails if `tree` is
+    /// not the bucket this accumulator holds for `hash`.
+    pub fn witness(&self, tree: &BucketTree, hash: &HashBytes) -> Result<Witness, HdbAccError> {
+        let bucket = bucket_index(hash);
+        if tree.digest() != *self.bucket_digest(bucket) {
+            return Err(HdbAccError::StaleBucket(bucket));
+        }
+        let mut position = bucket;
+        let bucket_siblings = self.levels[..BUCKET_DEPTH]
+            .iter()
+            .map(|level| {
+                let sibling = level[position ^ 1];
+                position /= 2;
+                sibling
+            })
+            .collect();
+        Ok(Witness {
+            bucket_siblings,
+            bucket: tree.witness(hash),
+        })
+    }
+}
+
+/// An entry of the HDB, along with its path to the root of its bucket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafProof {
+    pub index: u64,
+    pub hash: HashBytes,
+    pub metadata: MetaBytes,
+    /// The sibling at each level, from the leaves up. `None` where the node had no sibling and
+    /// was promoted unchanged.
+    pub siblings: Vec<Option<Digest>>,
+}
+
+impl LeafProof {
+    /// Recomputes the digest of a bucket of `len` entries with this leaf at `self.index`.
+    /// Returns `None` if the path does not have the shape such a bucket would give it.
+    fn bucket_digest(&self, len: u64) -> Option<Digest> {
+        if self.index >= len {
+            return None;
+        }
+        let mut position = self.index;
+        let mut width = len;
+        let mut node = leaf_hash(&self.hash, &self.metadata);
+        let mut siblings = self.siblings.iter();
+        while width > 1 {
+            match (siblings.next()?, (position ^ 1) < width) {
+                (Some(sibling), true) if position % 2 == 0 => node = node_hash(&node, sibling),
+                (Some(sibling), true) => node = node_hash(sibling, &node),
+                (None, false) => {}
+                _ => return None,
+            }
+            position /= 2;
+            width = width.div_ceil(2);
+        }
+        siblings
+            .next()
+            .is_none()
+            .then(|| bucket_digest(len, Some(&node)))
+    }
+}
+
+/// Proof that a hash is, or is not, in its bucket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BucketWitness {
+    /// The hash is the entry at `leaf`.
+    Member { len: u64, leaf: LeafProof },
+    /// The hash falls strictly between `lower` and `upper`, which are adjacent. Either is `None`
+    /// where the hash falls before the first entry of the bucket or after the last.
+    NonMember {
+        len: u64,
+        lower: Option<LeafProof>,
+        upper: Option<LeafProof>,
+    },
+}
+
+impl BucketWitness {
+    /// Recomputes the digest of the bucket of `hash`, if this witness proves what it claims
+    /// about `hash` in it.
+    fn bucket_digest(&self, hash: &HashBytes) -> Option<Digest> {
+        let bucket = bucket_index(hash);
+        let in_bucket = |leaf: &LeafProof| bucket_index(&leaf.hash) == bucket;
+        match self {
+            Self::Member { len, leaf } => {
+                (leaf.hash == *hash).then_some(())?;
+                leaf.bucket_digest(*len)
+            }
+            Self::NonMember { len, lower, upper } => {
+                let lower_digest = match lower {
+                    Some(lower) if lower.hash < *hash && in_bucket(lower) => {
+                        Some(lower.bucket_digest(*len)?)
+                    }
+                    Some(_) => return None,
+                    None => None,
+                };
+                let upper_digest = match upper {
+                    Some(upper) if *hash < upper.hash && in_bucket(upper) => {
+                        Some(upper.bucket_digest(*len)?)
+                    }
+                    Some(_) => return None,
+                    None => None,
+                };
+                match (lower, upper) {
+                    (Some(lower), Some(upper)) => (lower.index + 1 == upper.index
+                        && lower_digest == upper_digest)
+                        .then_some(lower_digest?),
+                    (None, Some(upper)) => (upper.index == 0).then_some(upper_digest?),
+                    (Some(lower), None) => (lower.index + 1 == *len).then_some(lower_digest?),
+                    (None, None) => (*len == 0).then(|| bucket_digest(0, None)),
+                }
+            }
+        }
+    }
+}
+
+/// Proof that a hash is, or is not, in the HDB committed to by an [`HdbCommitment`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Witness {
+    /// The sibling of the hash's bucket at each level of the tree over buckets, from the
+    /// buckets up.
+    pub bucket_siblings: Vec<Digest>,
+    pub bucket: BucketWitness,
+}
+
+impl Witness {
+    pub fn is_member(&self) -> bool {
+        matches!(self.bucket, BucketWitness::Member { .. })
+    }
+
+    /// The metadata of the entry for the hash, if it is a member.
+    pub fn metadata(&self) -> Option<&MetaBytes> {
+        match &self.bucket {
+            BucketWitness::Member { leaf, .. } => Some(&leaf.metadata),
+            BucketWitness::NonMember { .. } => None,
+        }
+    }
+
+    /// Whether this witness proves what it claims about `hash` in the HDB committed to.
+    pub fn verify(&self, commitment: &HdbCommitment, hash: &HashBytes) -> bool {
+        let Some(mut node) = self.bucket.bucket_digest(hash) else {
+            return false;
+        };
+        if self.bucket_siblings.len() != BUCKET_DEPTH {
+            return false;
+        }
+        let mut position = bucket_index(hash);
+        for sibling in &self.bucket_siblings {
+            node = if position % 2 == 0 {
+                node_hash(&node, sibling)
+            } else {
+                node_hash(sibling, &node)
+            };
+            position /= 2;
+        }
+        node == commitment.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A hash in `bucket`, ordered within it by `b`.
+    fn hash(bucket: u16, b: u8) -> HashBytes {
+        let mut hash = [0; HASH_BYTE_LENGTH];
+        hash[..2].copy_from_slice(&bucket.to_be_bytes());
+        hash[2] = b;
+        hash
+    }
+
+    /// Entries with even hashes 2, 4, ..., 2n in bucket 0x0200, so that odd hashes fall between
+    /// them, plus one entry in each of a couple of other buckets.
+    fn sample(n: u8) -> Vec<(HashBytes, MetaBytes)> {
+        (0..n)
+            .map(|i| (hash(0x0200, 2 * i + 2), [i; META_BYTE_LENGTH]))
+            .chain([
+                (hash(0x01ff, 7), [0xaa; META_BYTE_LENGTH]),
+                (hash(0xfe00, 7), [0xbb; META_BYTE_LENGTH]),
+            ])
+            .collect()
+    }
+
+    fn witness(
+        accumulator: &HdbAccumulator,
+        entries: &[(HashBytes, MetaBytes)],
+        hash: &HashBytes,
+    ) -> Witness {
+        let bucket = bucket_index(hash);
+        let tree = BucketTree::new(
+            entries
+                .iter()
+                .copied()
+                .filter(|(entry, _)| bucket_index(entry) == bucket),
+        )
+        .unwrap();
+        accumulator.witness(&tree, hash).unwrap()
+    }
+
+    #[test]
+    fn every_hash_has_a_valid_witness() {
+        for n in 0..20 {
+            let entries = sample(n);
+            let accumulator = HdbAccumulator::new(entries.iter().copied().rev()).unwrap();
+            let commitment = accumulator.commitment();
+            for b in 0..=(2 * n + 3) {
+                let h = hash(0x0200, b);
+                let witness = witness(&accumulator, &entries, &h);
+                assert_eq!(witness.is_member(), b % 2 == 0 && (2..=2 * n).contains(&b));
+                assert!(witness.verify(&commitment, &h), "{b} in {n} entries");
+            }
+            for h in [hash(0x01ff, 7), hash(0x01ff, 8), hash(0x0000, 0), hash(0xffff, 0xff)] {
+                assert!(witness(&accumulator, &entries, &h).verify(&commitment, &h));
+            }
+        }
+    }
+
+    #[test]
+    fn set_bucket_matches_rebuild() {
+        let mut accumulator = HdbAccumulator::new(sample(3)).unwrap();
+        let updated = sample(6);
+        let tree = BucketTree::new(
+            updated
+                .iter()
+                .copied()
+                .filter(|(entry, _)| bucket_index(entry) == 0x0200),
+        )
+        .unwrap();
+        accumulator.set_bucket(0x0200, &tree);
+        assert_eq!(accumulator, HdbAccumulator::new(updated).unwrap());
+        assert_eq!(accumulator.len(), 8);
+    }
+
+    #[test]
+    fn member_witness_carries_metadata() {
+        let entries = sample(5);
+        let accumulator = HdbAccumulator::new(entries.iter().copied()).unwrap();
+        let member = witness(&accumulator, &entries, &hash(0x0200, 6));
+        assert_eq!(member.metadata(), Some(&[2; META_BYTE_LENGTH]));
+        let absent = witness(&accumulator, &entries, &hash(0x0200, 7));
+        assert_eq!(absent.metadata(), None);
+    }
+
+    #[test]
+    fn witness_does_not_verify_for_other_hashes_or_commitments() {
+        let entries = sample(6);
+        let accumulator = HdbAccumulator::new(entries.iter().copied()).unwrap();
+        let commitment = accumulator.commitment();
+
+        let member = witness(&accumulator, &entries, &hash(0x0200, 4));
+        assert!(!member.verify(&commitment, &hash(0x0200, 6)));
+        let absent = witness(&accumulator, &entries, &hash(0x0200, 5));
+        assert!(!absent.verify(&commitment, &hash(0x0200, 7)));
+        assert!(!absent.verify(&commitment, &hash(0x0200, 4)));
+        // The same neighbours, but in another bucket
+        assert!(!absent.verify(&commitment, &hash(0x0300, 5)));
+
+        let other = HdbAccumulator::new(sample(7)).unwrap().commitment();
+        assert!(!member.verify(&other, &hash(0x0200, 4)));
+        assert!(!absent.verify(&other, &hash(0x0200, 5)));
+    }
+
+    #[test]
+    fn non_member_witness_rejects_gaps_and_missing_neighbours() {
+        let entries = sample(6);
+        let accumulator = HdbAccumulator::new(entries.iter().copied()).unwrap();
+        let commitment = accumulator.commitment();
+
+        // Skipping over the entry for 6 would hide it
+        let below = witness(&accumulator, &entries, &hash(0x0200, 5));
+        let above = witness(&accumulator, &entries, &hash(0x0200, 7));
+        let (
+            BucketWitness::NonMember { len, lower, .. },
+            BucketWitness::NonMember { upper, .. },
+        ) = (below.bucket, above.bucket)
+        else {
+            panic!("5 and 7 are not members");
+        };
+        let gap = Witness {
+            bucket_siblings: below.bucket_siblings.clone(),
+            bucket: BucketWitness::NonMember {
+                len,
+                lower: lower.clone(),
+                upper,
+            },
+        };
+        assert!(!gap.verify(&commitment, &hash(0x0200, 6)));
+
+        let open_ended = Witness {
+            bucket_siblings: below.bucket_siblings,
+            bucket: BucketWitness::NonMember {
+                len,
+                lower,
+                upper: None,
+            },
+        };
+        assert!(!open_ended.verify(&commitment, &hash(0x0200, 7)));
+    }
+
+    #[test]
+    fn leaf_proof_rejects_wrong_length() {
+        let entries = sample(6);
+        let accumulator = HdbAccumulator::new(entries.iter().copied()).unwrap();
+        let commitment = accumulator.commitment();
+        let mut witness = witness(&accumulator, &entries, &hash(0x0200, 13));
+        // The last entry claims to be last in a longer bucket, where a member could follow it
+        let BucketWitness::NonMember { len, .. } = &mut witness.bucket else {
+            panic!("13 is not a member");
+        };
+        *len = 7;
+        assert!(!witness.verify(&commitment, &hash(0x0200, 13)));
+    }
+
+    #[test]
+    fn stale_bucket_is_rejected() {
+        let accumulator = HdbAccumulator::new(sample(3)).unwrap();
+        let entries = sample(4).into_iter().filter(|(h, _)| bucket_index(h) == 0x0200);
+        let tree = BucketTree::new(entries).unwrap();
+        assert!(matches!(
+            accumulator.witness(&tree, &hash(0x0200, 1)),
+            Err(HdbAccError::StaleBucket(0x0200))
+        ));
+    }
+
+    #[test]
+    fn duplicate_hashes_are_rejected() {
+        let mut entries = sample(3);
+        entries.push((hash(0x0200, 4), [9; META_BYTE_LENGTH]));
+        assert!(matches!(
+            HdbAccumulator::new(entries),
+            Err(HdbAccError::DuplicateHash(_))
+        ));
+    }
+
+    #[test]
+    fn commitment_round_trips_through_hex() {
+        let commitment = HdbAccumulator::new(sample(3)).unwrap().commitment();
+        let json = serde_json::to_string(&commitment).unwrap();
+        assert_eq!(json, format!("\"{commitment}\""));
+        assert_eq!(serde_json::from_str::<HdbCommitment>(&json).unwrap(), commitment);
+    }
+}
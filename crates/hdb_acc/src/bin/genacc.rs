@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use clap::{crate_version, Parser, Subcommand};
+use hdb_acc::{read_delta_hashes, AccumulatorFile};
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Parser)]
+#[clap(
+    name = "genacc",
+    about = "Generates the accumulator that commits to a SecureDNA hashed database",
+    version = crate_version!()
+)]
+struct Opts {
+    #[clap(help = "path to database (as a directory)")]
+    database: PathBuf,
+
+    #[clap(
+        long,
+        help = "where to read and write the accumulator [default: next to the database's BUILD_INFO.json]"
+    )]
+    accumulator: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Builds the accumulator from every entry of the database
+    Build,
+    /// Updates an existing accumulator after the database was updated, rebuilding only the
+    /// parts the changed entries fall in
+    Update {
+        #[clap(
+            required = true,
+            help = "files of the entries added to or removed from the database since the accumulator was built, in the same layout as the database's shards"
+        )]
+        delta: Vec<PathBuf>,
+    },
+    /// Prints the commitment of an existing accumulator
+    Show,
+}
+
+fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+
+    let opts = Opts::parse();
+    let path = opts
+        .accumulator
+        .clone()
+        .unwrap_or_else(|| AccumulatorFile::path(&opts.database));
+
+    let accumulator = match &opts.command {
+        Command::Build => {
+            let accumulator = AccumulatorFile::build(&opts.database)?;
+            accumulator.write(&path)?;
+            accumulator
+        }
+        Command::Update { delta } => {
+            let mut accumulator = AccumulatorFile::read(&path)?;
+            if accumulator.is_current(&opts.database)? {
+                warn!("BUILD_INFO.json has not changed since the accumulator was built");
+            }
+            let changed = read_delta_hashes(delta).context("failed to read delta")?;
+            info!(entries = changed.len(), "Read delta");
+            accumulator.update(&opts.database, changed)?;
+            accumulator.write(&path)?;
+            accumulator
+        }
+        Command::Show => {
+            let accumulator = AccumulatorFile::read(&path)?;
+            if !accumulator.is_current(&opts.database)? {
+                bail!("accumulator is out of date with the database; run `genacc update`");
+            }
+            accumulator
+        }
+    };
+
+    info!(entries = accumulator.accumulator.len(), "Accumulator at {}", path.display());
+    println!("{}", accumulator.accumulator.commitment());
+    Ok(())
+}
@@ -1,32 +1,72 @@
+mod accumulator;
+mod screening;
+#[cfg(feature = "store")]
+mod store;
+
+pub use accumulator::{
+    bucket_index, BucketTree, BucketWitness, HdbAccumulator, HdbCommitment, LeafProof, Witness,
+    BUCKET_COUNT,
+};
+pub use screening::{is_reverse_screened, ScreeningWitnesses};
+#[cfg(feature = "store")]
+pub use store::{read_bucket, read_delta_hashes, AccumulatorFile, ACCUMULATOR_FILENAME};
+
+#[cfg(feature = "store")]
 use anyhow::{Context, Result};
+#[cfg(feature = "store")]
 use ark_bls12_381::Fr;
+#[cfg(feature = "store")]
 use ark_ff::PrimeField;
+#[cfg(feature = "store")]
 use std::fs::{self, File};
-use std::io::{self, BufReader, Read};
-use std::path::{Path, PathBuf};
+#[cfg(feature = "store")]
+use std::io::{BufReader, Read};
+use std::io;
+use std::path::PathBuf;
+#[cfg(feature = "store")]
+use std::path::Path;
 use thiserror::Error;
+#[cfg(feature = "store")]
 use tracing::{debug, info, instrument};
 
 const ENTRY_BYTE_LENGTH: usize = 40;
 const HASH_BYTE_LENGTH: usize = 32;
+const META_BYTE_LENGTH: usize = ENTRY_BYTE_LENGTH - HASH_BYTE_LENGTH;
+#[cfg(feature = "store")]
 const HLT_FILENAME: &str = "hlt.json";
+#[cfg(feature = "store")]
 const BUILD_INFO_FILENAME: &str = "BUILD_INFO.json";
+#[cfg(feature = "store")]
 const INDEX_DIR_NAME: &str = "index";
 
 #[derive(Error, Debug)]
 pub enum HdbAccError {
     #[error("Invalid entry size in file {0}: expected multiple of {ENTRY_BYTE_LENGTH}, got {1}")]
     InvalidEntrySize(PathBuf, usize),
+    #[error("Duplicate hash in HDB: {0}")]
+    DuplicateHash(String),
+    #[error("Shard file {0} is not sorted")]
+    UnsortedShard(PathBuf),
+    #[error("Bucket {0} does not match the accumulator; the HDB has changed since it was built")]
+    StaleBucket(usize),
+    #[error("Not an HDB accumulator file")]
+    InvalidAccumulatorFile,
+    #[error("Expected a witness for each of {expected} hashes, got {actual}")]
+    WitnessCount { expected: usize, actual: usize },
+    #[error("The witness for hash {0} does not verify against the HDB commitment")]
+    InvalidWitness(usize),
     #[error("IO Error during HDB processing")]
     IoError(#[from] io::Error),
 }
 
+#[cfg(feature = "store")]
 /// Converts a 32-byte hash (typically little-endian) into an Fr element.
 /// Uses arkworks' modular reduction.
 fn hash_bytes_to_fr(bytes: &[u8; HASH_BYTE_LENGTH]) -> Fr {
     Fr::from_le_bytes_mod_order(bytes)
 }
 
+#[cfg(feature = "store")]
 /// Iterates through the HDB shard files in the given root directory,
 /// reads all entries, extracts the 32-byte hashes, converts them to
 /// BLS12-381 scalar field elements (Fr), and returns them as a Vec.
@@ -39,6 +79,24 @@ pub fn load_hdb_hashes_as_scalars(hdb_root_path: impl AsRef<Path>) -> Result<Vec
     info!(path = %root.display(), "Loading HDB hashes from directory");
 
     let mut all_scalars = Vec::new();
+    for shard_path in hdb_shard_paths(root)? {
+        for (hash_bytes, _) in read_shard_entries(&shard_path)? {
+            // Convert to Fr
+            let scalar = hash_bytes_to_fr(&hash_bytes);
+            all_scalars.push(scalar);
+        }
+    }
+
+    info!(total_hashes = all_scalars.len(), "Finished loading and converting HDB hashes");
+    Ok(all_scalars)
+}
+
+#[cfg(feature = "store")]
+/// Lists the HDB shard files in the given root directory, sorted by name.
+///
+/// Skips the 'index' directory, 'hlt.json', 'BUILD_INFO.json', and
+/// any files with extensions.
+fn hdb_shard_paths(root: &Path) -> Result<Vec<PathBuf>> {
     let mut shard_paths = Vec::new();
 
     for entry_result in fs::read_dir(root)
@@ -82,36 +140,37 @@ pub fn load_hdb_hashes_as_scalars(hdb_root_path: impl AsRef<Path>) -> Result<Vec
 
     info!(count = shard_paths.len(), "Found HDB shard files to process");
 
-    for shard_path in shard_paths {
-        debug!(path = %shard_path.display(), "Processing shard file");
-        let file = File::open(&shard_path)
-            .with_context(|| format!("Failed to open shard file '{}'", shard_path.display()))?;
-        let mut reader = BufReader::new(file);
-        let mut buffer = Vec::new();
-        reader.read_to_end(&mut buffer)
-            .with_context(|| format!("Failed to read shard file '{}'", shard_path.display()))?;
-
-        if buffer.len() % ENTRY_BYTE_LENGTH != 0 {
-            return Err(HdbAccError::InvalidEntrySize(shard_path.clone(), buffer.len()).into());
-        }
-
-        for chunk in buffer.chunks_exact(ENTRY_BYTE_LENGTH) {
-            // Extract the first 32 bytes (hash)
-            let hash_bytes: &[u8; HASH_BYTE_LENGTH] = chunk[..HASH_BYTE_LENGTH]
-                .try_into()
-                .expect("Chunk size is guaranteed to be correct by chunks_exact");
+    Ok(shard_paths)
+}
 
-            // Convert to Fr
-            let scalar = hash_bytes_to_fr(hash_bytes);
-            all_scalars.push(scalar);
-        }
+#[cfg(feature = "store")]
+/// Reads every entry of a shard file, split into its hash and metadata.
+fn read_shard_entries(
+    shard_path: &Path,
+) -> Result<Vec<([u8; HASH_BYTE_LENGTH], [u8; META_BYTE_LENGTH])>> {
+    debug!(path = %shard_path.display(), "Processing shard file");
+    let file = File::open(shard_path)
+        .with_context(|| format!("Failed to open shard file '{}'", shard_path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut buffer = Vec::new();
+    reader.read_to_end(&mut buffer)
+        .with_context(|| format!("Failed to read shard file '{}'", shard_path.display()))?;
+
+    if buffer.len() % ENTRY_BYTE_LENGTH != 0 {
+        return Err(HdbAccError::InvalidEntrySize(shard_path.to_path_buf(), buffer.len()).into());
     }
 
-    info!(total_hashes = all_scalars.len(), "Finished loading and converting HDB hashes");
-    Ok(all_scalars)
+    Ok(buffer
+        .chunks_exact(ENTRY_BYTE_LENGTH)
+        .map(|chunk| {
+            let (hash, metadata) = chunk.split_at(HASH_BYTE_LENGTH);
+            // Chunk size is guaranteed to be correct by chunks_exact
+            (hash.try_into().unwrap(), metadata.try_into().unwrap())
+        })
+        .collect())
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "store"))]
 mod tests {
     use super::*;
     use std::io::Write;
@@ -0,0 +1,130 @@
+//! Screening an order's hashes against an [`HdbCommitment`] from witnesses alone, as the
+//! verification program does inside the zkVM.
+//!
+//! This only sees the 8 metadata bytes of each hit, not the hazard lookup table, so it cannot
+//! weigh a hit's tags against the request's region or exemptions the way the HDB does. Every
+//! hit denies synthesis unless its entry was reverse screened. A granted verdict therefore
+//! means the order matched nothing that could deny it; a denied one may be an order the HDB
+//! would have let through.
+
+use doprf::proof_output::HdbHits;
+use serde::{Deserialize, Serialize};
+
+use crate::accumulator::{HashBytes, HdbCommitment, MetaBytes, Witness};
+use crate::HdbAccError;
+
+/// The `reverse_screened` flag of the HDB's `Metadata`, in its little-endian `u64` encoding.
+const REVERSE_SCREENED_BIT: u64 = 1 << 4;
+
+/// Whether an HDB entry with this metadata was reverse screened, which means hits on it are
+/// expected in harmless sequences and never deny synthesis.
+pub fn is_reverse_screened(metadata: &MetaBytes) -> bool {
+    u64::from_le_bytes(*metadata) & REVERSE_SCREENED_BIT != 0
+}
+
+/// The HDB commitment an order is screened against, and a witness for each of its hashes in
+/// order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreeningWitnesses {
+    pub commitment: HdbCommitment,
+    pub witnesses: Vec<Witness>,
+}
+
+impl ScreeningWitnesses {
+    /// Checks each witness against the hash at the same position, and counts the hits. Fails
+    /// unless every hash has a witness that verifies.
+    pub fn hits(&self, hashes: &[HashBytes]) -> Result<HdbHits, HdbAccError> {
+        if hashes.len() != self.witnesses.len() {
+            return Err(HdbAccError::WitnessCount {
+                expected: hashes.len(),
+                actual: self.witnesses.len(),
+            });
+        }
+        let mut hits = HdbHits::default();
+        for (i, (hash, witness)) in hashes.iter().zip(&self.witnesses).enumerate() {
+            if !witness.verify(&self.commitment, hash) {
+                return Err(HdbAccError::InvalidWitness(i));
+            }
+            if let Some(metadata) = witness.metadata() {
+                hits.hits += 1;
+                if is_reverse_screened(metadata) {
+                    hits.reverse_screened_hits += 1;
+                }
+            }
+        }
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bucket_index, BucketTree, HdbAccumulator};
+
+    const PLAIN: MetaBytes = [0; 8];
+    const REVERSE_SCREENED: MetaBytes = [REVERSE_SCREENED_BIT as u8, 0, 0, 0, 0, 0, 0, 0];
+
+    fn hash(b: u8) -> HashBytes {
+        [b; 32]
+    }
+
+    fn witnesses(entries: &[(HashBytes, MetaBytes)], hashes: &[HashBytes]) -> ScreeningWitnesses {
+        let accumulator = HdbAccumulator::new(entries.iter().copied()).unwrap();
+        let witnesses = hashes
+            .iter()
+            .map(|hash| {
+                let bucket = bucket_index(hash);
+                let tree = BucketTree::new(
+                    entries
+                        .iter()
+                        .copied()
+                        .filter(|(entry, _)| bucket_index(entry) == bucket),
+                )
+                .unwrap();
+                accumulator.witness(&tree, hash).unwrap()
+            })
+            .collect();
+        ScreeningWitnesses {
+            commitment: accumulator.commitment(),
+            witnesses,
+        }
+    }
+
+    #[test]
+    fn counts_hits_and_reverse_screened_hits() {
+        let entries = [(hash(1), PLAIN), (hash(2), REVERSE_SCREENED), (hash(3), PLAIN)];
+        let hashes = [hash(1), hash(2), hash(4), hash(2), hash(5)];
+        let hits = witnesses(&entries, &hashes).hits(&hashes).unwrap();
+        assert_eq!(
+            hits,
+            HdbHits {
+                hits: 3,
+                reverse_screened_hits: 2,
+            }
+        );
+        assert!(hits.is_denied());
+
+        let clean = [hash(2), hash(4)];
+        let hits = witnesses(&entries, &clean).hits(&clean).unwrap();
+        assert!(!hits.is_denied());
+    }
+
+    #[test]
+    fn rejects_missing_or_mismatched_witnesses() {
+        let entries = [(hash(1), PLAIN)];
+        let hashes = [hash(1), hash(4)];
+        let screening = witnesses(&entries, &hashes);
+        assert!(matches!(
+            screening.hits(&hashes[..1]),
+            Err(HdbAccError::WitnessCount {
+                expected: 1,
+                actual: 2
+            })
+        ));
+        // A non-member witness for 4 says nothing about 1
+        assert!(matches!(
+            screening.hits(&[hash(4), hash(1)]),
+            Err(HdbAccError::InvalidWitness(0))
+        ));
+    }
+}
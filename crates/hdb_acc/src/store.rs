@@ -0,0 +1,453 @@
+//! Building an [`HdbAccumulator`] from the HDB on disk, and persisting it next to the HDB.
+//!
+//! The accumulator file holds every bucket's length and digest, along with the digest of the
+//! `BUILD_INFO.json` it was built for. It is small (under 3MB) however large the HDB is; the
+//! entries themselves are read back from the HDB shards a bucket at a time when needed.
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use doprf::tagged::TaggedHash;
+use sha3::{Digest as _, Sha3_256};
+use tracing::{debug, info, instrument};
+
+use crate::accumulator::{
+    bucket_index, BucketTree, Digest, HashBytes, HdbAccumulator, MetaBytes, Witness,
+    BUCKET_COUNT,
+};
+use crate::screening::ScreeningWitnesses;
+use crate::{
+    hdb_shard_paths, read_shard_entries, HdbAccError, BUILD_INFO_FILENAME, ENTRY_BYTE_LENGTH,
+    HASH_BYTE_LENGTH,
+};
+
+/// The name of the accumulator file within the HDB directory.
+pub const ACCUMULATOR_FILENAME: &str = "hdb.acc";
+
+const MAGIC: &[u8; 8] = b"SDNAACC\x01";
+
+/// An accumulator, along with the HDB build it was built for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccumulatorFile {
+    /// The digest of the HDB's `BUILD_INFO.json`, if it had one.
+    pub build_info_digest: Option<Digest>,
+    pub accumulator: HdbAccumulator,
+}
+
+impl AccumulatorFile {
+    pub fn path(hdb_root_path: impl AsRef<Path>) -> PathBuf {
+        hdb_root_path.as_ref().join(ACCUMULATOR_FILENAME)
+    }
+
+    /// Streams every shard of the HDB at `hdb_root_path` into a new accumulator. Only one
+    /// bucket's entries are held in memory at a time.
+    #[instrument(skip(hdb_root_path))]
+    pub fn build(hdb_root_path: impl AsRef<Path>) -> Result<Self> {
+        let root = hdb_root_path.as_ref();
+        let mut accumulator = HdbAccumulator::default();
+        for shard_path in hdb_shard_paths(root)? {
+            build_shard(&mut accumulator, &shard_path)?;
+        }
+        info!(
+            entries = accumulator.len(),
+            commitment = %accumulator.commitment(),
+            "Built HDB accumulator"
+        );
+        Ok(Self {
+            build_info_digest: build_info_digest(root)?,
+            accumulator,
+        })
+    }
+
+    /// Brings this accumulator up to date with the HDB at `hdb_root_path`, given the entries
+    /// that were added to or removed from it since. Only the buckets those entries fall in are
+    /// rebuilt, from their current contents in the HDB.
+    #[instrument(skip_all)]
+    pub fn update(
+        &mut self,
+        hdb_root_path: impl AsRef<Path>,
+        changed: impl IntoIterator<Item = HashBytes>,
+    ) -> Result<()> {
+        let root = hdb_root_path.as_ref();
+        let buckets: BTreeSet<_> = changed.into_iter().map(|hash| bucket_index(&hash)).collect();
+        info!(buckets = buckets.len(), "Rebuilding changed buckets");
+        for bucket in buckets {
+            let tree = read_bucket(root, bucket)?;
+            self.accumulator.set_bucket(bucket, &tree);
+        }
+        self.build_info_digest = build_info_digest(root)?;
+        info!(
+            entries = self.accumulator.len(),
+            commitment = %self.accumulator.commitment(),
+            "Updated HDB accumulator"
+        );
+        Ok(())
+    }
+
+    /// Whether this accumulator was built for the HDB build at `hdb_root_path`.
+    pub fn is_current(&self, hdb_root_path: impl AsRef<Path>) -> Result<bool> {
+        Ok(self.build_info_digest == build_info_digest(hdb_root_path.as_ref())?)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open accumulator file '{}'", path.display()))?;
+        Self::read_from(BufReader::new(file))
+            .with_context(|| format!("Failed to read accumulator file '{}'", path.display()))
+    }
+
+    fn read_from(mut reader: impl Read) -> Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(HdbAccError::InvalidAccumulatorFile.into());
+        }
+        let mut has_build_info = [0; 1];
+        reader.read_exact(&mut has_build_info)?;
+        let mut build_info_digest = [0; 32];
+        reader.read_exact(&mut build_info_digest)?;
+        let build_info_digest = match has_build_info {
+            [0] => None,
+            [1] => Some(build_info_digest),
+            _ => return Err(HdbAccError::InvalidAccumulatorFile.into()),
+        };
+
+        let mut bucket_lens = Vec::with_capacity(BUCKET_COUNT);
+        let mut bucket_digests = Vec::with_capacity(BUCKET_COUNT);
+        for _ in 0..BUCKET_COUNT {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len)?;
+            bucket_lens.push(u64::from_le_bytes(len));
+            let mut digest = [0; 32];
+            reader.read_exact(&mut digest)?;
+            bucket_digests.push(digest);
+        }
+        if reader.read(&mut [0])? != 0 {
+            return Err(HdbAccError::InvalidAccumulatorFile.into());
+        }
+        Ok(Self {
+            build_info_digest,
+            accumulator: HdbAccumulator::from_buckets(bucket_lens, bucket_digests),
+        })
+    }
+
+    /// Writes this accumulator to `path`, replacing any existing file only once it is complete.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let wip_path = path.with_extension("wip");
+        let mut writer = BufWriter::new(File::create(&wip_path).with_context(|| {
+            format!("Failed to create accumulator file '{}'", wip_path.display())
+        })?);
+        self.write_to(&mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&wip_path, path)
+            .with_context(|| format!("Failed to write accumulator file '{}'", path.display()))?;
+        Ok(())
+    }
+
+    fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[self.build_info_digest.is_some() as u8])?;
+        writer.write_all(&self.build_info_digest.unwrap_or_default())?;
+        for bucket in 0..BUCKET_COUNT {
+            writer.write_all(&self.accumulator.bucket_len(bucket).to_le_bytes())?;
+            writer.write_all(self.accumulator.bucket_digest(bucket))?;
+        }
+        Ok(())
+    }
+
+    /// Proves whether `hash` is in the HDB at `hdb_root_path`, which must be the build this
+    /// accumulator is for.
+    pub fn witness(&self, hdb_root_path: impl AsRef<Path>, hash: &HashBytes) -> Result<Witness> {
+        let tree = read_bucket(hdb_root_path.as_ref(), bucket_index(hash))?;
+        Ok(self.accumulator.witness(&tree, hash)?)
+    }
+
+    /// Proves whether the hash of `tagged_hash` is in the HDB. The tag plays no part.
+    pub fn witness_for(
+        &self,
+        hdb_root_path: impl AsRef<Path>,
+        tagged_hash: &TaggedHash,
+    ) -> Result<Witness> {
+        self.witness(hdb_root_path, &(&tagged_hash.hash).into())
+    }
+
+    /// Everything the verification program needs to screen `tagged_hashes` against this
+    /// accumulator itself. Each bucket is read from the HDB once, however many hashes fall in it.
+    pub fn screening_witnesses(
+        &self,
+        hdb_root_path: impl AsRef<Path>,
+        tagged_hashes: &[TaggedHash],
+    ) -> Result<ScreeningWitnesses> {
+        let root = hdb_root_path.as_ref();
+        let mut trees = BTreeMap::new();
+        let mut witnesses = Vec::with_capacity(tagged_hashes.len());
+        for tagged_hash in tagged_hashes {
+            let hash: HashBytes = (&tagged_hash.hash).into();
+            let bucket = bucket_index(&hash);
+            let tree = match trees.entry(bucket) {
+                Entry::Occupied(tree) => tree.into_mut(),
+                Entry::Vacant(slot) => slot.insert(read_bucket(root, bucket)?),
+            };
+            witnesses.push(self.accumulator.witness(tree, &hash)?);
+        }
+        Ok(ScreeningWitnesses {
+            commitment: self.accumulator.commitment(),
+            witnesses,
+        })
+    }
+}
+
+fn build_info_digest(root: &Path) -> Result<Option<Digest>> {
+    match fs::read(root.join(BUILD_INFO_FILENAME)) {
+        Ok(build_info) => Ok(Some(Sha3_256::digest(build_info).into())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).context("Failed to read build info"),
+    }
+}
+
+fn read_entry(reader: &mut impl Read) -> io::Result<Option<(HashBytes, MetaBytes)>> {
+    let mut entry = [0; ENTRY_BYTE_LENGTH];
+    match reader.read_exact(&mut entry) {
+        Ok(()) => {
+            let (hash, metadata) = entry.split_at(HASH_BYTE_LENGTH);
+            Ok(Some((hash.try_into().unwrap(), metadata.try_into().unwrap())))
+        }
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Streams a shard's entries, which are sorted, into the accumulator a bucket at a time.
+fn build_shard(accumulator: &mut HdbAccumulator, shard_path: &Path) -> Result<()> {
+    debug!(path = %shard_path.display(), "Processing shard file");
+    let file = File::open(shard_path)
+        .with_context(|| format!("Failed to open shard file '{}'", shard_path.display()))?;
+    let len = file.metadata()?.len();
+    if len % ENTRY_BYTE_LENGTH as u64 != 0 {
+        return Err(HdbAccError::InvalidEntrySize(shard_path.to_path_buf(), len as usize).into());
+    }
+
+    let mut reader = BufReader::new(file);
+    let mut bucket = None;
+    let mut entries = Vec::new();
+    let mut finished = BTreeSet::new();
+    loop {
+        let entry = read_entry(&mut reader)?;
+        let next_bucket = entry.as_ref().map(|(hash, _)| bucket_index(hash));
+        if next_bucket != bucket {
+            if let Some(bucket) = bucket {
+                accumulator.set_bucket(bucket, &BucketTree::new(entries.drain(..))?);
+                finished.insert(bucket);
+            }
+            if next_bucket.is_some_and(|b| finished.contains(&b)) {
+                return Err(HdbAccError::UnsortedShard(shard_path.to_path_buf()).into());
+            }
+            bucket = next_bucket;
+        }
+        match entry {
+            Some(entry) => entries.push(entry),
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Reads the entries of one bucket from the HDB at `root`, binary searching its shard for them.
+pub fn read_bucket(root: &Path, bucket: usize) -> Result<BucketTree> {
+    let [prefix, _] = (bucket as u16).to_be_bytes();
+    let shard_path = root.join(hex::encode([prefix]));
+    let mut file = match File::open(&shard_path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BucketTree::new(Vec::new())?),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to open shard file '{}'", shard_path.display()))
+        }
+    };
+    let entry_count = file.metadata()?.len() / ENTRY_BYTE_LENGTH as u64;
+
+    // The index of the first entry whose bucket is not below `bucket`
+    let mut lower_bound = |bucket: usize| -> io::Result<u64> {
+        let (mut low, mut high) = (0, entry_count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            file.seek(SeekFrom::Start(mid * ENTRY_BYTE_LENGTH as u64))?;
+            let (hash, _) = read_entry(&mut file)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+            if bucket_index(&hash) < bucket {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(low)
+    };
+    let start = lower_bound(bucket)?;
+    let end = lower_bound(bucket + 1)?;
+
+    file.seek(SeekFrom::Start(start * ENTRY_BYTE_LENGTH as u64))?;
+    let mut reader = BufReader::new(file).take((end - start) * ENTRY_BYTE_LENGTH as u64);
+    let mut entries = Vec::with_capacity((end - start) as usize);
+    while let Some(entry) = read_entry(&mut reader)? {
+        entries.push(entry);
+    }
+    Ok(BucketTree::new(entries)?)
+}
+
+/// Reads the hashes of entries in the shard layout from each of `paths`, such as the entries an
+/// HDB update added or removed.
+pub fn read_delta_hashes(paths: &[PathBuf]) -> Result<Vec<HashBytes>> {
+    let mut hashes = Vec::new();
+    for path in paths {
+        hashes.extend(read_shard_entries(path)?.into_iter().map(|(hash, _)| hash));
+    }
+    Ok(hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use doprf::prf::CompletedHashValue;
+    use doprf::tagged::HashTag;
+    use tempfile::tempdir;
+
+    fn entry(bucket: u16, b: u8) -> (HashBytes, MetaBytes) {
+        let mut hash = [0; HASH_BYTE_LENGTH];
+        hash[..2].copy_from_slice(&bucket.to_be_bytes());
+        hash[2] = b;
+        (hash, [b; 8])
+    }
+
+    /// Writes `entries` as sorted HDB shards under `root`, replacing any existing shards.
+    fn write_hdb(root: &Path, entries: &[(HashBytes, MetaBytes)]) {
+        for shard in hdb_shard_paths(root).unwrap() {
+            fs::remove_file(shard).unwrap();
+        }
+        let mut entries = entries.to_vec();
+        entries.sort();
+        for (hash, metadata) in entries {
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(root.join(hex::encode([hash[0]])))
+                .unwrap();
+            file.write_all(&hash).unwrap();
+            file.write_all(&metadata).unwrap();
+        }
+    }
+
+    fn sample() -> Vec<(HashBytes, MetaBytes)> {
+        vec![
+            entry(0x0000, 1),
+            entry(0x0002, 4),
+            entry(0x0002, 2),
+            entry(0x02fe, 9),
+            entry(0xfe00, 3),
+        ]
+    }
+
+    #[test]
+    fn build_matches_in_memory_accumulator() {
+        let hdb = tempdir().unwrap();
+        write_hdb(hdb.path(), &sample());
+        fs::write(hdb.path().join(BUILD_INFO_FILENAME), "{}").unwrap();
+
+        let built = AccumulatorFile::build(hdb.path()).unwrap();
+        assert_eq!(built.accumulator, HdbAccumulator::new(sample()).unwrap());
+        assert!(built.build_info_digest.is_some());
+        assert!(built.is_current(hdb.path()).unwrap());
+    }
+
+    #[test]
+    fn file_round_trips() {
+        let hdb = tempdir().unwrap();
+        write_hdb(hdb.path(), &sample());
+        let built = AccumulatorFile::build(hdb.path()).unwrap();
+        assert_eq!(built.build_info_digest, None);
+
+        let path = AccumulatorFile::path(hdb.path());
+        built.write(&path).unwrap();
+        assert_eq!(AccumulatorFile::read(&path).unwrap(), built);
+        // The accumulator file is not mistaken for a shard
+        assert_eq!(AccumulatorFile::build(hdb.path()).unwrap(), built);
+    }
+
+    #[test]
+    fn update_matches_rebuild() {
+        let hdb = tempdir().unwrap();
+        write_hdb(hdb.path(), &sample());
+        let mut accumulator = AccumulatorFile::build(hdb.path()).unwrap();
+
+        let mut updated = sample();
+        let removed = updated.remove(1);
+        let added = [entry(0x0002, 3), entry(0x8000, 1)];
+        updated.extend(added);
+        write_hdb(hdb.path(), &updated);
+        fs::write(hdb.path().join(BUILD_INFO_FILENAME), "{\"new\": true}").unwrap();
+        assert!(!accumulator.is_current(hdb.path()).unwrap());
+
+        let changed = added.iter().chain([&removed]).map(|(hash, _)| *hash);
+        accumulator.update(hdb.path(), changed).unwrap();
+        assert_eq!(accumulator, AccumulatorFile::build(hdb.path()).unwrap());
+    }
+
+    #[test]
+    fn witnesses_are_read_from_the_hdb() {
+        let hdb = tempdir().unwrap();
+        write_hdb(hdb.path(), &sample());
+        let accumulator = AccumulatorFile::build(hdb.path()).unwrap();
+        let commitment = accumulator.accumulator.commitment();
+
+        let cases = [
+            (entry(0x0002, 2).0, true),
+            (entry(0x0002, 3).0, false),
+            (entry(0x0100, 0).0, false),
+        ];
+        for (hash, present) in cases {
+            let witness = accumulator.witness(hdb.path(), &hash).unwrap();
+            assert_eq!(witness.is_member(), present);
+            assert!(witness.verify(&commitment, &hash));
+        }
+    }
+
+    #[test]
+    fn screening_witnesses_cover_every_hash() {
+        let tagged_hashes: Vec<_> = (0..4u8)
+            .map(|i| TaggedHash {
+                tag: HashTag::new(i == 0, 0, i.into()),
+                hash: CompletedHashValue::hash_from_bytes_for_tests_only(&[i]),
+            })
+            .collect();
+        let hashes: Vec<HashBytes> = tagged_hashes.iter().map(|h| (&h.hash).into()).collect();
+        // Only the first two windows are hazardous
+        let entries: Vec<_> = hashes[..2].iter().map(|hash| (*hash, [0; 8])).collect();
+        let hdb = tempdir().unwrap();
+        write_hdb(hdb.path(), &entries);
+        let accumulator = AccumulatorFile::build(hdb.path()).unwrap();
+
+        let screening = accumulator
+            .screening_witnesses(hdb.path(), &tagged_hashes)
+            .unwrap();
+        assert_eq!(screening.commitment, accumulator.accumulator.commitment());
+        assert_eq!(screening.hits(&hashes).unwrap().hits, 2);
+    }
+
+    #[test]
+    fn unsorted_shard_is_rejected() {
+        let hdb = tempdir().unwrap();
+        let mut file = File::create(hdb.path().join("00")).unwrap();
+        for (hash, metadata) in [entry(0x0002, 1), entry(0x0001, 1), entry(0x0002, 2)] {
+            file.write_all(&hash).unwrap();
+            file.write_all(&metadata).unwrap();
+        }
+        let err = AccumulatorFile::build(hdb.path()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<HdbAccError>(),
+            Some(HdbAccError::UnsortedShard(_))
+        ));
+    }
+}
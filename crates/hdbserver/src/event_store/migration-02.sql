@@ -0,0 +1,17 @@
+-- Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+-- SPDX-License-Identifier: MIT OR Apache-2.0
+
+-- Screening proofs that clients sent and that were rejected, with the verifying key hash the
+-- client claimed and why the proof was rejected.
+
+CREATE TABLE rejected_proofs(
+    rejection_id INTEGER PRIMARY KEY,
+    client_mid BLOB NOT NULL CHECK(length(client_mid) = 16),
+    vk_hash TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    timestamp_utc INTEGER NOT NULL,
+    FOREIGN KEY (client_mid) REFERENCES certs(client_mid) ON DELETE RESTRICT
+) STRICT;
+
+CREATE INDEX idx_rejected_proofs_client_mid ON rejected_proofs(client_mid);
+CREATE INDEX idx_rejected_proofs_timestamp_utc ON rejected_proofs(timestamp_utc);
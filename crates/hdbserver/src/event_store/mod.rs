@@ -30,6 +30,7 @@ pub async fn open_db(path: impl AsRef<Path>) -> Result<Connection, OpenError> {
         Migrations::from_iter([
             M::up(include_str!("migration-00.sql")),
             M::up(include_str!("migration-01.sql")),
+            M::up(include_str!("migration-02.sql")),
         ]),
     )
     .await
@@ -188,6 +189,31 @@ pub async fn insert_ratelimit_exceedance_at_time(
     Ok(())
 }
 
+pub async fn insert_rejected_proof(
+    conn: &Connection,
+    client_mid: Id,
+    vk_hash: String,
+    reason: String,
+) -> Result<(), tokio_rusqlite::Error> {
+    conn.call(move |conn| {
+        conn.execute(
+            r#"
+            INSERT INTO rejected_proofs (client_mid, vk_hash, reason, timestamp_utc)
+            VALUES (?1, ?2, ?3, ?4);
+            "#,
+            params![
+                SqlCertificateId(client_mid),
+                vk_hash,
+                reason,
+                SqlOffsetDateTime::now_utc(),
+            ],
+        )?;
+        Ok(())
+    })
+    .await?;
+    Ok(())
+}
+
 pub async fn query_client_screened_bp_in_last_day(
     conn: &Connection,
     client_mid: Id,
@@ -615,4 +641,41 @@ mod tests {
         assert!(exceedances_per_day.contains(&(date.into(), client_2_id, 1)));
         assert!(exceedances_per_day.contains(&(date_1.into(), client_1_id, 1)));
     }
+
+    #[tokio::test]
+    async fn insert_rejected_proofs() {
+        let conn = open_db(":memory:").await.unwrap();
+
+        let [client_1] = make_synth_tokens();
+        let client_1_id = *client_1.token.issuance_id();
+        insert_open_event(&conn, &client_1, 0).await.unwrap();
+
+        insert_rejected_proof(
+            &conn,
+            client_1_id,
+            "0x01".to_owned(),
+            "screening proof is invalid: bad proof".to_owned(),
+        )
+        .await
+        .unwrap();
+
+        let rejected: Vec<(SqlCertificateId, String, String)> = conn
+            .call(|conn| {
+                Ok(conn
+                    .prepare("SELECT client_mid, vk_hash, reason FROM rejected_proofs")?
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                    .map(|x| x.unwrap())
+                    .collect())
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            rejected,
+            vec![(
+                SqlCertificateId(client_1_id),
+                "0x01".to_owned(),
+                "screening proof is invalid: bad proof".to_owned()
+            )]
+        );
+    }
 }
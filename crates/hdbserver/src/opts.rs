@@ -3,7 +3,11 @@
 
 use std::path::{Path, PathBuf};
 
+#[cfg(feature = "zk")]
+use clap::ArgAction;
 use clap::{crate_version, Args, Parser};
+#[cfg(feature = "zk")]
+use doprf::active_security::Commitment;
 use serde::Deserialize;
 
 use minhttp::mpserver::{cli::ServerConfigSource, traits::RelativeConfig};
@@ -152,6 +156,72 @@ pub struct Config {
     )]
     #[serde(default = "Config::default_event_store_path")]
     pub event_store_path: PathBuf,
+
+    #[clap(
+        long,
+        action = ArgAction::Set,
+        value_delimiter = ',',
+        help = "Keyserver generations this database can be screened with. While keyservers move to a refreshed keyshare, list both the old and the new generation.",
+        env = "SECUREDNA_HDBSERVER_SUPPORTED_GENERATIONS",
+        default_values_t = Config::default_supported_generations()
+    )]
+    #[serde(default = "Config::default_supported_generations")]
+    pub supported_generations: Vec<u32>,
+
+    #[cfg(feature = "zk")]
+    #[clap(
+        long,
+        help = "Hex-encoded bytes32 hash of the verification program's verifying key. Screening proofs committed under any other key are rejected. Defaults to the key of the verification program bundled with this server.",
+        env = "SECUREDNA_HDBSERVER_VERIFICATION_VKEY_HASH"
+    )]
+    pub verification_vkey_hash: Option<String>,
+
+    #[cfg(feature = "zk")]
+    #[clap(
+        long,
+        help = "Hex-encoded bytes32 vkey digest of the hash program, whose proofs the verification program aggregates. Required along with verification_vkey_hash, which pins a verification program that may aggregate proofs of a different hash program than the one bundled with this server.",
+        env = "SECUREDNA_HDBSERVER_HASH_VKEY_DIGEST"
+    )]
+    pub hash_vkey_digest: Option<String>,
+
+    #[cfg(feature = "zk")]
+    #[clap(
+        long,
+        help = "Hex-encoded bytes32 vkey digest of the checksum program, whose proof the verification program aggregates. Required along with verification_vkey_hash.",
+        env = "SECUREDNA_HDBSERVER_CHECKSUM_VKEY_DIGEST"
+    )]
+    pub checksum_vkey_digest: Option<String>,
+
+    #[cfg(feature = "zk")]
+    #[clap(
+        long,
+        action = ArgAction::Set,
+        value_delimiter = ',',
+        env = "SECUREDNA_HDBSERVER_ACTIVE_SECURITY_KEY",
+        help = "List of commitments comprising the keyservers' active security key. Screening proofs that validated the keyserver responses against any other key are rejected.",
+    )]
+    pub active_security_key: Vec<Commitment>,
+
+    #[cfg(feature = "zk")]
+    #[clap(
+        long,
+        action = ArgAction::Set,
+        value_delimiter = ',',
+        env = "SECUREDNA_HDBSERVER_PREVIOUS_ACTIVE_SECURITY_KEY",
+        help = "List of commitments comprising the keyservers' previous active security key. Screening proofs that validated against it are also accepted, for as long as clients may still screen with the previous generation of keyshares.",
+    )]
+    #[serde(default)]
+    pub previous_active_security_key: Vec<Commitment>,
+
+    #[cfg(feature = "zk")]
+    #[clap(
+        long,
+        help = "Verify screening proofs as if they came from SP1's mock prover, which checks only their shape. Such proofs attest to nothing, so this must only be used for testing without proving hardware.",
+        env = "SECUREDNA_HDBSERVER_ACCEPT_MOCK_PROOFS",
+        default_value_t = false
+    )]
+    #[serde(default)]
+    pub accept_mock_proofs: bool,
 }
 
 impl Config {
@@ -178,6 +248,10 @@ impl Config {
     pub fn default_event_store_path() -> PathBuf {
         ":memory:".into()
     }
+
+    pub fn default_supported_generations() -> Vec<u32> {
+        vec![0]
+    }
 }
 
 pub const DEFAULT_HASH_SPEC: &str = r#"{
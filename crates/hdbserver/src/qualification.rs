@@ -42,7 +42,8 @@ pub async fn qualification(
     }
 
     let response = HdbQualificationResponse {
-        supported_generations: vec![0],
+        supported_generations: hdbs_state.supported_generations.clone(),
+        hdb_commitment: hdbs_state.hdb_commitment.map(|c| c.to_string()),
     };
 
     let json = serde_json::to_string(&response).map_err(|err| {
@@ -2,22 +2,30 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use std::sync::Arc;
+#[cfg(feature = "zk")]
+use std::time::Instant;
+#[cfg(feature = "zk")]
 use tracing::debug;
 use anyhow::Context;
-use doprf::prf::{CompletedHashValue, VerificationInput};
+use doprf::prf::CompletedHashValue;
+#[cfg(feature = "zk")]
+use doprf::prf::VerificationInput;
+#[cfg(feature = "zk")]
+use doprf::proof_output::{ScreeningProofOutput, ScreeningStatus, SubProofVkeys};
+#[cfg(feature = "zk")]
+use hdb_acc::HdbCommitment;
 use futures::{StreamExt, TryStreamExt};
+#[cfg(feature = "zk")]
 use http_body_util::{BodyExt, Full};
+#[cfg(feature = "zk")]
 use bytes::Bytes;
 use hyper::body::{Body, Incoming};
 use hyper::{Request, StatusCode};
 use scep::states::{EtState, ServerStateForClient};
 use scep::steps::{server_et_client, server_et_seq_hashes_client};
 use tracing::{error, info, warn};
-use packed_ristretto::PackedRistrettos;
-use sp1_sdk::{
-    include_elf, HashableKey, ProverClient, SP1Proof, SP1ProofWithPublicValues, SP1Stdin,
-    SP1VerifyingKey,
-};
+#[cfg(feature = "zk")]
+use sp1_sdk::HashableKey;
 
 use certificates::Issued;
 use doprf::tagged::{HashTag, TaggedHash};
@@ -28,6 +36,8 @@ use once_cell::sync::Lazy;
 use scep::error::ScepError;
 use scep::types::{ScreenCommon, ScreenWithExemptionParams};
 use shared_types::hdb::HdbScreeningResult;
+#[cfg(feature = "zk")]
+use shared_types::metrics::HdbMetrics;
 use shared_types::requests::RequestId;
 use shared_types::synthesis_permission::SynthesisPermission;
 use streamed_ristretto::hyper::{check_content_length, from_request};
@@ -36,6 +46,8 @@ use streamed_ristretto::HasContentType;
 
 use crate::event_store;
 use crate::state::HdbServerState;
+#[cfg(feature = "zk")]
+use crate::state::ProofVerification;
 use crate::validation::exemptions_after_validation;
 
 static NO_EXEMPTIONS: Lazy<Arc<Exemptions>> = Lazy::new(Arc::default);
@@ -78,6 +90,111 @@ impl StreamableRistretto for UnparsedTaggedHash {
     }
 }
 
+/// Decodes the public values committed by the verification program, and returns the encoded
+/// tagged hashes they attest to. The hashes are only trustworthy if the aggregated proofs are of
+/// our hash and checksum programs, and the keyserver responses validated against our active
+/// security key.
+///
+/// Proofs that screened the hashes inside the program commit a verdict rather than the hashes,
+/// which leaves nothing for this server to screen; they are rejected, and only checked offline.
+#[cfg(feature = "zk")]
+fn attested_hashes(
+    public_values: &[u8],
+    expected_sub_proof_vkeys: &SubProofVkeys,
+    expected_active_security_key_hashes: &[[u8; 32]],
+    expected_hdb_commitment: Option<&HdbCommitment>,
+) -> Result<Vec<u8>, scep::error::Screen> {
+    let output = ScreeningProofOutput::decode(public_values)
+        .map_err(|e| scep::error::Screen::MalformedProofOutput(e.to_string()))?;
+    if !expected_sub_proof_vkeys.matches(&output.sub_proof_vkeys) {
+        return Err(scep::error::Screen::UnexpectedSubProofs);
+    }
+    if !expected_active_security_key_hashes.contains(&output.keyserver_commitment_hash) {
+        return Err(scep::error::Screen::UnexpectedActiveSecurityKey);
+    }
+    // All zeroes unless the program screened against an HDB build
+    if output.hdb_commitment != [0; 32]
+        && expected_hdb_commitment.map(|c| c.0) != Some(output.hdb_commitment)
+    {
+        return Err(scep::error::Screen::UnexpectedHdbCommitment(hex::encode(
+            output.hdb_commitment,
+        )));
+    }
+    match output.status {
+        ScreeningStatus::Hashed(hashes) => Ok(hashes),
+        ScreeningStatus::Screened(_) => Err(scep::error::Screen::ProofAlreadyScreened),
+        status => Err(scep::error::Screen::ProofNotAccepted(status.to_string())),
+    }
+}
+
+/// Checks that `verification` is a valid proof of our verification program, attesting to
+/// exactly the tagged hashes in `ristretto_data`.
+#[cfg(feature = "zk")]
+async fn check_screening_proof(
+    expected: &ProofVerification,
+    verification: VerificationInput,
+    ristretto_data: &[u8],
+) -> Result<(), scep::error::Screen> {
+    // Only accept proofs of the pinned verification program, not whatever key the client sends.
+    let vk_hash = verification.vk.bytes32();
+    if vk_hash != expected.verification_vkey_hash {
+        return Err(scep::error::Screen::UnexpectedVerifyingKey {
+            expected: expected.verification_vkey_hash.clone(),
+            actual: vk_hash,
+        });
+    }
+
+    // Verifying takes long enough to stall every other request on this worker, so it runs on
+    // the blocking pool. SP1 can panic on proofs that are well-formed JSON but not well-formed
+    // proofs, and any authenticated client can send us one; such a panic ends up in the
+    // `JoinError`.
+    let public_values = verification.proof.public_values.to_vec();
+    let backend = expected.backend.clone();
+    let started = Instant::now();
+    let verified = tokio::task::spawn_blocking(move || backend.verify(&verification)).await;
+    match verified {
+        Ok(Ok(())) => debug!(elapsed = ?started.elapsed(), "verified screening proof"),
+        Ok(Err(e)) => return Err(scep::error::Screen::ProofInvalid(e.to_string())),
+        Err(_) => {
+            return Err(scep::error::Screen::ProofInvalid(
+                "proof could not be checked".to_owned(),
+            ))
+        }
+    }
+
+    // The proof must attest to exactly the hashes we are about to screen.
+    let proof_hashes = attested_hashes(
+        &public_values,
+        &expected.sub_proof_vkeys,
+        &expected.active_security_key_hashes,
+        expected.hdb_commitment.as_ref(),
+    )?;
+    if proof_hashes != ristretto_data {
+        return Err(scep::error::Screen::ProofHashMismatch);
+    }
+    Ok(())
+}
+
+/// Checks a screening proof as [`check_screening_proof`] does, counting the outcome in
+/// `metrics`.
+#[cfg(feature = "zk")]
+async fn check_and_count_screening_proof(
+    expected: &ProofVerification,
+    metrics: Option<&HdbMetrics>,
+    verification: VerificationInput,
+    ristretto_data: &[u8],
+) -> Result<(), scep::error::Screen> {
+    let result = check_screening_proof(expected, verification, ristretto_data).await;
+    if let Some(metrics) = metrics {
+        match result {
+            Ok(()) => metrics.proofs_verified.inc(),
+            Err(_) => metrics.proofs_rejected.inc(),
+        }
+    }
+    result
+}
+
+#[cfg(feature = "zk")]
 pub async fn scep_endpoint_screen_and_verify(
     request_id: &RequestId,
     hdbs_state: Arc<HdbServerState>,
@@ -121,11 +238,29 @@ pub async fn scep_endpoint_screen_and_verify(
     let request_data: RequestWithVerification = serde_json::from_slice(&bytes)
         .map_err(|e| scep::error::ScepError::InvalidMessage(e.into()))?;
 
-    // Verify the proof
-    let client = ProverClient::new();
-    let VerificationInput { proof, vk } = request_data.verification;
-    client.verify(&proof, &vk).expect("verification failed");
-    println!("HDB verification successful");
+    let verification = request_data.verification;
+    let vk_hash = verification.vk.bytes32();
+    if let Err(e) = check_and_count_screening_proof(
+        &hdbs_state.proof_verification,
+        hdbs_state.metrics.as_deref(),
+        verification,
+        &request_data.ristretto_data,
+    )
+    .await
+    {
+        warn!("{request_id}: rejected screening proof from {client_mid}: {e}");
+        if let Err(db_err) = event_store::insert_rejected_proof(
+            &hdbs_state.persistence_connection,
+            client_mid,
+            vk_hash,
+            e.to_string(),
+        )
+        .await
+        {
+            error!("Failed to persist rejected proof for {client_mid}: {db_err}");
+        }
+        return Err(e.into());
+    }
 
     // Build a fake request to mimic the form expected in scep_endpoint_screen, data is moved
     let fake_request = Request::builder()
@@ -288,7 +423,9 @@ pub async fn scep_endpoint_screen_and_verify(
             .context("in screen consolidation")
             .map_err(ScepError::InternalError)?;
 
-    let response: HdbScreeningResult = consolidation.to_hdb_screening_result(provider_reference);
+    let hdb_commitment = hdbs_state.hdb_commitment.map(|c| c.to_string());
+    let response: HdbScreeningResult =
+        consolidation.to_hdb_screening_result(provider_reference, hdb_commitment);
 
     let merged_permission =
         SynthesisPermission::merge(response.results.iter().map(|r| r.synthesis_permission));
@@ -502,7 +639,9 @@ pub async fn scep_endpoint_screen(
             .context("in screen consolidation")
             .map_err(ScepError::InternalError)?;
 
-    let response: HdbScreeningResult = consolidation.to_hdb_screening_result(provider_reference);
+    let hdb_commitment = hdbs_state.hdb_commitment.map(|c| c.to_string());
+    let response: HdbScreeningResult =
+        consolidation.to_hdb_screening_result(provider_reference, hdb_commitment);
 
     let merged_permission =
         SynthesisPermission::merge(response.results.iter().map(|r| r.synthesis_permission));
@@ -683,3 +822,223 @@ pub async fn scep_endpoint_exemption_seq_hashes(
     // Give them the OK to hit /exemption-screen-hashes next.
     Ok(response::json(StatusCode::OK, "{}"))
 }
+
+#[cfg(all(test, feature = "zk"))]
+mod tests {
+    use super::*;
+
+    use doprf::prf::CompletedHashValue;
+    use doprf::proof_backend::{MockBackend, ProofBackend, HASH_ELF};
+    use doprf::proof_output::HdbHits;
+    use sp1_sdk::{SP1PublicValues, SP1Stdin};
+
+    const SUB_PROOF_VKEYS: SubProofVkeys = SubProofVkeys {
+        hash: [1; 8],
+        checksum: [2; 8],
+    };
+    const CHUNKED_VKEYS: [[u32; 8]; 3] = [[1; 8], [1; 8], [2; 8]];
+    const ACTIVE_SECURITY_KEY_HASH: [u8; 32] = [4; 32];
+
+    fn public_values(sub_proof_vkeys: &[[u32; 8]], status: ScreeningStatus) -> Vec<u8> {
+        ScreeningProofOutput {
+            sub_proof_vkeys: sub_proof_vkeys.to_vec(),
+            order_commitment: [0; 32],
+            hdb_commitment: [0; 32],
+            keyserver_commitment_hash: ACTIVE_SECURITY_KEY_HASH,
+            window_count: 0,
+            status,
+        }
+        .encode()
+    }
+
+    fn tagged_hashes() -> Vec<TaggedHash> {
+        (0..3)
+            .map(|i| TaggedHash {
+                tag: HashTag::new(i == 0, 0, i),
+                hash: CompletedHashValue::hash_from_bytes_for_tests_only(&[i as u8]),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn attested_hashes_are_the_encoded_tagged_hashes() {
+        let hashes = tagged_hashes();
+        let status = ScreeningStatus::from_hashes(hashes.clone());
+
+        let attested = attested_hashes(
+            &public_values(&CHUNKED_VKEYS, status),
+            &SUB_PROOF_VKEYS,
+            &[ACTIVE_SECURITY_KEY_HASH],
+            None,
+        )
+        .unwrap();
+        let expected: Vec<u8> = hashes
+            .into_iter()
+            .flat_map(<[u8; TaggedHash::SIZE]>::from)
+            .collect();
+        assert_eq!(attested, expected);
+    }
+
+    #[test]
+    fn rejects_unexpected_sub_proofs() {
+        let public_values = public_values(&[[3; 8]], ScreeningStatus::Hashed(vec![]));
+        assert!(matches!(
+            attested_hashes(&public_values, &SUB_PROOF_VKEYS, &[ACTIVE_SECURITY_KEY_HASH], None),
+            Err(scep::error::Screen::UnexpectedSubProofs)
+        ));
+    }
+
+    #[test]
+    fn rejects_other_active_security_keys() {
+        let public_values = public_values(&CHUNKED_VKEYS, ScreeningStatus::Hashed(vec![]));
+        assert!(matches!(
+            attested_hashes(&public_values, &SUB_PROOF_VKEYS, &[[5; 32]], None),
+            Err(scep::error::Screen::UnexpectedActiveSecurityKey)
+        ));
+    }
+
+    #[test]
+    fn accepts_previous_active_security_key() {
+        let public_values = public_values(&CHUNKED_VKEYS, ScreeningStatus::Hashed(vec![]));
+        let expected = [[5; 32], ACTIVE_SECURITY_KEY_HASH];
+        assert!(attested_hashes(&public_values, &SUB_PROOF_VKEYS, &expected, None).is_ok());
+    }
+
+    #[test]
+    fn rejects_unhashed_status() {
+        let public_values =
+            public_values(&CHUNKED_VKEYS, ScreeningStatus::MissingKeyserverResponse);
+        assert!(matches!(
+            attested_hashes(&public_values, &SUB_PROOF_VKEYS, &[ACTIVE_SECURITY_KEY_HASH], None),
+            Err(scep::error::Screen::ProofNotAccepted(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_in_circuit_verdicts() {
+        let mut output = ScreeningProofOutput::decode(&public_values(
+            &CHUNKED_VKEYS,
+            ScreeningStatus::Screened(HdbHits::default()),
+        ))
+        .unwrap();
+        output.hdb_commitment = [6; 32];
+        let public_values = output.encode();
+
+        // Even against the HDB build this server has loaded, there are no hashes to screen
+        assert!(matches!(
+            attested_hashes(
+                &public_values,
+                &SUB_PROOF_VKEYS,
+                &[ACTIVE_SECURITY_KEY_HASH],
+                Some(&HdbCommitment([6; 32])),
+            ),
+            Err(scep::error::Screen::ProofAlreadyScreened)
+        ));
+        for loaded in [None, Some(HdbCommitment([7; 32]))] {
+            assert!(matches!(
+                attested_hashes(
+                    &public_values,
+                    &SUB_PROOF_VKEYS,
+                    &[ACTIVE_SECURITY_KEY_HASH],
+                    loaded.as_ref(),
+                ),
+                Err(scep::error::Screen::UnexpectedHdbCommitment(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_public_values() {
+        let public_values = public_values(&CHUNKED_VKEYS, ScreeningStatus::Hashed(vec![0; 36]));
+        assert!(matches!(
+            attested_hashes(
+                &public_values[..public_values.len() - 1],
+                &SUB_PROOF_VKEYS,
+                &[ACTIVE_SECURITY_KEY_HASH],
+                None,
+            ),
+            Err(scep::error::Screen::MalformedProofOutput(_))
+        ));
+    }
+
+    /// A mock proof whose public values attest to `hashes`, and what it is checked against.
+    fn mock_screening_proof(hashes: Vec<TaggedHash>) -> (ProofVerification, VerificationInput) {
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&[3u8; 32]);
+        stdin.write(&1u32);
+        stdin.write(&b"ACGT".to_vec());
+        stdin.write(&[7u8; 32]);
+        stdin.write(&[11u8; 32]);
+        let backend = MockBackend::new();
+        let (_, input) = backend.run(HASH_ELF, stdin).unwrap();
+        let mut input = input.unwrap();
+
+        // Mock proofs don't bind their public values, so they can be made to attest to anything
+        let public_values = public_values(&CHUNKED_VKEYS, ScreeningStatus::from_hashes(hashes));
+        input.proof.public_values = SP1PublicValues::from(&public_values);
+
+        let expected = ProofVerification {
+            backend: Arc::new(backend),
+            verification_vkey_hash: input.vk.bytes32(),
+            sub_proof_vkeys: SUB_PROOF_VKEYS,
+            active_security_key_hashes: vec![ACTIVE_SECURITY_KEY_HASH],
+            hdb_commitment: None,
+        };
+        (expected, input)
+    }
+
+    #[tokio::test]
+    async fn screening_proofs_are_counted() {
+        let hashes = tagged_hashes();
+        let encoded: Vec<u8> = hashes
+            .iter()
+            .cloned()
+            .flat_map(<[u8; TaggedHash::SIZE]>::from)
+            .collect();
+        let mismatched: Vec<u8> = hashes
+            .iter()
+            .rev()
+            .cloned()
+            .flat_map(<[u8; TaggedHash::SIZE]>::from)
+            .collect();
+        let metrics = HdbMetrics::new();
+
+        let (expected, input) = mock_screening_proof(hashes);
+        check_and_count_screening_proof(&expected, Some(&metrics), input.clone(), &encoded)
+            .await
+            .unwrap();
+        assert_eq!(metrics.proofs_verified.get(), 1);
+        assert_eq!(metrics.proofs_rejected.get(), 0);
+
+        // The same proof does not vouch for the hashes in any other order
+        let result =
+            check_and_count_screening_proof(&expected, Some(&metrics), input, &mismatched).await;
+        assert!(
+            matches!(result, Err(scep::error::Screen::ProofHashMismatch)),
+            "got {result:?}"
+        );
+        assert_eq!(metrics.proofs_verified.get(), 1);
+        assert_eq!(metrics.proofs_rejected.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_proofs_of_other_programs() {
+        let hashes = tagged_hashes();
+        let encoded: Vec<u8> = hashes
+            .iter()
+            .cloned()
+            .flat_map(<[u8; TaggedHash::SIZE]>::from)
+            .collect();
+        let (mut expected, input) = mock_screening_proof(hashes);
+        expected.verification_vkey_hash = format!("0x{}", "00".repeat(32));
+
+        let result = check_screening_proof(&expected, input, &encoded).await;
+        assert!(
+            matches!(
+                result,
+                Err(scep::error::Screen::UnexpectedVerifyingKey { .. })
+            ),
+            "got {result:?}"
+        );
+    }
+}
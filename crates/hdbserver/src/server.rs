@@ -15,7 +15,18 @@ use tokio::sync::Semaphore;
 use tracing::{error, info, warn};
 
 use certificates::{DatabaseTokenGroup, Exemption, Issued, Manufacturer};
+#[cfg(feature = "zk")]
+use doprf::active_security::ActiveSecurityKey;
+#[cfg(feature = "zk")]
+use doprf::proof_backend::{
+    MockBackend, ProofBackend, Sp1Backend, CHECKSUM_ELF, HASH_ELF, VERIFICATION_ELF,
+};
+#[cfg(feature = "zk")]
+use doprf::proof_output::SubProofVkeys;
+#[cfg(feature = "zk")]
+use doprf::public_values::vkey_digest_from_bytes;
 use hdb::{Database, HazardLookupTable};
+use hdb_acc::{AccumulatorFile, HdbCommitment};
 use minhttp::error::ErrWrapper;
 use minhttp::mpserver::traits::ValidServerSetup;
 use minhttp::mpserver::{MultiplaneServer, ServerConfig};
@@ -27,9 +38,13 @@ use shared_types::http::add_cors_headers;
 use shared_types::metrics::{get_metrics_output, HdbMetrics};
 use shared_types::requests::RequestId;
 use shared_types::server_versions::HdbVersion;
+#[cfg(feature = "zk")]
+use sp1_sdk::HashableKey;
 
 use crate::event_store;
 use crate::opts::Config;
+#[cfg(feature = "zk")]
+use crate::state::ProofVerification;
 use crate::state::{BuildTimestamp, HdbServerState};
 use crate::validation::NetworkingValidator;
 
@@ -57,6 +72,17 @@ async fn reconfigure(
     }
     let build_timestamp = build_info.ok().map(|bi| BuildTimestamp(bi.build_timestamp));
 
+    let hdb_commitment = match get_hdb_commitment(&app_cfg.database) {
+        Ok(commitment) => {
+            info!("HDB commitment: {commitment}");
+            Some(commitment)
+        }
+        Err(err) => {
+            warn!("{err:?}");
+            None
+        }
+    };
+
     info!("Starting HDB server");
     let path = &app_cfg.database;
     let database =
@@ -146,8 +172,12 @@ async fn reconfigure(
             .context("opening event_store db")?
     };
 
+    #[cfg(feature = "zk")]
+    let proof_verification = load_proof_verification(&app_cfg, hdb_commitment).await?;
+
     Ok(Arc::new(HdbServerState {
         build_timestamp,
+        hdb_commitment,
         database,
         heavy_requests,
         hlt,
@@ -169,9 +199,110 @@ async fn reconfigure(
         exemptions_roots,
         persistence_path: app_cfg.event_store_path,
         persistence_connection,
+        supported_generations: app_cfg.supported_generations,
+        #[cfg(feature = "zk")]
+        proof_verification,
     }))
 }
 
+/// Sets up verification of screening proofs: the backend to verify them with, the hash of the
+/// only verifying key they may be proven under, the vkeys of the programs they aggregate, and
+/// the active security keys the keyserver responses must have been validated against.
+#[cfg(feature = "zk")]
+async fn load_proof_verification(
+    app_cfg: &Config,
+    hdb_commitment: Option<HdbCommitment>,
+) -> anyhow::Result<ProofVerification> {
+    anyhow::ensure!(
+        !app_cfg.active_security_key.is_empty(),
+        "active_security_key is required to verify screening proofs"
+    );
+    let mut active_security_key_hashes = vec![];
+    for key in [&app_cfg.active_security_key, &app_cfg.previous_active_security_key] {
+        if key.is_empty() {
+            continue;
+        }
+        let hash = ActiveSecurityKey::from_commitments(key.iter().copied()).commitment_hash();
+        info!(
+            "Accepting screening proofs for active security key {}",
+            hex::encode(hash)
+        );
+        active_security_key_hashes.push(hash);
+    }
+
+    let backend: Arc<dyn ProofBackend> = if app_cfg.accept_mock_proofs {
+        warn!(
+            "Accepting mock screening proofs. These attest to nothing; never do this in production!"
+        );
+        Arc::new(MockBackend::new())
+    } else {
+        Arc::new(Sp1Backend::new())
+    };
+
+    // A pinned verification program must come with the sub-proof digests it was built to
+    // aggregate; the bundled hash and checksum programs need not be those.
+    let (verification_vkey_hash, sub_proof_vkeys) = match (
+        &app_cfg.verification_vkey_hash,
+        &app_cfg.hash_vkey_digest,
+        &app_cfg.checksum_vkey_digest,
+    ) {
+        (Some(vkey_hash), Some(hash), Some(checksum)) => {
+            let verification_vkey_hash =
+                normalize_vkey_hash(vkey_hash).context("invalid verification_vkey_hash")?;
+            let sub_proof_vkeys = SubProofVkeys {
+                hash: parse_vkey_digest(hash).context("invalid hash_vkey_digest")?,
+                checksum: parse_vkey_digest(checksum).context("invalid checksum_vkey_digest")?,
+            };
+            (verification_vkey_hash, sub_proof_vkeys)
+        }
+        (None, None, None) => {
+            let backend = backend.clone();
+            tokio::task::spawn_blocking(move || {
+                let verification_vkey_hash = backend.verifying_key(VERIFICATION_ELF).bytes32();
+                let sub_proof_vkeys = SubProofVkeys {
+                    hash: backend.verifying_key(HASH_ELF).hash_u32(),
+                    checksum: backend.verifying_key(CHECKSUM_ELF).hash_u32(),
+                };
+                (verification_vkey_hash, sub_proof_vkeys)
+            })
+            .await
+            .context("deriving bundled program verifying keys")?
+        }
+        _ => anyhow::bail!(
+            "verification_vkey_hash, hash_vkey_digest and checksum_vkey_digest must be set together"
+        ),
+    };
+    info!("Accepting screening proofs for verifying key {verification_vkey_hash}");
+
+    Ok(ProofVerification {
+        backend,
+        verification_vkey_hash,
+        sub_proof_vkeys,
+        active_security_key_hashes,
+        hdb_commitment,
+    })
+}
+
+/// Accepts a bytes32 hash with or without the `0x` prefix, in either case, and returns it in
+/// the form produced by `HashableKey::bytes32`.
+#[cfg(feature = "zk")]
+fn normalize_vkey_hash(hash: &str) -> anyhow::Result<String> {
+    let hex_digits = hash.trim().trim_start_matches("0x").to_ascii_lowercase();
+    let bytes = hex::decode(&hex_digits).context("not a hex string")?;
+    anyhow::ensure!(bytes.len() == 32, "expected 32 bytes, found {}", bytes.len());
+    Ok(format!("0x{hex_digits}"))
+}
+
+/// Parses a hex-encoded bytes32 vkey digest, as passed to `SecureDNAVerifier`.
+#[cfg(feature = "zk")]
+fn parse_vkey_digest(digest: &str) -> anyhow::Result<[u32; 8]> {
+    let bytes = hex::decode(digest.trim().trim_start_matches("0x")).context("not a hex string")?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|b: Vec<u8>| anyhow::anyhow!("expected 32 bytes, found {}", b.len()))?;
+    Ok(vkey_digest_from_bytes(&bytes))
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct BuildInfo {
@@ -187,6 +318,15 @@ fn get_hdb_build_info(database: &Path) -> anyhow::Result<BuildInfo> {
     serde_json::from_reader(f).context("Could not parse BUILD_INFO.json file.")
 }
 
+fn get_hdb_commitment(database: &Path) -> anyhow::Result<HdbCommitment> {
+    let accumulator = AccumulatorFile::read(AccumulatorFile::path(database))
+        .context("Could not read HDB accumulator; run `genacc build`")?;
+    if !accumulator.is_current(database)? {
+        anyhow::bail!("HDB accumulator is out of date with BUILD_INFO.json; run `genacc update`");
+    }
+    Ok(accumulator.accumulator.commitment())
+}
+
 async fn respond(
     hdbs_state: Arc<HdbServerState>,
     peer: SocketAddr,
@@ -248,6 +388,7 @@ async fn respond(
             )
             .await
         }
+        #[cfg(feature = "zk")]
         scep::SCREEN_AND_VERIFY_ENDPOINT => {
             handle_post(
                 &method,
@@ -322,9 +463,11 @@ async fn respond(
 async fn version(hdbs_state: &HdbServerState) -> GenericResponse {
     let server_version = get_version();
     let hdb_timestamp = hdbs_state.build_timestamp.clone().map(|t| t.0);
+    let hdb_commitment = hdbs_state.hdb_commitment.map(|c| c.to_string());
     let response = HdbVersion {
         server_version,
         hdb_timestamp,
+        hdb_commitment,
     };
     // this serialization can't fail
     let json = serde_json::to_string(&response).unwrap();
@@ -484,17 +627,24 @@ async fn scep_endpoint_authenticate(
 mod test {
     use super::*;
 
+    use std::path::PathBuf;
+
     use minhttp::mpserver::common::{read_no_disk, stub_cfg};
     use minhttp::mpserver::{ExternalWorld, PlaneConfig};
-    use minhttp::test::FakeNetwork;
+    use minhttp::test::{send_request, FakeNetwork};
+    use shared_types::server_selection::HdbQualificationResponse;
 
-    #[tokio::test]
-    async fn test_empty_hdb_returns_error() {
-        let hdb_dir = tempfile::tempdir().unwrap();
-        let network = Arc::new(FakeNetwork::default());
+    const SERVER_ADDR: &str = "192.0.2.2:80";
+
+    fn test_cert(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../test/certs")
+            .join(name)
+    }
 
+    fn server_config(database: &Path) -> ServerConfig<Config> {
         let app_cfg = Config {
-            database: hdb_dir.path().to_owned(),
+            database: database.to_owned(),
             max_heavy_clients: Config::default_max_heavy_clients(),
             disk_parallelism_per_server: Config::default_disk_parallelism_per_server(),
             disk_parallelism_per_request: Config::default_disk_parallelism_per_request(),
@@ -503,36 +653,128 @@ mod test {
             yubico_api_secret_key: None,
             scep_json_size_limit: Config::default_scep_json_size_limit(),
             et_size_limit: Config::default_et_size_limit(),
-            exemption_roots: "test/certs/exemption-roots".into(),
-            manufacturer_roots: "test/certs/manufacturer-roots".into(),
+            exemption_roots: test_cert("exemption-roots"),
+            manufacturer_roots: test_cert("manufacturer-roots"),
             revocation_list: None,
-            token_file: "test/certs/database-token.dt".into(),
-            keypair_file: "test/certs/database-token.priv".into(),
-            keypair_passphrase_file: "test/certs/database-token.passphrase".into(),
+            token_file: test_cert("database-token.dt"),
+            keypair_file: test_cert("database-token.priv"),
+            keypair_passphrase_file: test_cert("database-token.passphrase"),
             allow_insecure_cookie: true,
             event_store_path: Config::default_event_store_path(),
+            supported_generations: Config::default_supported_generations(),
+            #[cfg(feature = "zk")]
+            verification_vkey_hash: Some(format!("0x{}", "00".repeat(32))),
+            #[cfg(feature = "zk")]
+            hash_vkey_digest: Some(format!("0x{}", "00".repeat(32))),
+            #[cfg(feature = "zk")]
+            checksum_vkey_digest: Some(format!("0x{}", "00".repeat(32))),
+            #[cfg(feature = "zk")]
+            active_security_key: vec![Default::default()],
+            #[cfg(feature = "zk")]
+            previous_active_security_key: vec![],
+            #[cfg(feature = "zk")]
+            accept_mock_proofs: true,
         };
-        let server_config = ServerConfig {
+        ServerConfig {
             main: PlaneConfig {
-                address: Some("192.0.2.2:80".parse().unwrap()),
+                address: Some(SERVER_ADDR.parse().unwrap()),
                 tls_config: None,
                 max_connections: PlaneConfig::DEFAULT_MAX_CONNECTIONS,
                 custom: app_cfg,
             },
             monitoring: PlaneConfig::default(),
             control: PlaneConfig::default(),
-        };
+        }
+    }
+
+    fn test_server(database: &Path, network: &Arc<FakeNetwork>) -> MultiplaneServer {
+        let server_config = server_config(database);
         let external_world = ExternalWorld {
             listen: network.listen_fn(),
             load_cfg: stub_cfg(move || server_config.clone()),
             read_file: read_no_disk,
         };
-        let server = server_setup()
+        server_setup()
             .to_server_setup()
-            .build_with_external_world(external_world);
+            .build_with_external_world(external_world)
+    }
+
+    /// Writes a single-entry HDB, with an empty HLT, to `database`.
+    fn write_hdb(database: &Path) {
+        let mut entry = [0u8; 40];
+        entry[1] = 0x42;
+        fs::write(database.join("00"), entry).unwrap();
+        HazardLookupTable::default().write(database).unwrap();
+        write_build_info(database, "2024-01-01T00:00:00Z");
+    }
+
+    fn write_build_info(database: &Path, build_timestamp: &str) {
+        let build_info = serde_json::json!({
+            "build_timestamp": build_timestamp,
+            "pipeline_git_sha": "",
+            "pipeline_git_timestamp": "",
+            "hdb_git_sha": "",
+        });
+        fs::write(database.join("BUILD_INFO.json"), build_info.to_string()).unwrap();
+    }
+
+    /// Sends `request` to the test server, and parses the JSON body of its response.
+    async fn get_json<T: serde::de::DeserializeOwned>(network: &FakeNetwork, request: &str) -> T {
+        let conn = network.connect(SERVER_ADDR.parse().unwrap()).await.unwrap();
+        let http_res = send_request(conn, request).await.unwrap();
+        assert!(http_res.starts_with("HTTP/1.1 200 OK\r\n"), "{http_res}");
+        let (_, body) = http_res.split_once("\r\n\r\n").unwrap();
+        serde_json::from_str(body).unwrap()
+    }
+
+    /// The HDB commitments served by /version and /qualification.
+    async fn served_commitments(network: &FakeNetwork) -> (Option<String>, Option<String>) {
+        let version: HdbVersion = get_json(network, "GET /version HTTP/1.1\r\n\r\n").await;
+        let body = r#"{"client_version":0}"#;
+        let request = format!(
+            "POST /qualification HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let qualification: HdbQualificationResponse = get_json(network, &request).await;
+        (version.hdb_commitment, qualification.hdb_commitment)
+    }
+
+    #[tokio::test]
+    async fn test_empty_hdb_returns_error() {
+        let hdb_dir = tempfile::tempdir().unwrap();
+        let network = Arc::new(FakeNetwork::default());
+        let server = test_server(hdb_dir.path(), &network);
 
         // Checking that the HDB/etc is valid happens during a reconfiguration...
         // This should fail because the HDB is empty.
         assert!(server.reload_cfg().await.is_err());
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_serves_commitment_of_loaded_accumulator() {
+        let hdb_dir = tempfile::tempdir().unwrap();
+        write_hdb(hdb_dir.path());
+        let accumulator = AccumulatorFile::build(hdb_dir.path()).unwrap();
+        accumulator
+            .write(AccumulatorFile::path(hdb_dir.path()))
+            .unwrap();
+        let expected = accumulator.accumulator.commitment().to_string();
+
+        let network = Arc::new(FakeNetwork::default());
+        let server = test_server(hdb_dir.path(), &network);
+        server.reload_cfg().await.unwrap();
+
+        tokio::join!(server.serve(), async {
+            let (version, qualification) = served_commitments(&network).await;
+            assert_eq!(version.as_deref(), Some(expected.as_str()));
+            assert_eq!(qualification.as_deref(), Some(expected.as_str()));
+
+            // A new HDB build leaves the accumulator out of date, so nothing is committed to
+            write_build_info(hdb_dir.path(), "2024-02-01T00:00:00Z");
+            server.reload_cfg().await.unwrap();
+            assert_eq!(served_commitments(&network).await, (None, None));
+
+            server.graceful_shutdown().await;
+        });
+    }
 }
@@ -8,7 +8,12 @@ use hyper::StatusCode;
 use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 use certificates::{DatabaseTokenGroup, PublicKey};
+#[cfg(feature = "zk")]
+use doprf::proof_backend::ProofBackend;
+#[cfg(feature = "zk")]
+use doprf::proof_output::SubProofVkeys;
 use hdb::{Database, HazardLookupTable};
+use hdb_acc::HdbCommitment;
 use minhttp::response::{self, GenericResponse};
 use scep_server_helpers::server::ServerState;
 use shared_types::hash::HashSpec;
@@ -22,6 +27,8 @@ pub struct BuildTimestamp(pub String);
 
 pub struct HdbServerState {
     pub build_timestamp: Option<BuildTimestamp>,
+    /// The commitment to the database from its accumulator file, if it has an up-to-date one.
+    pub hdb_commitment: Option<HdbCommitment>,
     pub database: Database,
     pub heavy_requests: Arc<Semaphore>,
     pub hlt: HazardLookupTable,
@@ -36,6 +43,30 @@ pub struct HdbServerState {
     pub exemptions_roots: Vec<PublicKey>,
     pub persistence_path: PathBuf,
     pub persistence_connection: Connection,
+    /// The keyserver generations served to clients qualifying this server.
+    pub supported_generations: Vec<u32>,
+    #[cfg(feature = "zk")]
+    pub proof_verification: ProofVerification,
+}
+
+/// What screening proofs are checked against.
+#[cfg(feature = "zk")]
+pub struct ProofVerification {
+    /// Verifies screening proofs.
+    pub backend: Arc<dyn ProofBackend>,
+    /// The bytes32 hash of the only verifying key accepted for screening proofs.
+    pub verification_vkey_hash: String,
+    /// The vkey digests of the hash and checksum programs, whose proofs the verification
+    /// program aggregates.
+    pub sub_proof_vkeys: SubProofVkeys,
+    /// The commitment hashes of this deployment's active security key, and of the previous one
+    /// while keyservers move to a new generation. Proofs validating the keyserver responses
+    /// against any other key are rejected, since a client could otherwise validate them against
+    /// commitments to keys of its own.
+    pub active_security_key_hashes: Vec<[u8; 32]>,
+    /// The commitment of the loaded HDB accumulator. Proofs that screened against any other HDB
+    /// build are rejected.
+    pub hdb_commitment: Option<HdbCommitment>,
 }
 
 impl HdbServerState {
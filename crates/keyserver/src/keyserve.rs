@@ -1,12 +1,12 @@
 // Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use anyhow::Context;
 use bytes::{Buf, Bytes, BytesMut};
 
-use futures::stream::iter as to_stream;
+use futures::stream::{iter as to_stream, once};
 use futures::{StreamExt, TryStream, TryStreamExt};
 use http_body_util::{BodyExt, StreamBody};
 use hyper::body::{Body, Frame, Incoming};
@@ -14,6 +14,7 @@ use hyper::header::{HeaderValue, CONTENT_TYPE};
 use hyper::{Request, Response};
 use tracing::{error, info};
 
+use doprf::dleq::DleqProof;
 use doprf::prf::{HashPart, Query};
 use minhttp::response::GenericResponse;
 use shared_types::requests::RequestId;
@@ -27,14 +28,43 @@ use streamed_ristretto::HasContentType;
 use crate::event_store;
 use crate::state::KeyserverState;
 
+// The queries of a request and the `HashPart`s answering them, in order, kept until the
+// response is complete so it can be proven.
+#[derive(Default)]
+struct Transcript {
+    queries: Vec<Bytes>,
+    hash_parts: Vec<Bytes>,
+}
+
+impl Transcript {
+    // Proves the response with `prove`, or returns nothing if it was cut short.
+    fn prove(self, prove: impl FnOnce(&[Query], &[HashPart]) -> DleqProof) -> Option<DleqProof> {
+        let queries: Vec<Query> = decode_all(&self.queries)?;
+        let hash_parts: Vec<HashPart> = decode_all(&self.hash_parts)?;
+        (queries.len() == hash_parts.len()).then(|| prove(&queries, &hash_parts))
+    }
+}
+
+fn decode_all<T>(bufs: &[Bytes]) -> Option<Vec<T>>
+where
+    T: for<'a> TryFrom<&'a [u8; HASH_SIZE]>,
+{
+    bufs.iter()
+        .flat_map(|buf| buf.chunks_exact(HASH_SIZE))
+        .map(|item| T::try_from(item.try_into().unwrap()).ok())
+        .collect()
+}
+
 // Given a stream of `Bytes`/errors, interprets them as `Queries` and applies `f` to them
 //
-// Encodes the resulting `HashPart`s back into Bytes and returns a stream of said `Bytes`/errors.
+// Encodes the resulting `HashPart`s back into Bytes and returns a stream of said `Bytes`/errors,
+// ending with the `DleqProof` made by `prove`, if any, once every `HashPart` has been sent.
 fn map_ristretto_stream<I, P>(
     ks_state: &Arc<KeyserverState>,
     heavy_request_permit: P,
     input: I,
     f: impl FnMut(Query) -> HashPart + Clone + Send + 'static,
+    prove: Option<impl FnOnce(&[Query], &[HashPart]) -> DleqProof + Send + 'static>,
 ) -> impl TryStream<Ok = Bytes, Error = RistrettoError<I::Error, ConversionError<Query>>>
 where
     I: TryStream,
@@ -43,6 +73,26 @@ where
 {
     let mut output_bufs = BytesMut::new();
     let ks_state2 = ks_state.clone();
+    let transcript = Arc::new(Mutex::new(Transcript::default()));
+    let query_transcript = transcript.clone();
+    let hash_part_transcript = transcript.clone();
+    let proof = once(async move {
+        let Some(prove) = prove else {
+            return Ok(Bytes::new());
+        };
+        let transcript = std::mem::take(&mut *transcript.lock().unwrap());
+        let proof = match tokio::task::spawn_blocking(move || transcript.prove(prove)).await {
+            Ok(proof) => proof,
+            Err(e) => {
+                error!("Failed to prove keyserve response: {e}");
+                return Err(RistrettoError::Internal);
+            }
+        };
+        // An incomplete response has already ended in an error, so there is nothing to prove
+        Ok(proof.map_or_else(Bytes::new, |proof| {
+            Bytes::copy_from_slice(&proof.to_bytes())
+        }))
+    });
     chunked(input, HASH_SIZE)
         .map(move |chunk| {
             // IMPORTANT: The request continues to be processed AFTER the outer function returns
@@ -53,6 +103,7 @@ where
             if chunk.len() % HASH_SIZE != 0 {
                 return Err(RistrettoError::Incomplete { data: chunk });
             }
+            query_transcript.lock().unwrap().queries.push(chunk.clone());
 
             // We split off enough memory to hold a mapped chunk
             // so that map_ristretto_chunk doesn't typically need to allocate anything.
@@ -75,11 +126,22 @@ where
                     Ok(map_ristretto_chunk(chunk, out_buf, f))
                 })
                 .await
-                .unwrap()
+                .unwrap_or_else(|e| {
+                    error!("Failed to process keyserve chunk: {e}");
+                    Err(RistrettoError::Internal)
+                })
             })
         })
         .try_buffered(ks_state.parallelism_per_request)
         .try_flatten()
+        .inspect_ok(move |buf| {
+            hash_part_transcript
+                .lock()
+                .unwrap()
+                .hash_parts
+                .push(buf.clone())
+        })
+        .chain(proof)
         .flat_map(|chunk| match chunk {
             Ok(buf) => {
                 let items = [Ok(buf)];
@@ -155,6 +217,8 @@ pub async fn scep_endpoint_keyserve(
         })?;
     let client_mid = client_state.client_mid();
     let nucleotide_total_count = client_state.open_request().nucleotide_total_count;
+    // Clients that don't ask for a proof would take it for one more hash part
+    let dleq_proof = client_state.open_request().dleq_proof;
 
     let hash_count_from_content_len =
         check_content_length(request.body().size_hint().exact(), HASH_SIZE)
@@ -185,12 +249,16 @@ pub async fn scep_endpoint_keyserve(
         }
         keyshare.apply_query_and_lagrange_coefficient(query, &lagrange_coeff)
     };
+    let prove = dleq_proof.then_some(move |queries: &[Query], hash_parts: &[HashPart]| {
+        keyshare.prove_responses(&lagrange_coeff, queries, hash_parts)
+    });
 
     let chunks = map_ristretto_stream(
         server_state,
         permit,
         BodyStream(request.into_body()),
         encrypt_query,
+        prove,
     );
 
     let body = StreamBody::new(chunks.map_ok(Frame::data));
@@ -55,6 +55,15 @@ pub struct Config {
     )]
     pub active_security_key: Vec<Commitment>,
 
+    #[clap(
+        long,
+        env = "SECUREDNA_KEYSERVER_GENERATION",
+        help = "The generation of the keyshare and active security key. Refreshed keyshares must be loaded as a new generation, since clients only combine keyservers of the same generation.",
+        default_value_t = 0
+    )]
+    #[serde(default)]
+    pub generation: u32,
+
     #[clap(
         long,
         help = "Maximum simultaneous hashing/encryption requests before 503 unavailable is returned",
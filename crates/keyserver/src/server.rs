@@ -74,6 +74,42 @@ async fn reconfigure(
         ).into());
     }
     let active_security_key = ActiveSecurityKey::from_commitments(app_cfg.active_security_key);
+    let keyshare_commitment = active_security_key.keyserver_commitment(&app_cfg.id);
+    if app_cfg.keyshare.multiply_by_base() != keyshare_commitment {
+        return Err(anyhow::anyhow!(
+            "The keyshare does not match the commitment to keyserver {}'s keyshare in the active security key",
+            app_cfg.id
+        )
+        .into());
+    }
+    // Reloading swaps in a refreshed or reshared keyshare and active security key, but the
+    // secret key must stay the same, or the HDB would no longer match. Keyservers are reloaded
+    // one at a time, and old and new keyshares do not work together, so the new keyshare is
+    // served as a new generation: clients only ever combine keyservers of one generation, and
+    // move to the new one once a quorum of keyservers serves it.
+    let prev_generation = prev_state
+        .as_ref()
+        .and_then(|s| s.generations_key_info.0.iter().next());
+    if let Some((&prev_generation, prev_key_info)) = prev_generation {
+        if !prev_key_info.active_security_key.has_same_secret(&active_security_key) {
+            return Err(anyhow::anyhow!(
+                "The active security key commits to a different secret key; restart the keyserver to change keys"
+            )
+            .into());
+        }
+        if prev_key_info.active_security_key != active_security_key {
+            if app_cfg.generation <= prev_generation {
+                return Err(anyhow::anyhow!(
+                    "A new keyshare and active security key must be loaded as a new generation, above the current generation {prev_generation}"
+                )
+                .into());
+            }
+            info!(
+                "Swapping in a new keyshare and active security key for the same secret key as generation {}",
+                app_cfg.generation
+            );
+        }
+    }
 
     let manufacturer_roots =
         scep_server_helpers::certs::read_certificates::<Manufacturer>(app_cfg.manufacturer_roots)
@@ -122,7 +158,7 @@ async fn reconfigure(
     let generations_key_info = {
         let mut h = HashMap::new();
         h.insert(
-            0,
+            app_cfg.generation,
             KeyInfo {
                 quorum: app_cfg.keyholders_required,
                 active_security_key,
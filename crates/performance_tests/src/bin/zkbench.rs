@@ -0,0 +1,81 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+
+use clap::Parser;
+use doprf::proof_inputs::DEFAULT_CHUNK_SIZE;
+
+use performance_tests::zkbench::{HashSpecPreset, Scenario, ZkBench, DEFAULT_CYCLES_PER_SECOND};
+
+/// Measures the screening programs over random orders of every combination of the given
+/// lengths, hash specs and keyholder quorums, writing one JSON object per program run.
+#[derive(Debug, Parser)]
+#[clap(name = "zkbench")]
+struct Opts {
+    /// Order lengths, in bp
+    #[clap(long, value_delimiter = ',', default_values_t = [100, 1000, 10000])]
+    bp: Vec<usize>,
+
+    /// Which windows to hash
+    #[clap(long, value_enum, value_delimiter = ',', default_values_t = [HashSpecPreset::DnaNormal])]
+    hash_spec: Vec<HashSpecPreset>,
+
+    /// Keyholder quorum sizes
+    #[clap(long, value_delimiter = ',', default_values_t = [NonZeroU32::new(3).unwrap()])]
+    keyholders: Vec<NonZeroU32>,
+
+    /// Windows per hash proof
+    #[clap(long, default_value_t = DEFAULT_CHUNK_SIZE)]
+    chunk_size: usize,
+
+    /// Prove for real and time it, honoring SP1_PROVER, instead of estimating proving time
+    #[clap(long)]
+    prove: bool,
+
+    /// Proving rate to estimate proving time with
+    #[clap(long, default_value_t = DEFAULT_CYCLES_PER_SECOND)]
+    cycles_per_second: f64,
+
+    /// Where to write the measurements, as JSON lines. Defaults to stdout.
+    #[clap(long)]
+    output: Option<PathBuf>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let opts = Opts::parse();
+    anyhow::ensure!(
+        opts.chunk_size.is_power_of_two(),
+        "--chunk-size must be a power of two"
+    );
+
+    let mut out: Box<dyn Write> = match &opts.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+
+    let bench = ZkBench::new(opts.prove, opts.cycles_per_second);
+    for &hash_spec in &opts.hash_spec {
+        for &keyholders in &opts.keyholders {
+            for &bp in &opts.bp {
+                let scenario = Scenario {
+                    bp,
+                    hash_spec,
+                    keyholders,
+                    chunk_size: opts.chunk_size,
+                };
+                eprintln!("Measuring {scenario:?}");
+                for measurement in bench.run(scenario)? {
+                    serde_json::to_writer(&mut out, &measurement)?;
+                    writeln!(out)?;
+                }
+                // Long runs can be followed as they go
+                out.flush()?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -4,3 +4,5 @@
 pub mod analyzer;
 pub mod loadtest;
 pub mod shared;
+#[cfg(feature = "zk")]
+pub mod zkbench;
@@ -0,0 +1,209 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Measures what the hash, checksum and verification programs cost over synthetic orders.
+//!
+//! Every program run is executed once for its cycle and syscall counts (precompiles such as
+//! the curve operations are syscalls, so they show up there), and proven once so the
+//! verification program has sub-proofs to aggregate. Unless real proving is asked for, the
+//! proofs are mock ones, and proving time is only estimated from the cycle count.
+
+pub mod order;
+
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+use std::time::Instant;
+
+use anyhow::Context;
+use clap::ValueEnum;
+use doprf::prf::{QueryStateSet, VerificationInput};
+use doprf::proof_backend::{
+    MockBackend, ProofBackend, Sp1Backend, CHECKSUM_ELF, HASH_ELF, VERIFICATION_ELF,
+};
+use doprf_client::proof_job::verification_stdin;
+use rand::rngs::OsRng;
+use serde::Serialize;
+use shared_types::hash::HashSpec;
+use shared_types::requests::{RequestContext, RequestId};
+use sp1_sdk::{ProverClient, SP1Stdin};
+
+use order::{random_windows, Keyholders};
+
+/// A rough rate for proving on a CPU, for when proving time is estimated rather than measured.
+/// Runs with `--prove` report the real rate of the machine they ran on.
+pub const DEFAULT_CYCLES_PER_SECOND: f64 = 1_000_000.0;
+
+#[derive(Clone, Copy, Debug, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum HashSpecPreset {
+    /// Normal-size CECH DNA windows only
+    DnaNormal,
+    /// DNA windows and amino acid windows in both directions
+    DnaAa,
+    /// DNA windows, runt DNA windows and amino acid windows
+    DnaRuntsAa,
+}
+
+impl HashSpecPreset {
+    pub fn hash_spec(self) -> HashSpec {
+        match self {
+            Self::DnaNormal => HashSpec::dna_normal_cech(),
+            Self::DnaAa => HashSpec::from_include_runts(false),
+            Self::DnaRuntsAa => HashSpec::from_include_runts(true),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Program {
+    Hash,
+    Checksum,
+    Verification,
+}
+
+/// One synthetic order, screened by one quorum of keyholders.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Scenario {
+    pub bp: usize,
+    pub hash_spec: HashSpecPreset,
+    pub keyholders: NonZeroU32,
+    pub chunk_size: usize,
+}
+
+/// The cost of one program run in a [`Scenario`].
+#[derive(Debug, Serialize)]
+pub struct Measurement {
+    #[serde(flatten)]
+    pub scenario: Scenario,
+    pub windows: usize,
+    pub program: Program,
+    /// Which chunk of the windows a hash program run covered
+    pub chunk: Option<usize>,
+    /// Instructions executed. Syscalls are instructions too, so they are already counted here.
+    pub cycles: u64,
+    /// Number of calls of each syscall that was made at all, by name
+    pub syscalls: BTreeMap<String, u64>,
+    pub execution_secs: f64,
+    pub estimated_proving_secs: f64,
+    /// Only measured when proving for real
+    pub proving_secs: Option<f64>,
+    pub cycles_per_second: Option<f64>,
+}
+
+pub struct ZkBench {
+    client: ProverClient,
+    backend: Box<dyn ProofBackend>,
+    prove: bool,
+    cycles_per_second: f64,
+}
+
+impl ZkBench {
+    /// With `prove`, programs are proven for real and proving is timed. Otherwise proving time
+    /// is estimated at `cycles_per_second`.
+    pub fn new(prove: bool, cycles_per_second: f64) -> Self {
+        let backend: Box<dyn ProofBackend> = if prove {
+            Box::new(Sp1Backend::new())
+        } else {
+            Box::new(MockBackend::new())
+        };
+        Self {
+            client: ProverClient::mock(),
+            backend,
+            prove,
+            cycles_per_second,
+        }
+    }
+
+    /// Runs every program screening a fresh random order needs, in the order they run in.
+    pub fn run(&self, scenario: Scenario) -> anyhow::Result<Vec<Measurement>> {
+        anyhow::ensure!(
+            scenario.chunk_size.is_power_of_two(),
+            "chunk size {} is not a power of two",
+            scenario.chunk_size
+        );
+        let mut rng = OsRng;
+        let windows = random_windows(scenario.bp, &scenario.hash_spec.hash_spec(), &mut rng)?;
+        anyhow::ensure!(!windows.is_empty(), "{} bp has no windows", scenario.bp);
+        let keyholders = Keyholders::random(scenario.keyholders, &mut rng)?;
+
+        let (querystate, hash_inputs, checksum_inputs) = QueryStateSet::prepare(
+            windows.iter().map(|(tag, window)| (*tag, window.as_bytes())),
+            keyholders.quorum(),
+            keyholders.active_security_key.clone(),
+        );
+
+        let record = |program, chunk, run: Run| Measurement {
+            scenario,
+            windows: windows.len(),
+            program,
+            chunk,
+            cycles: run.cycles,
+            syscalls: run.syscalls,
+            execution_secs: run.execution_secs,
+            estimated_proving_secs: run.cycles as f64 / self.cycles_per_second,
+            proving_secs: run.proving_secs,
+            cycles_per_second: run.proving_secs.map(|secs| run.cycles as f64 / secs),
+        };
+
+        let mut measurements = Vec::new();
+        let mut inputs = Vec::new();
+        for (i, chunk) in hash_inputs.chunks(scenario.chunk_size).iter().enumerate() {
+            let (run, input) = self.measure(HASH_ELF, chunk.stdin())?;
+            measurements.push(record(Program::Hash, Some(i), run));
+            inputs.push(input);
+        }
+        let (run, input) = self.measure(CHECKSUM_ELF, checksum_inputs.stdin())?;
+        measurements.push(record(Program::Checksum, None, run));
+        inputs.push(input);
+
+        let request_ctx = RequestContext::single(RequestId::new_unique());
+        let stdin = verification_stdin(
+            inputs,
+            &querystate.to_serializable_set(),
+            &keyholders.respond(&querystate),
+            &request_ctx.to_serializable_request_context(),
+            None,
+        )?;
+        let (run, _) = self.measure(VERIFICATION_ELF, stdin)?;
+        measurements.push(record(Program::Verification, None, run));
+
+        Ok(measurements)
+    }
+
+    fn measure(&self, elf: &[u8], stdin: SP1Stdin) -> anyhow::Result<(Run, VerificationInput)> {
+        let started = Instant::now();
+        let (_, report) = self
+            .client
+            .execute(elf, stdin.clone())
+            .deferred_proof_verification(false)
+            .run()
+            .context("executing program")?;
+        let execution_secs = started.elapsed().as_secs_f64();
+
+        let started = Instant::now();
+        let (_, input) = self.backend.run(elf, stdin).context("proving program")?;
+        let proving_secs = self.prove.then(|| started.elapsed().as_secs_f64());
+
+        let syscalls = report
+            .syscall_counts
+            .iter()
+            .filter(|(_, &count)| count > 0)
+            .map(|(code, &count)| (format!("{code:?}"), count))
+            .collect();
+        let run = Run {
+            cycles: report.total_instruction_count(),
+            syscalls,
+            execution_secs,
+            proving_secs,
+        };
+        Ok((run, input.context("backend produced no proof")?))
+    }
+}
+
+struct Run {
+    cycles: u64,
+    syscalls: BTreeMap<String, u64>,
+    execution_secs: f64,
+    proving_secs: Option<f64>,
+}
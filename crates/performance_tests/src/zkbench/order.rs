@@ -0,0 +1,77 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Synthetic orders, and keyholders to answer their queries without running any servers.
+
+use std::num::NonZeroU32;
+
+use curve25519_dalek::Scalar;
+use doprf::active_security::{commitments_from_secret_and_keyshares, ActiveSecurityKey};
+use doprf::party::{KeyserverId, KeyserverIdSet};
+use doprf::prf::{generate_keyshares, HashPart, KeyShare, QueryStateSet};
+use doprf::tagged::HashTag;
+use doprf_client::windows::Windows;
+use packed_ristretto::PackedRistrettos;
+use quickdna::Nucleotide;
+use rand::seq::SliceRandom;
+use rand::{CryptoRng, RngCore};
+use shared_types::hash::HashSpec;
+
+/// Windows `bp` random nucleotides the way `hash_spec` says to.
+pub fn random_windows(
+    bp: usize,
+    hash_spec: &HashSpec,
+    rng: &mut impl RngCore,
+) -> anyhow::Result<Vec<(HashTag, String)>> {
+    let nucleotides = [Nucleotide::A, Nucleotide::C, Nucleotide::G, Nucleotide::T];
+    let dna: Vec<Nucleotide> = (0..bp)
+        .map(|_| *nucleotides.choose(rng).unwrap())
+        .collect();
+    Ok(Windows::from_dna(dna, hash_spec)?.collect())
+}
+
+/// A quorum of keyholders sharing a fresh random key.
+pub struct Keyholders {
+    shares: Vec<(KeyserverId, KeyShare)>,
+    pub active_security_key: ActiveSecurityKey,
+}
+
+impl Keyholders {
+    pub fn random(quorum: NonZeroU32, rng: &mut (impl RngCore + CryptoRng)) -> anyhow::Result<Self> {
+        let secret = KeyShare::from(Scalar::random(rng));
+        let shares = generate_keyshares(&secret, quorum, quorum, rng)?;
+        let commitments = commitments_from_secret_and_keyshares(&secret, &shares, quorum)?;
+        let shares = shares
+            .into_iter()
+            .enumerate()
+            .map(|(i, share)| (KeyserverId::try_from(i as u32 + 1).unwrap(), share))
+            .collect();
+        Ok(Self {
+            shares,
+            active_security_key: ActiveSecurityKey::from_commitments(commitments),
+        })
+    }
+
+    pub fn quorum(&self) -> usize {
+        self.shares.len()
+    }
+
+    /// What each keyholder would respond to the queries of `querystate` with.
+    pub fn respond(
+        &self,
+        querystate: &QueryStateSet,
+    ) -> Vec<(KeyserverId, PackedRistrettos<HashPart>)> {
+        let ids: KeyserverIdSet = self.shares.iter().map(|(id, _)| *id).collect();
+        self.shares
+            .iter()
+            .map(|(id, share)| {
+                let coeff = ids.langrange_coefficient_for_id(id);
+                let parts = querystate
+                    .queries()
+                    .map(|q| share.apply_query_and_lagrange_coefficient(*q, &coeff))
+                    .collect();
+                (*id, parts)
+            })
+            .collect()
+    }
+}
@@ -90,6 +90,24 @@ pub enum Screen {
     ScreenBeforeEtHashes,
     #[error("exemption token validation error: {0}")]
     EtValidation(String),
+    #[error("screening proof verifying key {actual} does not match expected key {expected}")]
+    UnexpectedVerifyingKey { expected: String, actual: String },
+    #[error("screening proof is invalid: {0}")]
+    ProofInvalid(String),
+    #[error("screening proof public values could not be decoded: {0}")]
+    MalformedProofOutput(String),
+    #[error("screening proof aggregated proofs of programs other than the hash and checksum programs")]
+    UnexpectedSubProofs,
+    #[error("screening proof validated the keyserver responses against a different active security key")]
+    UnexpectedActiveSecurityKey,
+    #[error("screening proof did not attest to a successful screening: {0}")]
+    ProofNotAccepted(String),
+    #[error("screening proof screened against HDB build {0}, not the one loaded by this server")]
+    UnexpectedHdbCommitment(String),
+    #[error("screening proof screened the hashes itself; such proofs are only checked offline")]
+    ProofAlreadyScreened,
+    #[error("screened hashes do not match the hashes committed by the screening proof")]
+    ProofHashMismatch,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -22,6 +22,8 @@ pub struct OpenedClientState {
     pub client_mutual_auth_sig: Signature,
     pub hash_spec: HashSpec,
     pub server_version: u64,
+    /// Whether the server agreed to prove its keyserve response with a DLEQ proof.
+    pub dleq_proof: bool,
 }
 
 /// Small wrapper for handling client session logic.
@@ -55,6 +55,7 @@ pub fn client_initialize(
         protocol_version: 1,
         version_hint,
         nonce: client_nonce,
+        dleq_proof: request_type == ClientRequestType::Keyserve,
         request_type,
         cert_chain: cert_chain.clone(),
         nucleotide_total_count,
@@ -167,6 +168,7 @@ where
         cert_chain: server_cert_chain,
         sig: server_mutual_auth_sig,
         hash_spec,
+        dleq_proof: request.dleq_proof,
     };
     let client_state = ServerStateForClient::Opened(ServerStateForOpenedClient {
         cookie: rand::thread_rng().gen(),
@@ -275,6 +277,7 @@ where
         client_mutual_auth_sig,
         hash_spec: open_response.hash_spec,
         server_version: open_response.server_version,
+        dleq_proof: client_state.open_request.dleq_proof && open_response.dleq_proof,
     })
 }
 
@@ -32,6 +32,11 @@ pub struct OpenRequest {
     /// provided.
     #[serde(default)]
     pub debug_info: bool,
+    /// Whether the keyserve response should end with a DLEQ proof of its hash parts.
+    /// Defaults to `false` if not provided, since clients from before these proofs
+    /// would take the proof for a hash part.
+    #[serde(default)]
+    pub dleq_proof: bool,
 }
 
 impl OpenRequest {
@@ -86,6 +91,11 @@ pub struct OpenResponse<TokenKind: TokenGroup> {
     pub cert_chain: TokenBundle<TokenKind>,
     pub sig: Signature,
     pub hash_spec: HashSpec,
+    /// Whether the keyserve response will end with the DLEQ proof the client asked for.
+    /// Defaults to `false` if not provided, since servers from before these proofs
+    /// never send one.
+    #[serde(default)]
+    pub dleq_proof: bool,
 }
 
 pub type KeyserverOpenResponse = OpenResponse<KeyserverTokenGroup>;
@@ -10,9 +10,11 @@ use certificates::{
     TokenGroup,
 };
 use doprf::prf::CompletedHashValue;
+#[cfg(feature = "zk")]
+use doprf::prf::VerificationInput;
 use doprf::{
     party::{KeyserverId, KeyserverIdSet},
-    prf::{HashPart, Query, VerificationInput},
+    prf::{HashPart, Query},
     tagged::TaggedHash,
 };
 use http_client::{BaseApiClient, HttpError};
@@ -236,6 +238,7 @@ impl ScepClient<DatabaseTokenGroup> {
             .await
     }
 
+    #[cfg(feature = "zk")]
     pub async fn screen_and_verify(
         &self,
         hashes: &PackedRistrettos<TaggedHash>,
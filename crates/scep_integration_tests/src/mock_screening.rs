@@ -104,5 +104,6 @@ pub fn mock_screen(hashes: &[TaggedHash], exemptions: &Exemptions) -> HdbScreeni
             .collect(),
         debug_hdb_responses: None,
         provider_reference: None,
+        hdb_commitment: None,
     }
 }
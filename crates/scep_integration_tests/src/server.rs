@@ -22,7 +22,13 @@ use tracing::{error, info};
 
 use certificates::revocation::RevocationList;
 use certificates::{key_traits::CanLoadKey, KeyPair, PublicKey, TokenBundle, TokenGroup};
+#[cfg(feature = "zk")]
+use doprf::prf::VerificationInput;
 use doprf::prf::{CompletedHashValue, HashPart, Query};
+#[cfg(feature = "zk")]
+use doprf::proof_backend::{MockBackend, ProofBackend, CHECKSUM_ELF, HASH_ELF, VERIFICATION_ELF};
+#[cfg(feature = "zk")]
+use doprf::proof_output::{ScreeningProofOutput, ScreeningStatus, SubProofVkeys};
 use doprf::tagged::TaggedHash;
 use minhttp::nursery::Nursery;
 use minhttp::response::{self, GenericResponse};
@@ -31,6 +37,8 @@ use minhttp::signal::{fast_shutdown_requested, graceful_shutdown_requested};
 use scep::states::{ServerSessions, ServerStateForClient};
 use shared_types::hash::HashSpec;
 use shared_types::requests::RequestId;
+#[cfg(feature = "zk")]
+use sp1_sdk::HashableKey;
 use streamed_ristretto::hyper::{check_content_length, from_request, BodyStream};
 use streamed_ristretto::stream::{
     check_content_type, ConversionError, HasShortErrorMsg, RistrettoError, HASH_SIZE,
@@ -251,6 +259,14 @@ where
             peer,
         ),
         (&Method::OPTIONS, scep::SCREEN_WITH_EXEMPTION_ENDPOINT) => response::empty(),
+        #[cfg(feature = "zk")]
+        (&Method::POST, scep::SCREEN_AND_VERIFY_ENDPOINT) => ok_or_err(
+            endpoint_screen_and_verify(request_id, &server_state, request).await,
+            request_id,
+            peer,
+        ),
+        #[cfg(feature = "zk")]
+        (&Method::OPTIONS, scep::SCREEN_AND_VERIFY_ENDPOINT) => response::empty(),
         (&Method::POST, scep::EXEMPTION_ENDPOINT) => ok_or_err(
             endpoint_exemption(request_id, &server_state, request).await,
             request_id,
@@ -456,6 +472,107 @@ async fn endpoint_screen<T: TokenGroup>(
     ))
 }
 
+/// Checks a screening proof the way hdbserver does, against SP1's mock prover and the embedded
+/// programs, and returns the encoded tagged hashes it attests to. Any active security key is
+/// accepted.
+#[cfg(feature = "zk")]
+fn check_mock_screening_proof(
+    verification: &VerificationInput,
+) -> Result<Vec<u8>, scep::error::Screen> {
+    let backend = MockBackend::new();
+    let expected = backend.verifying_key(VERIFICATION_ELF).bytes32();
+    let actual = verification.vk.bytes32();
+    if actual != expected {
+        return Err(scep::error::Screen::UnexpectedVerifyingKey { expected, actual });
+    }
+    backend
+        .verify(verification)
+        .map_err(|e| scep::error::Screen::ProofInvalid(e.to_string()))?;
+
+    let output = ScreeningProofOutput::decode(&verification.proof.public_values.to_vec())
+        .map_err(|e| scep::error::Screen::MalformedProofOutput(e.to_string()))?;
+    let sub_proof_vkeys = SubProofVkeys {
+        hash: backend.verifying_key(HASH_ELF).hash_u32(),
+        checksum: backend.verifying_key(CHECKSUM_ELF).hash_u32(),
+    };
+    if !sub_proof_vkeys.matches(&output.sub_proof_vkeys) {
+        return Err(scep::error::Screen::UnexpectedSubProofs);
+    }
+    match output.status {
+        ScreeningStatus::Hashed(hashes) => Ok(hashes),
+        ScreeningStatus::Screened(_) => Err(scep::error::Screen::ProofAlreadyScreened),
+        status => Err(scep::error::Screen::ProofNotAccepted(status.to_string())),
+    }
+}
+
+#[cfg(feature = "zk")]
+async fn endpoint_screen_and_verify<T: TokenGroup>(
+    request_id: &RequestId,
+    server_state: &ServerState<T>,
+    request: Request<Incoming>,
+) -> Result<GenericResponse, scep::error::ScepError<scep::error::Screen>> {
+    #[derive(serde::Deserialize)]
+    struct RequestWithVerification {
+        ristretto_data: Vec<u8>,
+        verification: VerificationInput,
+    }
+
+    check_content_type(request.headers(), "application/json")
+        .context("in screen-and-verify")
+        .map_err(scep::error::ScepError::InvalidMessage)?;
+
+    let cookie = scep_server_helpers::request::get_session_cookie(request.headers())?;
+
+    let client_state = server_state
+        .clients
+        .write()
+        .unwrap()
+        .take_session(&cookie)
+        .ok_or_else(|| {
+            scep::error::ScepError::InvalidMessage(anyhow::anyhow!("unknown cookie {cookie}"))
+        })?;
+
+    let bytes = request
+        .into_body()
+        .collect()
+        .await
+        .map_err(|e| scep::error::ScepError::InvalidMessage(e.into()))?
+        .to_bytes();
+    let request_data: RequestWithVerification = serde_json::from_slice(&bytes)
+        .map_err(|e| scep::error::ScepError::InvalidMessage(e.into()))?;
+
+    let hash_count = check_content_length(
+        Some(request_data.ristretto_data.len() as u64),
+        TaggedHash::SIZE,
+    )
+    .context("in screen-and-verify")
+    .map_err(scep::error::ScepError::InvalidMessage)?;
+
+    info!("{request_id}: Screening request of size {hash_count} with a proof");
+
+    let (_common, _client) = scep::steps::server_screen_client(hash_count, client_state)?;
+
+    // The proof must attest to exactly the hashes we are about to screen
+    if check_mock_screening_proof(&request_data.verification)? != request_data.ristretto_data {
+        return Err(scep::error::Screen::ProofHashMismatch.into());
+    }
+    let hashes: Vec<TaggedHash> = request_data
+        .ristretto_data
+        .chunks_exact(TaggedHash::SIZE)
+        .map(|hash| {
+            let hash: [u8; TaggedHash::SIZE] = hash.try_into().unwrap();
+            hash.try_into().unwrap()
+        })
+        .collect();
+
+    info!("Got proven hashes: {hashes:?}");
+
+    Ok(response::json(
+        StatusCode::OK,
+        serde_json::to_string_pretty(&mock_screen(&hashes, &Default::default())).unwrap(),
+    ))
+}
+
 async fn endpoint_screen_with_exemption<T: TokenGroup>(
     request_id: &RequestId,
     server_state: &ServerState<T>,
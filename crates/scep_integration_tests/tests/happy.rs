@@ -208,6 +208,7 @@ pub async fn smoketest() {
             results: vec![],
             debug_hdb_responses: None,
             provider_reference: None,
+            hdb_commitment: None,
         },
     })
     .await
@@ -231,6 +232,7 @@ pub async fn test_hazard_denied() {
             }],
             debug_hdb_responses: None,
             provider_reference: None,
+            hdb_commitment: None,
         },
     })
     .await
@@ -255,6 +257,7 @@ pub async fn test_hazard_exempt_organism() {
             }],
             debug_hdb_responses: None,
             provider_reference: None,
+            hdb_commitment: None,
         },
     })
     .await
@@ -280,6 +283,7 @@ pub async fn test_hazard_exempt_hash() {
             }],
             debug_hdb_responses: None,
             provider_reference: None,
+            hdb_commitment: None,
         },
     })
     .await;
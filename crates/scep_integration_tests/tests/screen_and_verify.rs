@@ -0,0 +1,197 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+#![cfg(feature = "zk")]
+
+use std::sync::Arc;
+
+use certificates::{DatabaseTokenGroup, KeyserverTokenGroup};
+use doprf::{
+    active_security::{ActiveSecurityKey, Commitment},
+    prf::{KeyShare, Query, QueryStateSet, VerificationInput},
+    proof_backend::{MockBackend, ProofBackend, VERIFICATION_ELF},
+    proof_inputs::DEFAULT_CHUNK_SIZE,
+    proof_output::ScreeningProofOutput,
+    shims::genkey,
+    tagged::{HashTag, TaggedHash},
+};
+use doprf_client::packed_ristretto::PackedRistrettos;
+use doprf_client::proof_job::verification_stdin;
+use scep_client_helpers::ClientCerts;
+use scep_integration_tests::{
+    make_certs::{make_certs, MakeCertsOptions},
+    server::{Opts, TestServer},
+};
+use shared_types::{
+    hash::HashSpec,
+    hdb::HdbScreeningResult,
+    requests::{RequestContext, RequestId},
+    synthesis_permission::Region,
+};
+use tracing::info;
+
+/// Screens `hashes` through the screen-and-verify endpoint in a new session.
+async fn screen_and_verify(
+    hdb_client: &scep_client_helpers::ScepClient<DatabaseTokenGroup>,
+    hashes: &PackedRistrettos<TaggedHash>,
+    verification: VerificationInput,
+) -> Result<HdbScreeningResult, http_client::HttpError> {
+    let keyserver_id = MakeCertsOptions::default().keyserver_id;
+    let opened_state = hdb_client
+        .open(
+            1,
+            None,
+            vec![keyserver_id].into(),
+            false,
+            Region::All,
+            false,
+        )
+        .await
+        .unwrap();
+    hdb_client
+        .authenticate(opened_state, hashes.len() as u64)
+        .await
+        .unwrap();
+    hdb_client.screen_and_verify(hashes, verification).await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn mock_screening_proof_is_verified() {
+    let certs = make_certs(Default::default());
+    let issuer_pks = vec![
+        certs.infra_root_keypair.public_key(),
+        certs.manu_root_keypair.public_key(),
+    ];
+
+    // A single keyserver holding the whole key, so its responses validate against a quorum of one
+    let key: KeyShare = {
+        let mut stdout: Vec<u8> = vec![];
+        genkey::main(&genkey::Opts {}, &mut stdout, &mut vec![]).expect("Generating key failed");
+        std::str::from_utf8(&stdout)
+            .expect("Got invalid utf8 key")
+            .trim_end()
+            .parse()
+            .expect("Got invalid keyshare")
+    };
+    let active_security_key =
+        ActiveSecurityKey::from_commitments([Commitment::from_keyshare(&key)]);
+    let keyserver_id = MakeCertsOptions::default().keyserver_id;
+
+    let keyserver = TestServer::spawn(
+        Opts {
+            issuer_pks: issuer_pks.clone(),
+            revocation_list: Default::default(),
+            server_cert_chain: certs.keyserver_tokenbundle,
+            server_keypair: certs.keyserver_keypair,
+            keyserve_fn: Arc::new(move |query: Query| key.apply(query)),
+            hash_spec: HashSpec::dna_normal_cech(),
+        },
+        async {},
+    )
+    .await;
+
+    let hdb = TestServer::spawn(
+        Opts {
+            issuer_pks: issuer_pks.clone(),
+            revocation_list: Default::default(),
+            server_cert_chain: certs.database_tokenbundle,
+            server_keypair: certs.database_keypair,
+            keyserve_fn: Arc::new(move |query: Query| key.apply(query)),
+            hash_spec: HashSpec::dna_normal_cech(),
+        },
+        async {},
+    )
+    .await;
+
+    let keyserver_port = keyserver.port();
+    let hdb_port = hdb.port();
+
+    let client_certs = Arc::new(ClientCerts::with_custom_roots(
+        issuer_pks.clone(),
+        certs.synth_tokenbundle.clone(),
+        certs.synth_keypair.clone(),
+    ));
+    let request_id = RequestId::new_unique();
+    let request_ctx = RequestContext::single(request_id.clone());
+    let http_client = http_client::BaseApiClient::new(request_id);
+
+    // Blind the windows, proving the hash and checksum programs with the mock prover
+    let backend = MockBackend::new();
+    let windows = ["CGGCTTTTTGGTAGTTAGGCTATTGG", "GTACAGACCACAGTTGCCGCGCCCTC"];
+    let (querystate, inputs) = QueryStateSet::from_iter(
+        windows
+            .iter()
+            .enumerate()
+            .map(|(i, window)| (HashTag::new(i == 0, 0, i), window)),
+        1,
+        active_security_key,
+        DEFAULT_CHUNK_SIZE,
+        &backend,
+    )
+    .unwrap();
+
+    let keyserver_client = scep_client_helpers::ScepClient::<KeyserverTokenGroup>::new(
+        http_client.clone(),
+        format!("http://localhost:{keyserver_port}"),
+        client_certs.clone(),
+        "smoketest".to_owned(),
+    );
+    let opened_state = keyserver_client
+        .open(1, None, vec![keyserver_id].into(), keyserver_id, false)
+        .await
+        .unwrap();
+    keyserver_client
+        .authenticate(opened_state, querystate.len() as u64)
+        .await
+        .unwrap();
+    let response = keyserver_client
+        .keyserve(&PackedRistrettos::<Query>::from(&querystate))
+        .await
+        .unwrap();
+
+    // Aggregate the sub-proofs over the keyserver's responses, as doprf_client does
+    let stdin = verification_stdin(
+        inputs,
+        &querystate.to_serializable_set(),
+        &vec![(keyserver_id, response)],
+        &request_ctx.to_serializable_request_context(),
+        None,
+    )
+    .unwrap();
+    let (public_values, verification) = backend.run(VERIFICATION_ELF, stdin).unwrap();
+    let verification = verification.expect("the mock prover produces proofs");
+    let output = ScreeningProofOutput::decode(&public_values).unwrap();
+    let hashes: PackedRistrettos<TaggedHash> = output
+        .status
+        .tagged_hashes()
+        .expect("the keyserver responses should validate")
+        .chunks_exact(TaggedHash::SIZE)
+        .map(|hash| <[u8; TaggedHash::SIZE]>::try_from(hash).unwrap())
+        .collect();
+    assert_eq!(hashes.len(), windows.len());
+
+    let hdb_client = scep_client_helpers::ScepClient::<DatabaseTokenGroup>::new(
+        http_client,
+        format!("http://localhost:{hdb_port}"),
+        client_certs,
+        "smoketest".to_owned(),
+    );
+
+    // The hashes the proof attests to are screened...
+    let screen_response = screen_and_verify(&hdb_client, &hashes, verification.clone())
+        .await
+        .unwrap();
+    info!("hdb screen_response = {screen_response:#?}");
+    assert_eq!(screen_response.results, vec![]);
+
+    // ...but the same proof does not vouch for any other hashes
+    let reordered: PackedRistrettos<TaggedHash> =
+        hashes.encoded_items().iter().rev().copied().collect();
+    let rejected = screen_and_verify(&hdb_client, &reordered, verification).await;
+    assert!(rejected.is_err(), "got {rejected:?}");
+
+    info!("finished test");
+
+    keyserver.stop().await;
+    hdb.stop().await;
+}
@@ -13,6 +13,9 @@ pub struct HdbScreeningResult {
     pub results: Vec<ConsolidatedHazardResult>,
     pub debug_hdb_responses: Option<Vec<DebugSeqHdbResponse>>,
     pub provider_reference: Option<String>,
+    /// Hex commitment to the entries of the HDB the hashes were screened against, if known.
+    #[serde(default)]
+    pub hdb_commitment: Option<String>,
 }
 
 /// Consolidated Result of DOPRF on contiguous sequences that were contained in the HDB
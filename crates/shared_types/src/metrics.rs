@@ -33,6 +33,22 @@ static HDB_IO_ERRORS_NAME: &str = "hdb_io_errors";
 static HDB_IO_ERRORS_DESCRIPTION: &str =
     "Total number of I/O errors (disk read errors, malformed entries, etc.) since last start";
 
+static PROOFS_GENERATED_NAME: &str = "total_proofs_generated";
+static PROOFS_GENERATED_DESCRIPTION: &str =
+    "Total number of screening proofs generated since last start";
+
+static PROOFS_VERIFIED_NAME: &str = "total_proofs_verified";
+static PROOFS_VERIFIED_DESCRIPTION: &str =
+    "Total number of screening proofs verified since last start";
+
+static PROOFS_REJECTED_NAME: &str = "total_proofs_rejected";
+static PROOFS_REJECTED_DESCRIPTION: &str =
+    "Total number of screening proofs rejected since last start";
+
+static KEYSERVER_FAILOVERS_NAME: &str = "total_keyserver_failovers";
+static KEYSERVER_FAILOVERS_DESCRIPTION: &str =
+    "Total number of times a screening moved to a new keyserver quorum after catching keyservers misbehaving since last start";
+
 pub struct SynthClientMetrics {
     pub hash_counter: IntCounter,
     pub bp_counter: IntCounter,
@@ -40,6 +56,8 @@ pub struct SynthClientMetrics {
     pub max_clients: IntGauge,
     pub requests: IntCounter,
     pub hazards: IntCounter,
+    pub proofs_generated: IntCounter,
+    pub keyserver_failovers: IntCounter,
 }
 
 impl SynthClientMetrics {
@@ -57,6 +75,16 @@ impl SynthClientMetrics {
             requests: register_int_counter!(TOTAL_REQUESTS_NAME, TOTAL_REQUESTS_DESCRIPTION)
                 .unwrap(),
             hazards: register_int_counter!(TOTAL_HAZARDS_NAME, TOTAL_HAZARDS_DESCRIPTION).unwrap(),
+            proofs_generated: register_int_counter!(
+                PROOFS_GENERATED_NAME,
+                PROOFS_GENERATED_DESCRIPTION
+            )
+            .unwrap(),
+            keyserver_failovers: register_int_counter!(
+                KEYSERVER_FAILOVERS_NAME,
+                KEYSERVER_FAILOVERS_DESCRIPTION
+            )
+            .unwrap(),
         }
     }
 
@@ -81,6 +109,8 @@ pub struct HdbMetrics {
     pub requests: IntCounter,
     pub io_errors: IntCounter,
     pub bad_requests: IntCounter,
+    pub proofs_verified: IntCounter,
+    pub proofs_rejected: IntCounter,
 }
 
 impl HdbMetrics {
@@ -100,6 +130,16 @@ impl HdbMetrics {
                 .unwrap(),
             bad_requests: register_int_counter!(BAD_REQUESTS_NAME, BAD_REQUESTS_DESCRIPTION)
                 .unwrap(),
+            proofs_verified: register_int_counter!(
+                PROOFS_VERIFIED_NAME,
+                PROOFS_VERIFIED_DESCRIPTION
+            )
+            .unwrap(),
+            proofs_rejected: register_int_counter!(
+                PROOFS_REJECTED_NAME,
+                PROOFS_REJECTED_DESCRIPTION
+            )
+            .unwrap(),
         }
     }
 
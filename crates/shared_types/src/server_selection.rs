@@ -93,4 +93,7 @@ pub struct KeyserverQualificationResponse {
 pub struct HdbQualificationResponse {
     /// Which generation numbers this HDB supports (usually one, but sometimes more)
     pub supported_generations: Vec<u32>,
+    /// Hex commitment to the entries of the HDB this server screens against, if it has one.
+    #[serde(default)]
+    pub hdb_commitment: Option<String>,
 }
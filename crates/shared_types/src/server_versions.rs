@@ -17,4 +17,8 @@ pub struct HdbVersion {
     /// Timestamp this HDB was generated.
     /// `None` if unknown.
     pub hdb_timestamp: Option<String>,
+    /// Hex commitment to the entries of this HDB, as built by `genacc`.
+    /// `None` if the HDB has no up-to-date accumulator.
+    #[serde(default)]
+    pub hdb_commitment: Option<String>,
 }
@@ -123,6 +123,9 @@ pub enum RistrettoError<SE = std::convert::Infallible, CE: Debug = std::convert:
     /// Unable to interpret a chunk of bytes as a ristretto
     #[error("data {data:?} cannot be converted to ristretto because {error:?}")]
     Conversion { data: Bytes, error: CE },
+    /// The server failed to produce the rest of the stream
+    #[error("internal error")]
+    Internal,
 }
 
 impl<SE, CE: Debug> HasShortErrorMsg for RistrettoError<SE, CE> {
@@ -131,6 +134,7 @@ impl<SE, CE: Debug> HasShortErrorMsg for RistrettoError<SE, CE> {
             Self::Stream(_) => *b"Error reading ristrettos.\0\0\0\0\0",
             Self::Incomplete { .. } => *b"Ristretto was incomplete.\0\0\0\0\0",
             Self::Conversion { .. } => *b"Ristretto was invalid.\0\0\0\0\0\0\0\0",
+            Self::Internal => *b"Internal server error.\0\0\0\0\0\0\0\0",
         }
     }
 }
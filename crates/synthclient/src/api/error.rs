@@ -61,6 +61,8 @@ pub enum ApiWarning {
     CertificateExpiringSoon(Fields),
     TooShort(Fields),
     TooAmbiguous(Fields),
+    KeyserversReplaced(Fields),
+    ProofQueueFull(Fields),
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
@@ -100,6 +102,19 @@ impl ApiWarning {
             "Permission was granted because the order was too ambiguous for SecureDNA to detect hazards."
         ))
     }
+
+    pub fn keyservers_replaced(domains: &[String]) -> Self {
+        Self::KeyserversReplaced(Fields::new(format!(
+            "Keyservers {} sent responses that failed validation, so the order was screened with a replacement quorum.",
+            domains.join(", ")
+        )))
+    }
+
+    pub fn proof_queue_full() -> Self {
+        Self::ProofQueueFull(Fields::new(
+            "The order was screened, but not queued for proving because too many screenings are already waiting for a proof."
+        ))
+    }
 }
 
 impl ApiError {
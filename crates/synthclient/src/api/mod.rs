@@ -7,7 +7,9 @@ pub mod types;
 
 pub use debug::{DebugFastaRecordHits, DebugHit, DebugInfo, SequenceProvenance};
 pub use error::{ApiError, ApiWarning};
+#[cfg(feature = "zk")]
+pub use types::ProofJobResponse;
 pub use types::{
     ApiResponse, CheckFastaRequest, CheckNcbiRequest, FastaRecordHits, HazardHits, HitOrganism,
-    HitRegion, HitType, Region, RequestCommon, SynthesisPermission, VersionInfo,
+    HitRegion, HitType, ProofJobStatus, Region, RequestCommon, SynthesisPermission, VersionInfo,
 };
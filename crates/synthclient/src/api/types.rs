@@ -2,6 +2,8 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use doprf::party::KeyserverId;
+#[cfg(feature = "zk")]
+use doprf::proof_bundle::ProofBundle;
 use serde::{Deserialize, Serialize};
 use shared_types::{et::WithOtps, hdb::ConsolidatedHazardResult};
 
@@ -114,6 +116,14 @@ pub struct ApiResponse {
     /// Additional debug info, if requested.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub debug_info: Option<DebugInfo>,
+    /// If known, the hex commitment to the hazard database the input was screened against,
+    /// which ties any proof of this screening to a specific database build.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hdb_commitment: Option<String>,
+    /// If the screening is being proven in the background, the id its proof can be fetched
+    /// with from `/v1/proof/{id}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_job_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -215,6 +225,35 @@ pub struct VersionInfo {
     pub keyserver_versions: Option<Vec<(KeyserverId, Option<String>)>>,
 }
 
+/// Where a background proof of a screening has got to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofJobStatus {
+    /// Waiting for a free prover.
+    Queued,
+    Proving,
+    /// Finished, with a bundle.
+    Proven,
+    /// Finished, with an error.
+    Failed,
+}
+
+/// The response to `GET /v1/proof/{id}`.
+#[cfg(feature = "zk")]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProofJobResponse {
+    pub id: String,
+    /// The id of the screening request being proven.
+    pub request_id: String,
+    pub status: ProofJobStatus,
+    /// Why proving failed, if it did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The finished proof, once proven.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<ProofBundle>,
+}
+
 impl HazardHits {
     pub fn from_consolidated_hazard_result(grouped: ConsolidatedHazardResult, dna: &str) -> Self {
         Self {
@@ -358,6 +397,8 @@ mod tests {
                 errors: vec![],
                 debug_info: None,
                 provider_reference: None,
+                hdb_commitment: None,
+                proof_job_id: None,
             },
             json!({"synthesis_permission": "granted"}),
         );
@@ -406,6 +447,8 @@ mod tests {
                 errors: vec![],
                 debug_info: None,
                 provider_reference: None,
+                hdb_commitment: None,
+                proof_job_id: None,
             },
             json!({
                 "synthesis_permission": "denied",
@@ -463,6 +506,8 @@ mod tests {
                 errors: vec![],
                 debug_info: None,
                 provider_reference: Some("my_reference".to_owned()),
+                hdb_commitment: None,
+                proof_job_id: None,
             },
             json!({
                 "synthesis_permission": "granted",
@@ -498,6 +543,8 @@ ATCGATAoopsATCGATCGATCGATCGA
                 errors: vec![err.into()],
                 debug_info: None,
                 provider_reference: Some("arbitrary string".to_owned()),
+                hdb_commitment: None,
+                proof_job_id: None,
             },
             json!({
                 "synthesis_permission": "denied",
@@ -523,6 +570,8 @@ ATCGATAoopsATCGATCGATCGATCGA
                 errors: vec![ApiError::not_found("/foo")],
                 debug_info: None,
                 provider_reference: None,
+                hdb_commitment: None,
+                proof_job_id: None,
             },
             json!({
                 "synthesis_permission": "denied",
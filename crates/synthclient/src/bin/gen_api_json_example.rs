@@ -48,6 +48,8 @@ fn main() {
         errors: vec![],
         debug_info: None,
         provider_reference: Some("provider reference string".into()),
+        hdb_commitment: None,
+        proof_job_id: None,
     };
 
     println!("{}", serde_json::to_string_pretty(&resp).unwrap());
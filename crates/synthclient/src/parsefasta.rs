@@ -2,13 +2,25 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use std::cmp::min;
+#[cfg(feature = "zk")]
+use std::future::Future;
+#[cfg(feature = "zk")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "zk")]
+use std::pin::Pin;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use certificates::{ExemptionTokenGroup, TokenBundle};
+#[cfg(feature = "zk")]
+use doprf::prf::ProofMode;
+#[cfg(feature = "zk")]
+use doprf::proof_bundle::{BundleFormat, ProofBundle};
 use shared_types::et::WithOtps;
 use thiserror::Error;
 use tracing::info;
+#[cfg(feature = "zk")]
+use tracing::warn;
 
 use crate::api::ApiWarning;
 use crate::{
@@ -18,6 +30,10 @@ use crate::{
     },
     retry_if::retry_if,
 };
+#[cfg(feature = "zk")]
+use doprf_client::proof_job::ProofJob;
+#[cfg(feature = "zk")]
+use doprf_client::{ProvingConfig, ScreeningProof};
 use doprf_client::{
     error::DoprfError, server_selection::ServerSelector,
     server_version_handler::LastServerVersionHandler, windows::WindowsError, DoprfConfig,
@@ -80,6 +96,54 @@ pub struct CheckerConfiguration<'a> {
     /// Exemption tokens.
     pub ets: Vec<WithOtps<TokenBundle<ExemptionTokenGroup>>>,
     pub server_version_handler: LastServerVersionHandler,
+    /// How screenings are proven to the HDB
+    #[cfg(feature = "zk")]
+    pub proof_mode: ProofMode,
+    /// Where to archive the proof bundle of each proven screening, if anywhere
+    #[cfg(feature = "zk")]
+    pub proof_bundle_dir: Option<PathBuf>,
+    #[cfg(feature = "zk")]
+    pub proof_bundle_format: BundleFormat,
+    /// How many windows each hash proof covers
+    #[cfg(feature = "zk")]
+    pub proof_chunk_size: usize,
+    /// Where to send screenings to be proven in the background. If set, screenings are answered
+    /// without waiting for a proof, and `proof_mode` only applies to whoever proves the jobs.
+    #[cfg(feature = "zk")]
+    pub proof_jobs: Option<ProofJobSubmitter>,
+}
+
+#[cfg(feature = "zk")]
+type DynFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+#[cfg(feature = "zk")]
+type SubmitJob = Box<dyn Fn(ProofJob) -> DynFuture<Result<String, ProofQueueError>> + Send + Sync>;
+
+/// Why a deferred proof was not queued.
+#[cfg(feature = "zk")]
+#[derive(Debug, Error)]
+pub enum ProofQueueError {
+    /// As many jobs are waiting as the queue holds.
+    #[error("the proof queue is full")]
+    Busy,
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Hands deferred proofs to a prover, getting back an id to look the proof up with.
+#[cfg(feature = "zk")]
+pub struct ProofJobSubmitter {
+    submit: SubmitJob,
+}
+
+#[cfg(feature = "zk")]
+impl ProofJobSubmitter {
+    pub fn new(submit: SubmitJob) -> Self {
+        Self { submit }
+    }
+
+    pub async fn submit(&self, job: ProofJob) -> Result<String, ProofQueueError> {
+        (self.submit)(job).await
+    }
 }
 
 pub struct LimitConfiguration<'a> {
@@ -259,6 +323,61 @@ fn group_hits<T: NucleotideLike>(
     Ok(hits_by_record)
 }
 
+/// Writes `bundle` to `dir`, named by its request id. Request ids can come from clients, so
+/// anything but alphanumerics, `-` and `_` is replaced.
+#[cfg(feature = "zk")]
+pub(crate) fn write_proof_bundle(
+    dir: &Path,
+    bundle: &ProofBundle,
+    format: BundleFormat,
+) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let name: String = bundle
+        .request_id
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    let path = dir.join(format!("{name}.{}", format.extension()));
+    std::fs::write(path, bundle.to_bytes(format)?)?;
+    Ok(())
+}
+
+/// Archives the proof of a screening, or queues the screening to be proven, returning the id of
+/// the proof job if one was queued.
+#[cfg(feature = "zk")]
+async fn handle_proof(
+    request_ctx: &RequestContext,
+    config: &CheckerConfiguration<'_>,
+    proof: Option<ScreeningProof>,
+) -> Result<Option<String>, ProofQueueError> {
+    let Some(proof) = proof else {
+        return Ok(None);
+    };
+    match (proof, &config.proof_jobs) {
+        (ScreeningProof::Bundle(bundle), _) => {
+            if let Some(m) = &config.metrics {
+                m.proofs_generated.inc();
+            }
+            if let Some(dir) = &config.proof_bundle_dir {
+                // The screening itself succeeded, so a failure to archive its proof is not fatal
+                if let Err(e) = write_proof_bundle(dir, &bundle, config.proof_bundle_format) {
+                    warn!("{request_ctx}: failed to archive proof bundle: {e}");
+                }
+            }
+            Ok(None)
+        }
+        (ScreeningProof::Job(job), Some(proof_jobs)) => {
+            let id = proof_jobs.submit(job).await?;
+            info!("{request_ctx}: queued proof job {id}");
+            Ok(Some(id))
+        }
+        _ => Ok(None),
+    }
+}
+
 pub async fn check_parsed_fasta<T: NucleotideLike>(
     request_id: &RequestId,
     fasta_file: FastaFile<DnaSequence<T>>,
@@ -305,6 +424,12 @@ pub async fn check_parsed_fasta<T: NucleotideLike>(
                 version_hint: config.synthclient_version_hint.to_owned(),
                 ets: config.ets.clone(),
                 server_version_handler: &config.server_version_handler,
+                #[cfg(feature = "zk")]
+                proving: ProvingConfig {
+                    mode: config.proof_mode,
+                    defer: config.proof_jobs.is_some(),
+                    chunk_size: config.proof_chunk_size,
+                },
             })
         },
         |err: &DoprfError| {
@@ -323,6 +448,23 @@ pub async fn check_parsed_fasta<T: NucleotideLike>(
         e
     })?;
 
+    // As with archiving, the screening stands without its proof
+    #[cfg(feature = "zk")]
+    let (proof_job_id, proof_warning) = match handle_proof(&request_ctx, config, output.proof).await
+    {
+        Ok(id) => (id, None),
+        Err(ProofQueueError::Busy) => {
+            warn!("{request_ctx}: proof queue is full, not proving");
+            (None, Some(ApiWarning::proof_queue_full()))
+        }
+        Err(e) => {
+            warn!("{request_ctx}: failed to queue proof job: {e}");
+            (None, None)
+        }
+    };
+    #[cfg(not(feature = "zk"))]
+    let (proof_job_id, proof_warning) = (None, None);
+
     let synthesis_permission = synthesis_permission::SynthesisPermission::merge(
         output
             .response
@@ -345,14 +487,19 @@ pub async fn check_parsed_fasta<T: NucleotideLike>(
             total.saturating_add(record.contents.len().try_into().unwrap_or(u64::MAX))
         });
         m.bp_counter.inc_by(total_bp);
+        m.keyserver_failovers.inc_by(output.failovers as u64);
     }
 
     use synthesis_permission::SynthesisPermission::Granted;
-    let warnings = match synthesis_permission {
+    let mut warnings = match synthesis_permission {
         Granted if output.too_short => vec![ApiWarning::too_short()],
         Granted if output.n_hashes == 0 => vec![ApiWarning::too_ambiguous()],
         _ => vec![],
     };
+    if !output.replaced_keyservers.is_empty() {
+        warnings.push(ApiWarning::keyservers_replaced(&output.replaced_keyservers));
+    }
+    warnings.extend(proof_warning);
 
     if let Some(m) = &config.metrics {
         m.hazards.inc_by(hits_by_record.len() as u64);
@@ -367,6 +514,8 @@ pub async fn check_parsed_fasta<T: NucleotideLike>(
             grouped_hits: debug_grouped_hits.unwrap_or_default(),
         }),
         provider_reference: config.provider_reference.clone(),
+        hdb_commitment: output.response.hdb_commitment,
+        proof_job_id,
     })
 }
 
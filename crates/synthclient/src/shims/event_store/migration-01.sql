@@ -0,0 +1,15 @@
+-- Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+-- SPDX-License-Identifier: MIT OR Apache-2.0
+
+CREATE TABLE proof_jobs(
+    id TEXT PRIMARY KEY UNIQUE NOT NULL,
+    request_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    error TEXT,
+    -- the JSON encoded proof bundle, once proven
+    bundle TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+) STRICT;
+
+CREATE INDEX idx_proof_jobs_status ON proof_jobs(status);
@@ -4,13 +4,20 @@
 use std::path::Path;
 
 pub use persistence::Connection;
-use persistence::{params, rusqlite::OptionalExtension, tokio_rusqlite, Migrations, OpenError, M};
+use persistence::rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
+use persistence::rusqlite::{OptionalExtension, ToSql};
+use persistence::{params, rusqlite, tokio_rusqlite, Migrations, OpenError, SqlOffsetDateTime, M};
+
+use crate::api::ProofJobStatus;
 
 pub async fn open_db(path: impl AsRef<Path>) -> Result<Connection, OpenError> {
     persistence::open_db(
         path,
         // do not modify these migrations, instead create a new migration
-        Migrations::from_iter([M::up(include_str!("migration-00.sql"))]),
+        Migrations::from_iter([
+            M::up(include_str!("migration-00.sql")),
+            M::up(include_str!("migration-01.sql")),
+        ]),
     )
     .await
 }
@@ -55,6 +62,137 @@ pub async fn query_last_server_version(
         .await?;
     Ok(server_version)
 }
+impl ToSql for ProofJobStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'static>> {
+        Ok(ToSqlOutput::from(match self {
+            ProofJobStatus::Queued => "queued",
+            ProofJobStatus::Proving => "proving",
+            ProofJobStatus::Proven => "proven",
+            ProofJobStatus::Failed => "failed",
+        }))
+    }
+}
+
+impl FromSql for ProofJobStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        match s {
+            "queued" => Ok(ProofJobStatus::Queued),
+            "proving" => Ok(ProofJobStatus::Proving),
+            "proven" => Ok(ProofJobStatus::Proven),
+            "failed" => Ok(ProofJobStatus::Failed),
+            _ => Err(FromSqlError::Other(
+                format!("Unknown proof job status str: {s:?}").into(),
+            )),
+        }
+    }
+}
+
+/// A proof job as stored, with its bundle still JSON encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofJobRecord {
+    pub request_id: String,
+    pub status: ProofJobStatus,
+    pub error: Option<String>,
+    pub bundle: Option<String>,
+}
+
+pub async fn insert_proof_job(
+    conn: &Connection,
+    id: String,
+    request_id: String,
+) -> Result<(), tokio_rusqlite::Error> {
+    let now = SqlOffsetDateTime::now_utc();
+    conn.call(move |conn| {
+        conn.execute(
+            r#"
+            INSERT INTO proof_jobs (id, request_id, status, created_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?4);
+            "#,
+            params![id, request_id, ProofJobStatus::Queued, now],
+        )?;
+        Ok(())
+    })
+    .await?;
+    Ok(())
+}
+
+pub async fn update_proof_job(
+    conn: &Connection,
+    id: String,
+    status: ProofJobStatus,
+    error: Option<String>,
+    bundle: Option<String>,
+) -> Result<(), tokio_rusqlite::Error> {
+    let now = SqlOffsetDateTime::now_utc();
+    conn.call(move |conn| {
+        conn.execute(
+            r#"
+            UPDATE proof_jobs SET status = ?2, error = ?3, bundle = ?4, updated_at = ?5
+            WHERE id = ?1;
+            "#,
+            params![id, status, error, bundle, now],
+        )?;
+        Ok(())
+    })
+    .await?;
+    Ok(())
+}
+
+pub async fn query_proof_job(
+    conn: &Connection,
+    id: String,
+) -> Result<Option<ProofJobRecord>, tokio_rusqlite::Error> {
+    let record = conn
+        .call(move |conn| {
+            let record = conn
+                .query_row(
+                    r#"
+                    SELECT request_id, status, error, bundle FROM proof_jobs
+                    WHERE id = ?1;
+                    "#,
+                    params![id],
+                    |row| {
+                        Ok(ProofJobRecord {
+                            request_id: row.get(0)?,
+                            status: row.get(1)?,
+                            error: row.get(2)?,
+                            bundle: row.get(3)?,
+                        })
+                    },
+                )
+                .optional()?;
+            Ok(record)
+        })
+        .await?;
+    Ok(record)
+}
+
+/// Fails every job that was queued or proving, returning how many there were. Unfinished jobs
+/// are only held in memory, so none survive a restart.
+pub async fn fail_unfinished_proof_jobs(conn: &Connection) -> Result<usize, tokio_rusqlite::Error> {
+    let now = SqlOffsetDateTime::now_utc();
+    let failed = conn
+        .call(move |conn| {
+            let failed = conn.execute(
+                r#"
+                UPDATE proof_jobs SET status = ?1, error = ?2, updated_at = ?3
+                WHERE status IN (?4, ?5);
+                "#,
+                params![
+                    ProofJobStatus::Failed,
+                    "synthclient restarted before the proof finished",
+                    now,
+                    ProofJobStatus::Queued,
+                    ProofJobStatus::Proving,
+                ],
+            )?;
+            Ok(failed)
+        })
+        .await?;
+    Ok(failed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,6 +236,66 @@ mod tests {
         assert_eq!(result, Some(updated_version));
     }
 
+    #[tokio::test]
+    async fn test_proof_job_lifecycle() {
+        let conn = open_db(":memory:").await.unwrap();
+
+        let id = "job".to_string();
+        insert_proof_job(&conn, id.clone(), "request".to_string())
+            .await
+            .unwrap();
+        let record = query_proof_job(&conn, id.clone()).await.unwrap().unwrap();
+        assert_eq!(record.request_id, "request");
+        assert_eq!(record.status, ProofJobStatus::Queued);
+
+        update_proof_job(
+            &conn,
+            id.clone(),
+            ProofJobStatus::Proven,
+            None,
+            Some("{}".to_string()),
+        )
+        .await
+        .unwrap();
+        let record = query_proof_job(&conn, id.clone()).await.unwrap().unwrap();
+        assert_eq!(record.status, ProofJobStatus::Proven);
+        assert_eq!(record.bundle.as_deref(), Some("{}"));
+
+        assert_eq!(
+            query_proof_job(&conn, "nonexistent".to_string())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fail_unfinished_proof_jobs() {
+        let conn = open_db(":memory:").await.unwrap();
+
+        for id in ["queued", "proving", "proven"] {
+            insert_proof_job(&conn, id.to_string(), "request".to_string())
+                .await
+                .unwrap();
+        }
+        update_proof_job(&conn, "proving".to_string(), ProofJobStatus::Proving, None, None)
+            .await
+            .unwrap();
+        update_proof_job(&conn, "proven".to_string(), ProofJobStatus::Proven, None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(fail_unfinished_proof_jobs(&conn).await.unwrap(), 2);
+        for (id, status) in [
+            ("queued", ProofJobStatus::Failed),
+            ("proving", ProofJobStatus::Failed),
+            ("proven", ProofJobStatus::Proven),
+        ] {
+            let record = query_proof_job(&conn, id.to_string()).await.unwrap().unwrap();
+            assert_eq!(record.status, status);
+        }
+    }
+
     #[tokio::test]
     async fn test_query_non_existent_domain() {
         let conn = open_db(":memory:").await.unwrap();
@@ -3,6 +3,8 @@
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod event_store;
+#[cfg(all(not(target_arch = "wasm32"), feature = "zk"))]
+pub mod proof_queue;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod server;
 
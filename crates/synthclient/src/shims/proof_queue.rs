@@ -0,0 +1,162 @@
+// Copyright 2021-2024 SecureDNA Stiftung (SecureDNA Foundation) <licensing@securedna.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! A pool of workers proving screenings that were answered without waiting for a proof.
+//!
+//! Jobs are recorded in the event store when queued, and again as they start and finish, so
+//! `/v1/proof/{id}` can report on them. The jobs themselves stay in memory, so only a bounded
+//! number may wait for a worker, and any left unfinished on restart are recorded as failed.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use doprf::prf::ProofMode;
+use doprf::proof_bundle::BundleFormat;
+use doprf_client::proof_job::ProofJob;
+use shared_types::metrics::SynthClientMetrics;
+
+use crate::api::ProofJobStatus;
+use crate::parsefasta::{write_proof_bundle, ProofJobSubmitter, ProofQueueError};
+use crate::shims::event_store::{self, Connection};
+
+type Queued = (String, ProofJob);
+
+pub struct ProofQueue {
+    connection: Arc<Connection>,
+    sender: mpsc::Sender<Queued>,
+}
+
+impl ProofQueue {
+    /// Starts `workers` workers, each proving one job at a time in `proof_mode`, with room for
+    /// `queue_size` jobs to wait for them. Finished bundles are also archived to `bundle_dir`, if
+    /// given, and counted in `metrics`.
+    ///
+    /// The workers stop once the queue is dropped and they have proven every job left in it.
+    pub fn start(
+        connection: Arc<Connection>,
+        workers: usize,
+        queue_size: usize,
+        proof_mode: ProofMode,
+        bundle_dir: Option<PathBuf>,
+        bundle_format: BundleFormat,
+        metrics: Option<Arc<SynthClientMetrics>>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(queue_size);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..workers {
+            tokio::spawn(work(
+                connection.clone(),
+                receiver.clone(),
+                proof_mode,
+                bundle_dir.clone(),
+                bundle_format,
+                metrics.clone(),
+            ));
+        }
+        Self { connection, sender }
+    }
+
+    /// Records `job` as queued and hands it to the workers, returning its id. Fails with
+    /// [`ProofQueueError::Busy`] rather than waiting if the queue is full.
+    pub async fn submit(&self, job: ProofJob) -> Result<String, ProofQueueError> {
+        // Claim a place in the queue first, so a full queue records nothing
+        let permit = self.sender.try_reserve().map_err(|e| match e {
+            TrySendError::Full(()) => ProofQueueError::Busy,
+            TrySendError::Closed(()) => anyhow::anyhow!("proof workers have stopped").into(),
+        })?;
+        let id = Uuid::new_v4().to_string();
+        event_store::insert_proof_job(&self.connection, id.clone(), job.request_id().to_owned())
+            .await
+            .context("recording proof job")?;
+        permit.send((id.clone(), job));
+        Ok(id)
+    }
+
+    pub fn submitter(self: &Arc<Self>) -> ProofJobSubmitter {
+        let queue = self.clone();
+        ProofJobSubmitter::new(Box::new(move |job| {
+            let queue = queue.clone();
+            Box::pin(async move { queue.submit(job).await })
+        }))
+    }
+}
+
+async fn work(
+    connection: Arc<Connection>,
+    receiver: Arc<Mutex<mpsc::Receiver<Queued>>>,
+    proof_mode: ProofMode,
+    bundle_dir: Option<PathBuf>,
+    bundle_format: BundleFormat,
+    metrics: Option<Arc<SynthClientMetrics>>,
+) {
+    loop {
+        // Only one idle worker waits on the channel at a time
+        let Some((id, job)) = receiver.lock().await.recv().await else {
+            return;
+        };
+        let request_id = job.request_id().to_owned();
+        info!("{request_id}: proving job {id}");
+        set_status(&connection, &id, ProofJobStatus::Proving, None, None).await;
+
+        let proven =
+            tokio::task::spawn_blocking(move || job.prove(proof_mode.backend().as_ref())).await;
+        let bundle = match proven {
+            Ok(Ok(bundle)) => {
+                if let Some(m) = &metrics {
+                    m.proofs_generated.inc();
+                }
+                bundle
+            }
+            Ok(Err(e)) => {
+                warn!("{request_id}: proof job {id} failed: {e}");
+                set_status(&connection, &id, ProofJobStatus::Failed, Some(e.to_string()), None)
+                    .await;
+                continue;
+            }
+            Err(e) => {
+                error!("{request_id}: proof job {id} panicked: {e}");
+                let error = "the prover panicked".to_owned();
+                set_status(&connection, &id, ProofJobStatus::Failed, Some(error), None).await;
+                continue;
+            }
+        };
+
+        if let Some(dir) = &bundle_dir {
+            if let Err(e) = write_proof_bundle(dir, &bundle, bundle_format) {
+                warn!("{request_id}: failed to archive proof bundle: {e}");
+            }
+        }
+        // Bundles are stored as JSON, which is also how they are served
+        match serde_json::to_string(&bundle) {
+            Ok(json) => {
+                info!("{request_id}: proved job {id}");
+                set_status(&connection, &id, ProofJobStatus::Proven, None, Some(json)).await;
+            }
+            Err(e) => {
+                error!("{request_id}: failed to encode proof bundle for job {id}: {e}");
+                let error = "the proof bundle could not be stored".to_owned();
+                set_status(&connection, &id, ProofJobStatus::Failed, Some(error), None).await;
+            }
+        }
+    }
+}
+
+async fn set_status(
+    connection: &Connection,
+    id: &str,
+    status: ProofJobStatus,
+    error: Option<String>,
+    bundle: Option<String>,
+) {
+    if let Err(e) =
+        event_store::update_proof_job(connection, id.to_owned(), status, error, bundle).await
+    {
+        error!("failed to record proof job {id} as {status:?}: {e}");
+    }
+}
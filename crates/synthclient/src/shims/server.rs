@@ -22,6 +22,8 @@ use tokio::sync::Mutex;
 use tracing::{error, info};
 
 use doprf::party::KeyserverId;
+#[cfg(feature = "zk")]
+use doprf::prf::ProofMode;
 use doprf_client::server_selection::ServerSelector;
 use doprf_client::server_version_handler::LastServerVersionHandler;
 use minhttp::error::ErrWrapper;
@@ -35,14 +37,18 @@ use shared_types::metrics::{get_metrics_output, SynthClientMetrics};
 use shared_types::requests::RequestId;
 use shared_types::server_versions::{HdbVersion, KeyserverVersion};
 
+#[cfg(feature = "zk")]
+use crate::api::ProofJobResponse;
 use crate::api::{
-    ApiError, ApiResponse, CheckFastaRequest, CheckNcbiRequest, RequestCommon, SynthesisPermission,
-    VersionInfo,
+    ApiError, ApiResponse, CheckFastaRequest, CheckNcbiRequest, RequestCommon,
+    SynthesisPermission, VersionInfo,
 };
 use crate::ncbi::download_fasta_by_acc_number;
 use crate::parsefasta::{check_fasta, CheckerConfiguration, CurrentSystemLoadTracker};
 use crate::rate_limiter::{RateLimiter, SystemTimeHourProvider};
 
+#[cfg(feature = "zk")]
+use crate::shims::proof_queue::ProofQueue;
 use crate::shims::recaptcha::validate_recaptcha;
 use crate::shims::server_selection::initialize_server_selector;
 use crate::shims::types::{Config, SynthClientState};
@@ -96,6 +102,9 @@ async fn reconfigure(
 
     let synthclient_version = securedna_versioning::version::get_version();
 
+    #[cfg(feature = "zk")]
+    validate_proof_config(&app_cfg)?;
+
     let persistence_connection = if let Some(prev_state) = prev_state {
         if app_cfg.event_store_path != prev_state.app_cfg.event_store_path {
             return Err(anyhow::anyhow!(
@@ -110,9 +119,29 @@ async fn reconfigure(
         let connection = crate::shims::event_store::open_db(&app_cfg.event_store_path)
             .await
             .context("opening event store db")?;
+        let unfinished = crate::shims::event_store::fail_unfinished_proof_jobs(&connection)
+            .await
+            .context("failing unfinished proof jobs")?;
+        if unfinished > 0 {
+            info!("Failed {unfinished} proof jobs left unfinished by a previous run");
+        }
         Arc::new(connection)
     };
 
+    // A previous queue keeps proving what it has already been given until it is dropped
+    #[cfg(feature = "zk")]
+    let proof_queue = (app_cfg.proof_workers > 0).then(|| {
+        Arc::new(ProofQueue::start(
+            persistence_connection.clone(),
+            app_cfg.proof_workers,
+            app_cfg.proof_queue_size,
+            app_cfg.proof_mode,
+            app_cfg.proof_bundle_dir.clone(),
+            app_cfg.proof_bundle_format,
+            metrics.clone(),
+        ))
+    });
+
     Ok(Arc::new(SynthClientState {
         app_cfg,
         is_serving_https: server_cfg.main.tls_config.is_some(),
@@ -123,9 +152,31 @@ async fn reconfigure(
         certs,
         synthclient_version,
         persistence_connection,
+        #[cfg(feature = "zk")]
+        proof_queue,
     }))
 }
 
+#[cfg(feature = "zk")]
+fn validate_proof_config(app_cfg: &Config) -> anyhow::Result<()> {
+    if app_cfg.proof_workers > 0 && app_cfg.proof_mode == ProofMode::Execute {
+        return Err(anyhow::anyhow!(
+            "proof_workers requires a proof_mode that produces proofs, but found {:?}",
+            app_cfg.proof_mode,
+        ));
+    }
+    if app_cfg.proof_workers > 0 && app_cfg.proof_queue_size == 0 {
+        return Err(anyhow::anyhow!("proof_queue_size must be nonzero"));
+    }
+    if !app_cfg.proof_chunk_size.is_power_of_two() {
+        return Err(anyhow::anyhow!(
+            "proof_chunk_size must be a power of two, but found {}",
+            app_cfg.proof_chunk_size,
+        ));
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ScreenSource {
     Fasta,
@@ -152,6 +203,9 @@ impl ScreenSource {
     }
 }
 
+#[cfg(feature = "zk")]
+const PROOF_PATH_PREFIX: &str = "/v1/proof/";
+
 async fn respond(
     sc_state: Arc<SynthClientState>,
     peer: SocketAddr,
@@ -170,6 +224,14 @@ async fn respond(
         (Method::OPTIONS, _, _) => response::empty(),
         (Method::GET, _, "/version") => query_server_version(&sc_state).await,
         (Method::GET, _, "/") => index(&sc_state, request),
+        #[cfg(feature = "zk")]
+        (Method::GET, _, path) if path.starts_with(PROOF_PATH_PREFIX) => {
+            let id = &path[PROOF_PATH_PREFIX.len()..];
+            match query_proof_job(&sc_state, id).await {
+                Ok(proof_job) => json_response(StatusCode::OK, &proof_job),
+                Err(api_error) => json_api_error(api_error, None),
+            }
+        }
         (Method::POST, Some(source), _) => {
             let mut provider_reference: Option<String> = None;
             match screen(source, &sc_state, real_ip, request, &mut provider_reference).await {
@@ -380,6 +442,16 @@ async fn screen(
         synthclient_version_hint: &state.synthclient_version,
         ets,
         server_version_handler,
+        #[cfg(feature = "zk")]
+        proof_mode: state.app_cfg.proof_mode,
+        #[cfg(feature = "zk")]
+        proof_bundle_dir: state.app_cfg.proof_bundle_dir.clone(),
+        #[cfg(feature = "zk")]
+        proof_bundle_format: state.app_cfg.proof_bundle_format,
+        #[cfg(feature = "zk")]
+        proof_chunk_size: state.app_cfg.proof_chunk_size,
+        #[cfg(feature = "zk")]
+        proof_jobs: state.proof_queue.as_ref().map(ProofQueue::submitter),
     };
 
     let api_response = check_fasta::<NucleotideAmbiguous>(&request_id, sequence, &config).await?;
@@ -392,6 +464,34 @@ async fn screen(
     Ok(api_response)
 }
 
+#[cfg(feature = "zk")]
+async fn query_proof_job(state: &SynthClientState, id: &str) -> Result<ProofJobResponse, ApiError> {
+    let not_found = || ApiError::not_found(format!("{PROOF_PATH_PREFIX}{id}"));
+    let record =
+        crate::shims::event_store::query_proof_job(&state.persistence_connection, id.to_owned())
+            .await
+            .map_err(|e| {
+                error!("failed to query proof job {id}: {e}");
+                ApiError::generic_internal_server_error()
+            })?
+            .ok_or_else(not_found)?;
+    let bundle = record
+        .bundle
+        .map(|json| serde_json::from_str(&json))
+        .transpose()
+        .map_err(|e| {
+            error!("failed to decode stored proof bundle for job {id}: {e}");
+            ApiError::generic_internal_server_error()
+        })?;
+    Ok(ProofJobResponse {
+        id: id.to_owned(),
+        request_id: record.request_id,
+        status: record.status,
+        error: record.error,
+        bundle,
+    })
+}
+
 async fn query_server_version(state: &SynthClientState) -> GenericResponse {
     let synthclient_version = get_version();
     let hdb_version = get_hdb_version(state.server_selector.clone(), state.app_cfg.use_http).await;
@@ -466,8 +566,12 @@ async fn get_keyserver_versions(
 }
 
 fn json_api_response(status_code: StatusCode, api_response: ApiResponse) -> GenericResponse {
+    json_response(status_code, &api_response)
+}
+
+fn json_response(status_code: StatusCode, body: &impl serde::Serialize) -> GenericResponse {
     // This serialization can't fail.
-    let body = serde_json::to_string(&api_response).unwrap();
+    let body = serde_json::to_string(body).unwrap();
     response::json(status_code, body)
 }
 
@@ -484,6 +588,8 @@ fn json_api_error(api_error: ApiError, provider_reference: Option<String>) -> Ge
         warnings: vec![],
         errors: vec![api_error],
         debug_info: None,
+        hdb_commitment: None,
+        proof_job_id: None,
     };
 
     json_api_response(status_code, api_response)
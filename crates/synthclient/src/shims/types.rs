@@ -16,6 +16,14 @@ use url::Url;
 use crate::parsefasta::{CurrentSystemLoadTracker, LimitConfiguration};
 use crate::rate_limiter::{RateLimiter, SystemTimeHourProvider};
 use crate::shims::event_store::Connection;
+#[cfg(feature = "zk")]
+use crate::shims::proof_queue::ProofQueue;
+#[cfg(feature = "zk")]
+use doprf::prf::ProofMode;
+#[cfg(feature = "zk")]
+use doprf::proof_bundle::BundleFormat;
+#[cfg(feature = "zk")]
+use doprf::proof_inputs::DEFAULT_CHUNK_SIZE;
 use doprf_client::server_selection::{ServerEnumerationSource, ServerSelector};
 use minhttp::mpserver::{cli::ServerConfigSource, traits::RelativeConfig};
 use scep_client_helpers::ClientCerts;
@@ -124,6 +132,66 @@ pub struct Config {
      )]
     #[serde(default = "Config::default_event_store_path")]
     pub event_store_path: PathBuf,
+
+    #[cfg(feature = "zk")]
+    #[clap(
+        long,
+        value_enum,
+        help = "How screenings are proven to the HDB: `execute` runs the hash and checksum programs without proving, `mock` uses SP1's mock prover, and `compressed` generates real proofs (honoring SP1_PROVER).",
+        env = "SECUREDNA_SYNTHCLIENT_PROOF_MODE",
+        default_value_t = ProofMode::default(),
+    )]
+    #[serde(default)]
+    pub proof_mode: ProofMode,
+
+    #[cfg(feature = "zk")]
+    #[clap(
+        long,
+        help = "Directory to archive a proof bundle in for each proven screening, named by request id. Check bundles with `zkverify`.",
+        env = "SECUREDNA_SYNTHCLIENT_PROOF_BUNDLE_DIR"
+    )]
+    pub proof_bundle_dir: Option<PathBuf>,
+
+    #[cfg(feature = "zk")]
+    #[clap(
+        long,
+        value_enum,
+        help = "Format of archived proof bundles",
+        env = "SECUREDNA_SYNTHCLIENT_PROOF_BUNDLE_FORMAT",
+        default_value_t = BundleFormat::default(),
+    )]
+    #[serde(default)]
+    pub proof_bundle_format: BundleFormat,
+
+    #[cfg(feature = "zk")]
+    #[clap(
+        long,
+        help = "Number of workers proving screenings in the background. If nonzero, screenings are answered without waiting for their proof, which can then be fetched from `/v1/proof/{id}`. Requires a proof mode other than `execute`.",
+        env = "SECUREDNA_SYNTHCLIENT_PROOF_WORKERS",
+        default_value_t = 0
+    )]
+    #[serde(default)]
+    pub proof_workers: usize,
+
+    #[cfg(feature = "zk")]
+    #[clap(
+        long,
+        help = "Number of screenings that may wait for a proof worker. Once this many are waiting, further screenings are answered with a `proof_queue_full` warning and no proof.",
+        env = "SECUREDNA_SYNTHCLIENT_PROOF_QUEUE_SIZE",
+        default_value_t = Config::default_proof_queue_size()
+    )]
+    #[serde(default = "Config::default_proof_queue_size")]
+    pub proof_queue_size: usize,
+
+    #[cfg(feature = "zk")]
+    #[clap(
+        long,
+        help = "Number of windows each hash proof covers. Longer orders are proven in several chunks that are aggregated into one proof. Must be a power of two.",
+        env = "SECUREDNA_SYNTHCLIENT_PROOF_CHUNK_SIZE",
+        default_value_t = DEFAULT_CHUNK_SIZE
+    )]
+    #[serde(default = "Config::default_proof_chunk_size")]
+    pub proof_chunk_size: usize,
 }
 
 impl Config {
@@ -149,6 +217,16 @@ impl Config {
         100000
     }
 
+    #[cfg(feature = "zk")]
+    fn default_proof_queue_size() -> usize {
+        64
+    }
+
+    #[cfg(feature = "zk")]
+    fn default_proof_chunk_size() -> usize {
+        DEFAULT_CHUNK_SIZE
+    }
+
     fn default_event_store_path() -> PathBuf {
         ":memory:".into()
     }
@@ -161,6 +239,10 @@ impl RelativeConfig for Config {
         if self.event_store_path != Path::new(":memory:") {
             self.event_store_path = base.join(self.event_store_path);
         }
+        #[cfg(feature = "zk")]
+        {
+            self.proof_bundle_dir = self.proof_bundle_dir.map(|dir| base.join(dir));
+        }
         self
     }
 }
@@ -396,6 +478,9 @@ pub struct SynthClientState {
     /// version string returned from /version and passed to doprf_client to identify us
     pub synthclient_version: String,
     pub persistence_connection: Arc<Connection>,
+    /// Proves screenings in the background, if `proof_workers` is nonzero.
+    #[cfg(feature = "zk")]
+    pub proof_queue: Option<Arc<ProofQueue>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
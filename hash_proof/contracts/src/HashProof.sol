@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import {ISP1Verifier} from "@sp1-contracts/ISP1Verifier.sol";
+
+/// @notice The public values committed by the hash_proof program. Must match `HashPublicValues`
+///         in crates/doprf/src/public_values.rs.
+struct HashPublicValues {
+    // The salted Merkle root over the order's windows.
+    bytes32 windowRoot;
+    // The compressed query for each window, in window order.
+    bytes32[] queries;
+    // The hash of the concatenated queries, which the checksum is randomized by.
+    bytes32 randomModifier;
+    // The compressed sum of each window's hash point times its verification factor.
+    bytes32 checksumSum;
+}
+
+/// @title HashProof.
+/// @notice Verifies proofs that each window of an order was blinded into the committed query.
+contract HashProof {
+    /// @notice The address of the SP1 verifier contract.
+    /// @dev This can either be a specific SP1Verifier for a specific version, or the
+    ///      SP1VerifierGateway which can be used to verify proofs for any version of SP1.
+    ///      For the list of supported verifiers on each chain, see:
+    ///      https://github.com/succinctlabs/sp1-contracts/tree/main/contracts/deployments
+    address public verifier;
+
+    /// @notice The verification key for the hash_proof program.
+    bytes32 public hashProgramVKey;
+
+    constructor(address _verifier, bytes32 _hashProgramVKey) {
+        verifier = _verifier;
+        hashProgramVKey = _hashProgramVKey;
+    }
+
+    /// @notice The entrypoint for verifying a proof of the hash_proof program.
+    /// @param _publicValues The encoded public values.
+    /// @param _proofBytes The encoded proof.
+    function verifyHashProof(bytes calldata _publicValues, bytes calldata _proofBytes)
+        public
+        view
+        returns (HashPublicValues memory)
+    {
+        ISP1Verifier(verifier).verifyProof(hashProgramVKey, _publicValues, _proofBytes);
+        return abi.decode(_publicValues, (HashPublicValues));
+    }
+}
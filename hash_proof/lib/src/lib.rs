@@ -1,22 +1,49 @@
-use alloy_sol_types::sol;
+//! The public values committed by the hash_proof program.
 
-sol! {
-    /// The public values encoded as a struct that can be easily deserialized inside Solidity.
-    struct PublicValuesStruct {
-        uint32 n;
-        uint32 a;
-        uint32 b;
+pub use doprf::public_values::HashPublicValues;
+
+#[cfg(test)]
+mod tests {
+    use alloy_sol_types::SolType;
+
+    use super::*;
+
+    /// The encoding of [`sample`], which `contracts/test/HashProof.t.sol` decodes too.
+    const ENCODED: &str = concat!(
+        "0000000000000000000000000000000000000000000000000000000000000020",
+        "1111111111111111111111111111111111111111111111111111111111111111",
+        "0000000000000000000000000000000000000000000000000000000000000080",
+        "3333333333333333333333333333333333333333333333333333333333333333",
+        "4444444444444444444444444444444444444444444444444444444444444444",
+        "0000000000000000000000000000000000000000000000000000000000000002",
+        "2222222222222222222222222222222222222222222222222222222222222222",
+        "2323232323232323232323232323232323232323232323232323232323232323",
+    );
+
+    fn sample() -> HashPublicValues {
+        HashPublicValues {
+            windowRoot: [0x11; 32].into(),
+            queries: vec![[0x22; 32].into(), [0x23; 32].into()],
+            randomModifier: [0x33; 32].into(),
+            checksumSum: [0x44; 32].into(),
+        }
+    }
+
+    #[test]
+    fn encodes_as_the_contract_decodes() {
+        assert_eq!(
+            hex::encode(HashPublicValues::abi_encode(&sample())),
+            ENCODED
+        );
     }
-}
 
-/// Compute the n'th fibonacci number (wrapping around on overflows), using normal Rust code.
-pub fn fibonacci(n: u32) -> (u32, u32) {
-    let mut a = 0u32;
-    let mut b = 1u32;
-    for _ in 0..n {
-        let c = a.wrapping_add(b);
-        a = b;
-        b = c;
+    #[test]
+    fn decodes_what_it_encodes() {
+        let encoded = hex::decode(ENCODED).unwrap();
+        assert_eq!(
+            HashPublicValues::abi_decode(&encoded, true).unwrap(),
+            sample()
+        );
+        assert!(HashPublicValues::abi_decode(&encoded[..encoded.len() - 32], true).is_err());
     }
-    (a, b)
 }
@@ -8,19 +8,28 @@ sp1_zkvm::entrypoint!(main);
 use sha3::Sha3_512;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::Identity;
 use alloy_sol_types::SolType;
-use fibonacci_lib::{fibonacci, PublicValuesStruct};
+use hash_proof_lib::HashPublicValues;
 use doprf::prf::Query;
+use doprf::window_commitment::{leaf_hash, merkle_root, SALT_SIZE};
 
 pub fn main() {
-    // Read in each byte array from the input until we reach the sentinel value.
-    loop {
+    // Read the per-order salt that the window commitment is computed under.
+    let salt = sp1_zkvm::io::read::<[u8; SALT_SIZE]>();
+    let mut window_leaves = Vec::new();
+    // The checksum inputs, accumulated over the same windows and queries.
+    let mut sum = RistrettoPoint::identity();
+    let mut concat_queries = Vec::new();
+    let mut queries = Vec::new();
+
+    // Read in exactly as many windows as the order has. Windows may be empty, so the count
+    // comes first rather than an end marker.
+    let window_count = sp1_zkvm::io::read::<u32>();
+    for _ in 0..window_count {
         let bytes = sp1_zkvm::io::read::<Vec<u8>>();
 
-        // Check for the sentinel, break the loop.
-        if bytes.is_empty() {
-            break;
-        }
+        window_leaves.push(leaf_hash(&salt, &bytes));
 
         // Hash the byte array directly to a RistrettoPoint.
         let hashed_point = RistrettoPoint::hash_from_bytes::<Sha3_512>(&bytes);
@@ -32,10 +41,22 @@ pub fn main() {
 
         let query = Query::from_rp(hashed_point * blinding_factor);
 
-        // Commit the compressed hash to the zkVM for public verification.
-        sp1_zkvm::io::commit::<Query>(&query);
+        // Read the window's verification factor, and add its share of the checksum sum
+        let verification_factor_bytes = sp1_zkvm::io::read::<[u8; 32]>();
+        let verification_factor = Scalar::from_canonical_bytes(verification_factor_bytes).expect("Invalid scalar bytes");
+        sum += hashed_point * verification_factor;
+        concat_queries.extend_from_slice(query.as_bytes());
+        queries.push((*query.as_bytes()).into());
     }
-    // Commit the sentinel value
-    let sentinel = Query::sentinel();
-    sp1_zkvm::io::commit::<Query>(&sentinel);
+
+    // Commit the queries along with the windows themselves, binding the proof to this order,
+    // and the inputs the checksum proof must have used, for the aggregation program to check.
+    let random_modifier = Scalar::hash_from_bytes::<Sha3_512>(&concat_queries);
+    let public_values = HashPublicValues {
+        windowRoot: merkle_root(&salt, window_leaves).into(),
+        queries,
+        randomModifier: random_modifier.to_bytes().into(),
+        checksumSum: sum.compress().to_bytes().into(),
+    };
+    sp1_zkvm::io::commit_slice(&HashPublicValues::abi_encode(&public_values));
 }
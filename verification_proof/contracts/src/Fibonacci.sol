@@ -1,44 +0,0 @@
-// SPDX-License-Identifier: MIT
-pragma solidity ^0.8.20;
-
-import {ISP1Verifier} from "@sp1-contracts/ISP1Verifier.sol";
-
-struct PublicValuesStruct {
-    uint32 n;
-    uint32 a;
-    uint32 b;
-}
-
-/// @title Fibonacci.
-/// @author Succinct Labs
-/// @notice This contract implements a simple example of verifying the proof of a computing a
-///         fibonacci number.
-contract Fibonacci {
-    /// @notice The address of the SP1 verifier contract.
-    /// @dev This can either be a specific SP1Verifier for a specific version, or the
-    ///      SP1VerifierGateway which can be used to verify proofs for any version of SP1.
-    ///      For the list of supported verifiers on each chain, see:
-    ///      https://github.com/succinctlabs/sp1-contracts/tree/main/contracts/deployments
-    address public verifier;
-
-    /// @notice The verification key for the fibonacci program.
-    bytes32 public fibonacciProgramVKey;
-
-    constructor(address _verifier, bytes32 _fibonacciProgramVKey) {
-        verifier = _verifier;
-        fibonacciProgramVKey = _fibonacciProgramVKey;
-    }
-
-    /// @notice The entrypoint for verifying the proof of a fibonacci number.
-    /// @param _proofBytes The encoded proof.
-    /// @param _publicValues The encoded public values.
-    function verifyFibonacciProof(bytes calldata _publicValues, bytes calldata _proofBytes)
-        public
-        view
-        returns (uint32, uint32, uint32)
-    {
-        ISP1Verifier(verifier).verifyProof(fibonacciProgramVKey, _publicValues, _proofBytes);
-        PublicValuesStruct memory publicValues = abi.decode(_publicValues, (PublicValuesStruct));
-        return (publicValues.n, publicValues.a, publicValues.b);
-    }
-}
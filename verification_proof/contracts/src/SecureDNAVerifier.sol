@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import {ISP1Verifier} from "@sp1-contracts/ISP1Verifier.sol";
+
+/// @notice The public values committed by the verification_proof program. Must match
+///         `ScreeningPublicValues` in crates/doprf/src/public_values.rs.
+struct ScreeningPublicValues {
+    // The vkey digests of the aggregated proofs: a hash proof for each chunk of the order, then
+    // the checksum proof.
+    bytes32[] subProofVkeys;
+    // The salted Merkle root over the order's windows.
+    bytes32 orderCommitment;
+    // The commitment to the HDB build the hashes were screened against.
+    bytes32 hdbCommitment;
+    // The hash of the active security key the responses were validated against.
+    bytes32 keyserverCommitmentHash;
+    uint32 windowCount;
+    // One of the VERDICT_* constants.
+    uint8 verdict;
+    // The number of windows found in the HDB, when the verdict is VERDICT_GRANTED or
+    // VERDICT_DENIED.
+    uint32 hitCount;
+    // How many of those hits were on reverse screened entries, which do not deny synthesis.
+    uint32 reverseScreenedHitCount;
+    // The keyservers responsible for a failed verdict.
+    uint32[] responsibleKeyservers;
+    // The concatenated encodings of the tagged hashes, when the verdict is VERDICT_HASHED.
+    bytes taggedHashes;
+}
+
+uint8 constant VERDICT_HASHED = 0;
+uint8 constant VERDICT_MALFORMED_RESPONSE = 1;
+uint8 constant VERDICT_WRONG_SIZE_RESPONSE = 2;
+uint8 constant VERDICT_MISSING_KEYSERVER_RESPONSE = 3;
+uint8 constant VERDICT_VALIDATION_FAILED = 4;
+// The hashes were screened against the HDB committed to by hdbCommitment inside the proof.
+uint8 constant VERDICT_GRANTED = 5;
+uint8 constant VERDICT_DENIED = 6;
+
+/// @title SecureDNAVerifier.
+/// @notice Verifies proofs that an order's windows were hashed, and the keyserver responses
+///         incorporated and validated, by the SecureDNA screening programs. Proofs with a
+///         VERDICT_GRANTED or VERDICT_DENIED verdict also attest to screening against an HDB.
+contract SecureDNAVerifier {
+    /// @notice The address of the SP1 verifier contract.
+    /// @dev This can either be a specific SP1Verifier for a specific version, or the
+    ///      SP1VerifierGateway which can be used to verify proofs for any version of SP1.
+    ///      For the list of supported verifiers on each chain, see:
+    ///      https://github.com/succinctlabs/sp1-contracts/tree/main/contracts/deployments
+    address public verifier;
+
+    /// @notice The verification key for the verification_proof program.
+    bytes32 public screeningProgramVKey;
+
+    /// @notice The vkey digest of the hash_proof program, which every aggregated proof but the
+    ///         last must be of.
+    bytes32 public hashProgramVKeyDigest;
+
+    /// @notice The vkey digest of the checksum_proof program, which the last aggregated proof
+    ///         must be of.
+    bytes32 public checksumProgramVKeyDigest;
+
+    /// @notice The proof aggregated proofs of programs other than the hash and checksum
+    ///         programs.
+    error UnexpectedSubProofs();
+
+    constructor(
+        address _verifier,
+        bytes32 _screeningProgramVKey,
+        bytes32 _hashProgramVKeyDigest,
+        bytes32 _checksumProgramVKeyDigest
+    ) {
+        verifier = _verifier;
+        screeningProgramVKey = _screeningProgramVKey;
+        hashProgramVKeyDigest = _hashProgramVKeyDigest;
+        checksumProgramVKeyDigest = _checksumProgramVKeyDigest;
+    }
+
+    /// @notice The entrypoint for verifying a proof of the verification_proof program.
+    /// @dev The verification_proof program only checks the aggregated proofs against the vkeys
+    ///      it is given, so they are pinned here, as the hdbserver pins them.
+    /// @param _publicValues The encoded public values.
+    /// @param _proofBytes The encoded proof.
+    function verifyScreeningProof(bytes calldata _publicValues, bytes calldata _proofBytes)
+        public
+        view
+        returns (ScreeningPublicValues memory)
+    {
+        ISP1Verifier(verifier).verifyProof(screeningProgramVKey, _publicValues, _proofBytes);
+        ScreeningPublicValues memory values = abi.decode(_publicValues, (ScreeningPublicValues));
+
+        // A hash proof for each chunk of the order, then the checksum proof
+        uint256 count = values.subProofVkeys.length;
+        if (count < 2 || values.subProofVkeys[count - 1] != checksumProgramVKeyDigest) {
+            revert UnexpectedSubProofs();
+        }
+        for (uint256 i = 0; i < count - 1; i++) {
+            if (values.subProofVkeys[i] != hashProgramVKeyDigest) {
+                revert UnexpectedSubProofs();
+            }
+        }
+        return values;
+    }
+}
@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+import {Test} from "forge-std/Test.sol";
+import {
+    SecureDNAVerifier,
+    ScreeningPublicValues,
+    VERDICT_HASHED,
+    VERDICT_VALIDATION_FAILED,
+    VERDICT_GRANTED
+} from "../src/SecureDNAVerifier.sol";
+import {SP1VerifierGateway} from "@sp1-contracts/SP1VerifierGateway.sol";
+
+contract SecureDNAVerifierTest is Test {
+    /// @dev The public values encoded by the tests in lib/src/lib.rs.
+    bytes constant PUBLIC_VALUES =
+        hex"0000000000000000000000000000000000000000000000000000000000000020"
+        hex"0000000000000000000000000000000000000000000000000000000000000140"
+        hex"1111111111111111111111111111111111111111111111111111111111111111"
+        hex"0000000000000000000000000000000000000000000000000000000000000000"
+        hex"6666666666666666666666666666666666666666666666666666666666666666"
+        hex"0000000000000000000000000000000000000000000000000000000000000002"
+        hex"0000000000000000000000000000000000000000000000000000000000000004"
+        hex"0000000000000000000000000000000000000000000000000000000000000000"
+        hex"0000000000000000000000000000000000000000000000000000000000000000"
+        hex"00000000000000000000000000000000000000000000000000000000000001a0"
+        hex"0000000000000000000000000000000000000000000000000000000000000200"
+        hex"0000000000000000000000000000000000000000000000000000000000000002"
+        hex"0000000100000002000000030000000400000005000000060000000700000008"
+        hex"000000090000000a0000000b0000000c0000000d0000000e0000000f00000010"
+        hex"0000000000000000000000000000000000000000000000000000000000000002"
+        hex"0000000000000000000000000000000000000000000000000000000000000001"
+        hex"0000000000000000000000000000000000000000000000000000000000000003"
+        hex"0000000000000000000000000000000000000000000000000000000000000000";
+
+    /// @dev The sub-proof vkey digests in PUBLIC_VALUES.
+    bytes32 constant HASH_VKEY_DIGEST =
+        hex"0000000100000002000000030000000400000005000000060000000700000008";
+    bytes32 constant CHECKSUM_VKEY_DIGEST =
+        hex"000000090000000a0000000b0000000c0000000d0000000e0000000f00000010";
+
+    address verifier;
+    SecureDNAVerifier public target;
+
+    function setUp() public {
+        verifier = address(new SP1VerifierGateway(address(1)));
+        target = new SecureDNAVerifier(verifier, bytes32(uint256(1)), HASH_VKEY_DIGEST, CHECKSUM_VKEY_DIGEST);
+    }
+
+    /// @dev The sub-proof vkey digests of an order proven in `chunks` hash proofs.
+    function subProofVkeys(uint256 chunks) internal pure returns (bytes32[] memory vkeys) {
+        vkeys = new bytes32[](chunks + 1);
+        for (uint256 i = 0; i < chunks; i++) {
+            vkeys[i] = HASH_VKEY_DIGEST;
+        }
+        vkeys[chunks] = CHECKSUM_VKEY_DIGEST;
+    }
+
+    /// @dev A bytes32 with every byte set to `b`.
+    function repeated(uint8 b) internal pure returns (bytes32) {
+        return bytes32(uint256(b) * (type(uint256).max / 0xff));
+    }
+
+    function test_DecodesPublicValues() public {
+        vm.mockCall(verifier, abi.encodeWithSelector(SP1VerifierGateway.verifyProof.selector), abi.encode(true));
+
+        ScreeningPublicValues memory values = target.verifyScreeningProof(PUBLIC_VALUES, new bytes(32));
+        assertEq(values.subProofVkeys.length, 2);
+        assertEq(values.subProofVkeys[0], HASH_VKEY_DIGEST);
+        assertEq(values.subProofVkeys[1], CHECKSUM_VKEY_DIGEST);
+        assertEq(values.orderCommitment, repeated(0x11));
+        assertEq(values.hdbCommitment, bytes32(0));
+        assertEq(values.keyserverCommitmentHash, repeated(0x66));
+        assertEq(uint256(values.windowCount), 2);
+        assertEq(uint256(values.verdict), uint256(VERDICT_VALIDATION_FAILED));
+        assertEq(uint256(values.hitCount), 0);
+        assertEq(uint256(values.reverseScreenedHitCount), 0);
+        assertEq(values.responsibleKeyservers.length, 2);
+        assertEq(uint256(values.responsibleKeyservers[0]), 1);
+        assertEq(uint256(values.responsibleKeyservers[1]), 3);
+        assertEq(values.taggedHashes.length, 0);
+    }
+
+    function test_RoundTripsHashedVerdict() public {
+        vm.mockCall(verifier, abi.encodeWithSelector(SP1VerifierGateway.verifyProof.selector), abi.encode(true));
+
+        ScreeningPublicValues memory hashed;
+        hashed.subProofVkeys = subProofVkeys(1);
+        hashed.orderCommitment = repeated(0x11);
+        hashed.windowCount = 1;
+        hashed.verdict = VERDICT_HASHED;
+        hashed.taggedHashes = new bytes(36);
+
+        ScreeningPublicValues memory values = target.verifyScreeningProof(abi.encode(hashed), new bytes(32));
+        assertEq(uint256(values.verdict), uint256(VERDICT_HASHED));
+        assertEq(values.orderCommitment, repeated(0x11));
+        assertEq(values.responsibleKeyservers.length, 0);
+        assertEq(values.taggedHashes.length, 36);
+    }
+
+    function test_RoundTripsGrantedVerdict() public {
+        vm.mockCall(verifier, abi.encodeWithSelector(SP1VerifierGateway.verifyProof.selector), abi.encode(true));
+
+        ScreeningPublicValues memory granted;
+        granted.subProofVkeys = subProofVkeys(3);
+        granted.orderCommitment = repeated(0x11);
+        granted.hdbCommitment = repeated(0x22);
+        granted.windowCount = 4;
+        granted.verdict = VERDICT_GRANTED;
+        granted.hitCount = 1;
+        granted.reverseScreenedHitCount = 1;
+
+        ScreeningPublicValues memory values = target.verifyScreeningProof(abi.encode(granted), new bytes(32));
+        assertEq(uint256(values.verdict), uint256(VERDICT_GRANTED));
+        assertEq(values.hdbCommitment, repeated(0x22));
+        assertEq(uint256(values.hitCount), 1);
+        assertEq(uint256(values.reverseScreenedHitCount), 1);
+        assertEq(values.taggedHashes.length, 0);
+    }
+
+    function test_RejectsUnexpectedSubProofs() public {
+        vm.mockCall(verifier, abi.encodeWithSelector(SP1VerifierGateway.verifyProof.selector), abi.encode(true));
+
+        ScreeningPublicValues memory values;
+        values.verdict = VERDICT_HASHED;
+
+        // No hash proof
+        values.subProofVkeys = subProofVkeys(0);
+        vm.expectRevert(SecureDNAVerifier.UnexpectedSubProofs.selector);
+        target.verifyScreeningProof(abi.encode(values), new bytes(32));
+
+        // A hash proof of another program
+        values.subProofVkeys = subProofVkeys(2);
+        values.subProofVkeys[1] = bytes32(uint256(7));
+        vm.expectRevert(SecureDNAVerifier.UnexpectedSubProofs.selector);
+        target.verifyScreeningProof(abi.encode(values), new bytes(32));
+
+        // The checksum proof in the wrong place
+        values.subProofVkeys = subProofVkeys(1);
+        values.subProofVkeys[1] = HASH_VKEY_DIGEST;
+        vm.expectRevert(SecureDNAVerifier.UnexpectedSubProofs.selector);
+        target.verifyScreeningProof(abi.encode(values), new bytes(32));
+    }
+
+    function testFail_InvalidSecureDNAVerifierProof() public view {
+        // Create a fake proof.
+        bytes memory fakeProof = new bytes(32);
+
+        target.verifyScreeningProof(PUBLIC_VALUES, fakeProof);
+    }
+}
@@ -1,22 +1,89 @@
-use alloy_sol_types::sol;
-
-sol! {
-    /// The public values encoded as a struct that can be easily deserialized inside Solidity.
-    struct PublicValuesStruct {
-        uint32 n;
-        uint32 a;
-        uint32 b;
+//! The public values committed by the verification_proof program.
+
+pub use doprf::proof_output::{ScreeningProofOutput, ScreeningStatus};
+pub use doprf::public_values::ScreeningPublicValues;
+
+#[cfg(test)]
+mod tests {
+    use alloy_sol_types::SolType;
+    use doprf::party::KeyserverId;
+    use doprf::public_values::{vkey_digest_to_bytes, VERDICT_VALIDATION_FAILED};
+
+    use super::*;
+
+    /// The encoding of [`sample`], which `contracts/test/SecureDNAVerifier.t.sol` decodes too.
+    const ENCODED: &str = concat!(
+        "0000000000000000000000000000000000000000000000000000000000000020",
+        "0000000000000000000000000000000000000000000000000000000000000140",
+        "1111111111111111111111111111111111111111111111111111111111111111",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "6666666666666666666666666666666666666666666666666666666666666666",
+        "0000000000000000000000000000000000000000000000000000000000000002",
+        "0000000000000000000000000000000000000000000000000000000000000004",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "00000000000000000000000000000000000000000000000000000000000001a0",
+        "0000000000000000000000000000000000000000000000000000000000000200",
+        "0000000000000000000000000000000000000000000000000000000000000002",
+        "0000000100000002000000030000000400000005000000060000000700000008",
+        "000000090000000a0000000b0000000c0000000d0000000e0000000f00000010",
+        "0000000000000000000000000000000000000000000000000000000000000002",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000000000000000000000000000003",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+    );
+
+    fn sample() -> ScreeningPublicValues {
+        ScreeningPublicValues {
+            subProofVkeys: vec![
+                vkey_digest_to_bytes(&[1, 2, 3, 4, 5, 6, 7, 8]).into(),
+                vkey_digest_to_bytes(&[9, 10, 11, 12, 13, 14, 15, 16]).into(),
+            ],
+            orderCommitment: [0x11; 32].into(),
+            hdbCommitment: [0; 32].into(),
+            keyserverCommitmentHash: [0x66; 32].into(),
+            windowCount: 2,
+            verdict: VERDICT_VALIDATION_FAILED,
+            hitCount: 0,
+            reverseScreenedHitCount: 0,
+            responsibleKeyservers: vec![1, 3],
+            taggedHashes: Vec::new().into(),
+        }
+    }
+
+    #[test]
+    fn encodes_as_the_contract_decodes() {
+        assert_eq!(
+            hex::encode(ScreeningPublicValues::abi_encode(&sample())),
+            ENCODED
+        );
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let encoded = hex::decode(ENCODED).unwrap();
+        assert_eq!(
+            ScreeningPublicValues::abi_decode(&encoded, true).unwrap(),
+            sample()
+        );
+        assert!(ScreeningPublicValues::abi_decode(&encoded[..encoded.len() - 32], true).is_err());
     }
-}
 
-/// Compute the n'th fibonacci number (wrapping around on overflows), using normal Rust code.
-pub fn fibonacci(n: u32) -> (u32, u32) {
-    let mut a = 0u32;
-    let mut b = 1u32;
-    for _ in 0..n {
-        let c = a.wrapping_add(b);
-        a = b;
-        b = c;
+    #[test]
+    fn decodes_as_screening_proof_output() {
+        let output = ScreeningProofOutput::decode(&hex::decode(ENCODED).unwrap()).unwrap();
+        assert_eq!(
+            output.sub_proof_vkeys,
+            [[1, 2, 3, 4, 5, 6, 7, 8], [9, 10, 11, 12, 13, 14, 15, 16]]
+        );
+        assert_eq!(output.window_count, 2);
+        assert_eq!(
+            output.status,
+            ScreeningStatus::ValidationFailed(vec![
+                KeyserverId::try_from(1).unwrap(),
+                KeyserverId::try_from(3).unwrap(),
+            ])
+        );
+        assert_eq!(hex::encode(output.encode()), ENCODED);
     }
-    (a, b)
 }
@@ -9,9 +9,12 @@ use futures::executor::block_on;
 use sha2::Digest;
 use sha2::Sha256;
 use alloy_sol_types::SolType;
-use doprf::prf::{SerializableQueryStateSet, HashPart};
+use doprf::prf::{SerializableQueryStateSet, HashPart, QueryStateSet};
+use doprf::proof_output::{ScreeningProofOutput, ScreeningStatus};
+use doprf::public_values::{ChecksumPublicValues, HashPublicValues};
 use doprf::party::KeyserverId;
 use doprf::tagged::TaggedHash;
+use hdb_acc::ScreeningWitnesses;
 use packed_ristretto::datatype::PackedRistrettos;
 use shared_types::requests::SerializableRequestContext;
 
@@ -30,46 +33,105 @@ pub fn main() -> () {
         sp1_zkvm::lib::verify::verify_sp1_proof(vkey, &public_values_digest.into());
     }
 
-    // Indicate that the proofs verified
-    sp1_zkvm::io::commit::<bool>(&true);
+    // A hash proof for each chunk of the order is followed by the checksum proof. Together the
+    // chunks must have committed the same checksum inputs as the checksum proof, or the checksum
+    // proves nothing about the hashed queries. Without the sub-proofs, nothing binds the
+    // screening to the order's windows, so they are required.
+    let (checksum_values, chunk_values) = public_values
+        .split_last()
+        .expect("Expected hash proofs and a checksum proof");
+    assert!(!chunk_values.is_empty(), "Expected hash proofs and a checksum proof");
+    let chunk_outputs: Vec<HashPublicValues> = chunk_values
+        .iter()
+        .map(|values| {
+            HashPublicValues::abi_decode(values, true).expect("Malformed hash proof public values")
+        })
+        .collect();
+    let hash_output = HashPublicValues::combine_chunks(&chunk_outputs)
+        .expect("Hash proof chunks do not combine");
+    let checksum_output = ChecksumPublicValues::abi_decode(checksum_values, true)
+        .expect("Malformed checksum proof public values");
+    assert_eq!(
+        hash_output.checksum_inputs(),
+        checksum_output.checksum_inputs(),
+        "Checksum proof was not computed from the hash proof's queries"
+    );
 
     // read the serializeed QueryStateSet and deserialized
     let serialize_querystate = sp1_zkvm::io::read::<SerializableQueryStateSet>();
-    let mut querystate = serialize_querystate.to_query_state_set();
+    let querystate = serialize_querystate.to_query_state_set();
+
+    let queries: Vec<[u8; 32]> = querystate.queries().map(|q| *q.as_bytes()).collect();
+    let (_, window_queries) = queries.split_last().expect("Empty query state set");
+    let keyserver_commitment_hash = querystate.randomized_target.commitment_hash();
+
+    // The responses must be incorporated into exactly the state that the sub-proofs attest to
+    let hash_queries: Vec<[u8; 32]> = hash_output.queries.iter().map(|q| q.0).collect();
+    assert_eq!(window_queries, &hash_queries[..], "Query state set does not match the hash proof");
+    assert_eq!(queries.last(), Some(&checksum_output.query.0), "Query state set does not match the checksum proof");
+    assert_eq!(
+        querystate.randomized_target.random_modifier.to_bytes(),
+        hash_output.randomModifier.0,
+        "Query state set's random modifier does not match the hash proof"
+    );
+    assert_eq!(
+        keyserver_commitment_hash,
+        checksum_output.keyserverCommitmentHash.0,
+        "Query state set's active security key does not match the checksum proof"
+    );
+    let commitment = querystate.order_commitment().expect("Query state set has no window commitment");
+    assert_eq!(commitment.root, hash_output.windowRoot.0, "Query state set's window commitment does not match the hash proof");
+    let order_commitment = hash_output.windowRoot.0;
+    let window_count = window_queries.len() as u32;
 
     let keyserver_responses = sp1_zkvm::io::read::<Vec<(KeyserverId, PackedRistrettos<HashPart>)>>();
     let request_ctx = sp1_zkvm::io::read::<SerializableRequestContext>().to_request_context();
+    // If given, the hashes are screened here rather than committed for the HDB to screen.
+    let hdb_witnesses = sp1_zkvm::io::read::<Option<ScreeningWitnesses>>();
 
-    // Replicating incorporate_responses_and_hash in crates/doprf_client/src/operations.rs
-    for (id, ks_pr) in keyserver_responses.into_iter() {
-        // Handle error return type to avoid '?'
-        let parts = match ks_pr.iter_decoded().collect::<Result<Vec<HashPart>, _>>() {
-            Ok(p) => p,
-            Err(e) => {
-                println!("Error decoding parts: {:?}", e);
-                return;
+    // Commit the outcome. Misbehaving keyservers are reported rather than aborting, so that the
+    // client can prove which keyservers to blame.
+    let mut hdb_commitment = [0; 32];
+    let status = match incorporate_responses_and_hash(querystate, keyserver_responses) {
+        Ok(hashes) => match hdb_witnesses {
+            Some(screening) => {
+                let hashes: Vec<[u8; 32]> = hashes.iter().map(|h| (&h.hash).into()).collect();
+                let hits = screening.hits(&hashes).expect("Invalid HDB witnesses");
+                hdb_commitment = screening.commitment.0;
+                ScreeningStatus::Screened(hits)
             }
+            None => ScreeningStatus::from_hashes(hashes),
+        },
+        Err(status) => status,
+    };
+    let output = ScreeningProofOutput {
+        sub_proof_vkeys: vkeys,
+        order_commitment,
+        hdb_commitment,
+        keyserver_commitment_hash,
+        window_count,
+        status,
+    };
+    sp1_zkvm::io::commit_slice(&output.encode());
+}
+
+/// Replicates incorporate_responses_and_hash in crates/doprf_client/src/operations.rs, without
+/// the thread spawning. Fails with the status to commit if the responses do not validate.
+fn incorporate_responses_and_hash(
+    mut querystate: QueryStateSet,
+    keyserver_responses: Vec<(KeyserverId, PackedRistrettos<HashPart>)>,
+) -> Result<Vec<TaggedHash>, ScreeningStatus> {
+    for (id, ks_pr) in keyserver_responses.into_iter() {
+        let Ok(parts) = ks_pr.iter_decoded().collect::<Result<Vec<HashPart>, _>>() else {
+            return Err(ScreeningStatus::MalformedResponse(id));
         };
-        
-        // removes thread spawning
-        if let Err(e) = querystate.incorporate_response(id, &parts) {
-            println!("Error incorporating response: {:?}", e);
-            return;
+        if querystate.incorporate_response(id, &parts).is_err() {
+            return Err(ScreeningStatus::WrongSizeResponse(id));
         }
     }
 
-    // Computes final hash, subsequently calls randomized_target.validate_responses() where checksym is verified
-    // Removes thread spawning
-    let hash_values = match querystate.get_hash_values() {
-        Ok(values) => values,
-        Err(e) => {
-            println!("Error getting hash values: {:?}", e);
-            return;
-        }
-    };
-    let packed_hashes: PackedRistrettos<TaggedHash> = hash_values.into_iter().collect();
-
-    // Commit the final hash for comparison
-    sp1_zkvm::io::commit::<PackedRistrettos<TaggedHash>>(&packed_hashes);
+    // Computes final hash, subsequently calls randomized_target.validate_responses() where checksum is verified
+    querystate
+        .get_hash_values()
+        .map_err(ScreeningStatus::from_query_error)
 }
-    